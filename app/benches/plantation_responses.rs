@@ -0,0 +1,385 @@
+//! Compares building the plantation listing, every page of the farm-scoped
+//! search, and the regional occurrences of the plantations shown one query per
+//! item against the batched loaders the handlers call. The seeded data is
+//! deleted at the end.
+//!
+//! `cargo bench -p app --bench plantation_responses` with `BENCH_DATABASE_URL`
+//! set to a migrated database of its own, never the one on `DATABASE_URL`: a
+//! run that fails halfway leaves the seeded rows behind.
+//! `BENCH_PLANTATIONS`, `BENCH_OCCURRENCES` and `BENCH_SHOWS` change the seeded
+//! sizes and how many plantations are shown.
+
+use app::{
+  models::{
+    culture::Culture,
+    pathogenic::Pathogenic,
+    plantation::{Plantation, PlantationFilter},
+    plantation_pathogenic_occurrences::{PlantationPathogenicOccurrences, RegionalFilter},
+    stations::Station,
+  },
+  utils::{
+    database::DataBase,
+    pagination::{Page, MAX_LIMIT},
+  },
+};
+use sqlx::{types::Uuid, PgPool, Row};
+use std::time::{Duration, Instant};
+
+const RADIUS_KM: f64 = 100.0;
+
+struct Seeded {
+  user_id: Uuid,
+  farm_id: Uuid,
+  plantations: Vec<(Uuid, i64, i64)>,
+  culture_ids: Vec<i64>,
+  station_ids: Vec<i64>,
+  pathogenic_ids: Vec<i64>,
+}
+
+#[tokio::main]
+async fn main() {
+  dotenv::dotenv().ok();
+
+  let Ok(url) = std::env::var("BENCH_DATABASE_URL") else {
+    println!("BENCH_DATABASE_URL not set, skipping");
+    return;
+  };
+
+  if std::env::var("DATABASE_URL").is_ok_and(|app_url| app_url == url) {
+    println!("BENCH_DATABASE_URL is the app database, skipping");
+    return;
+  }
+
+  let plantations = env_size("BENCH_PLANTATIONS", 3000);
+  let occurrences = env_size("BENCH_OCCURRENCES", 5000);
+  let shows = env_size("BENCH_SHOWS", 100) as usize;
+
+  let db = DataBase {
+    pool: PgPool::connect(&url).await.unwrap(),
+  };
+  let filter = RegionalFilter::new(RADIUS_KM);
+
+  let started = Instant::now();
+  let seeded = seed(&db, plantations, occurrences).await;
+  println!(
+    "seeded {} plantations and {} occurrences in {:?}",
+    plantations,
+    occurrences,
+    started.elapsed()
+  );
+
+  let (queries, elapsed) = listing_per_item(&db, &seeded, &filter).await;
+  report("listing, per item", queries, elapsed);
+  let (queries, elapsed) = listing_batched(&db, &seeded, &filter).await;
+  report("listing, batched", queries, elapsed);
+
+  let shown = &seeded.plantations[..shows.min(seeded.plantations.len())];
+  println!("showing {} plantations", shown.len());
+
+  let (queries, elapsed) = regional_per_item(&db, shown, &filter).await;
+  report("regional occurrences, per item", queries, elapsed);
+  let (queries, elapsed) = regional_batched(&db, shown, &filter).await;
+  report("regional occurrences, batched", queries, elapsed);
+
+  clean(&db, &seeded).await;
+}
+
+fn env_size(name: &str, default: i64) -> i64 {
+  std::env::var(name)
+    .ok()
+    .and_then(|value| value.parse().ok())
+    .unwrap_or(default)
+}
+
+fn report(name: &str, queries: usize, elapsed: Duration) {
+  println!("{:<32} {:>6} queries {:>12.2?}", name, queries, elapsed);
+}
+
+/// Plantations on a farm of one user spread over about 200 km, with recent
+/// confirmed occurrences on random plantations
+async fn seed(db: &DataBase, plantations: i64, occurrences: i64) -> Seeded {
+  let user_id = Uuid::new_v4();
+  let farm_id = Uuid::new_v4();
+
+  sqlx::query("INSERT INTO farms (id, name, created_by) VALUES ($1, 'Bench', $2)")
+    .bind(farm_id)
+    .bind(user_id)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("INSERT INTO farm_members (farm_id, user_id, role) VALUES ($1, $2, 'owner')")
+    .bind(farm_id)
+    .bind(user_id)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+
+  let culture_ids: Vec<i64> = sqlx::query_scalar(
+    "INSERT INTO cultures (name, scientific_name)
+    SELECT 'Bench ' || i, 'Bench ' || i FROM generate_series(1, 20) i
+    RETURNING id",
+  )
+  .fetch_all(&db.pool)
+  .await
+  .unwrap();
+
+  let station_ids: Vec<i64> = sqlx::query_scalar(
+    "INSERT INTO stations (city, uf, location)
+    SELECT 'Bench ' || i, 'RS', point(-28 - random(), -52 - random())
+    FROM generate_series(1, 50) i
+    RETURNING id",
+  )
+  .fetch_all(&db.pool)
+  .await
+  .unwrap();
+
+  let pathogenic_ids: Vec<i64> = sqlx::query_scalar(
+    "INSERT INTO pathogenics (name, scientific_name)
+    SELECT 'Bench ' || i, 'Bench ' || i FROM generate_series(1, 10) i
+    RETURNING id",
+  )
+  .fetch_all(&db.pool)
+  .await
+  .unwrap();
+
+  let rows = sqlx::query(
+    "INSERT INTO plantations (id, user_id, farm_id, culture_id, station_id, alias, location, area,
+                              planting_date)
+    SELECT gen_random_uuid(),
+           $1,
+           $5,
+           ($2::bigint[])[1 + i % cardinality($2)],
+           ($3::bigint[])[1 + i % cardinality($3)],
+           'Bench ' || i,
+           point(-28 - random() * 2, -52 - random() * 2),
+           10,
+           NOW()
+    FROM generate_series(1, $4) i
+    RETURNING id, culture_id, station_id",
+  )
+  .bind(user_id)
+  .bind(&culture_ids)
+  .bind(&station_ids)
+  .bind(plantations)
+  .bind(farm_id)
+  .fetch_all(&db.pool)
+  .await
+  .unwrap();
+
+  let plantations = rows
+    .iter()
+    .map(|row| (row.get("id"), row.get("culture_id"), row.get("station_id")))
+    .collect::<Vec<(Uuid, i64, i64)>>();
+  let plantation_ids = plantations.iter().map(|p| p.0).collect::<Vec<_>>();
+
+  sqlx::query(
+    "INSERT INTO plantation_pathogenic_occurrences (id, user_id, plantation_id, pathogenic_id,
+                                                    occurrence_date, status)
+    SELECT gen_random_uuid(),
+           $1,
+           ($2::uuid[])[1 + floor(random() * cardinality($2))::int],
+           ($3::bigint[])[1 + i % cardinality($3)],
+           NOW() - random() * INTERVAL '12 hours',
+           'confirmed'
+    FROM generate_series(1, $4) i",
+  )
+  .bind(user_id)
+  .bind(&plantation_ids)
+  .bind(&pathogenic_ids)
+  .bind(occurrences)
+  .execute(&db.pool)
+  .await
+  .unwrap();
+
+  Seeded {
+    user_id,
+    farm_id,
+    plantations,
+    culture_ids,
+    station_ids,
+    pathogenic_ids,
+  }
+}
+
+/// Deletes everything `seed` inserted
+async fn clean(db: &DataBase, seeded: &Seeded) {
+  let plantation_ids = seeded.plantations.iter().map(|p| p.0).collect::<Vec<_>>();
+
+  sqlx::query("DELETE FROM plantation_pathogenic_occurrences WHERE plantation_id = ANY($1)")
+    .bind(&plantation_ids)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM plantations WHERE id = ANY($1)")
+    .bind(&plantation_ids)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM farm_members WHERE farm_id = $1")
+    .bind(seeded.farm_id)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM farms WHERE id = $1")
+    .bind(seeded.farm_id)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM cultures WHERE id = ANY($1)")
+    .bind(&seeded.culture_ids)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM stations WHERE id = ANY($1)")
+    .bind(&seeded.station_ids)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+  sqlx::query("DELETE FROM pathogenics WHERE id = ANY($1)")
+    .bind(&seeded.pathogenic_ids)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+}
+
+/// Every page of the listing, the largest one allowed, as `all` searches it
+async fn listing_pages(
+  db: &DataBase,
+  seeded: &Seeded,
+  filter: &RegionalFilter,
+) -> Vec<Vec<Plantation>> {
+  let mut pages = vec![];
+  let mut page = Page {
+    limit: MAX_LIMIT,
+    cursor: None,
+    sort: "create_date".to_string(),
+    descending: true,
+    fields: None,
+  };
+
+  loop {
+    let mut plantations = Plantation::search(
+      db,
+      seeded.user_id,
+      &PlantationFilter::default(),
+      filter,
+      &page,
+    )
+    .await
+    .unwrap();
+
+    let has_more = plantations.len() as i64 > page.limit;
+    plantations.truncate(page.limit as usize);
+    page.cursor = plantations.last().map(|plantation| plantation.id);
+    pages.push(plantations);
+
+    if !has_more {
+      return pages;
+    }
+  }
+}
+
+async fn listing_per_item(
+  db: &DataBase,
+  seeded: &Seeded,
+  filter: &RegionalFilter,
+) -> (usize, Duration) {
+  let started = Instant::now();
+
+  let pages = listing_pages(db, seeded, filter).await;
+  let mut queries = pages.len();
+
+  for plantation in pages.iter().flatten() {
+    Culture::find_by_id(db, plantation.culture_id)
+      .await
+      .unwrap();
+    Station::find_by_id(db, plantation.station_id.unwrap()).await;
+    Plantation::has_ocurrence_last_24h(db, plantation.id, filter)
+      .await
+      .unwrap();
+
+    queries += 3;
+  }
+
+  (queries, started.elapsed())
+}
+
+async fn listing_batched(
+  db: &DataBase,
+  seeded: &Seeded,
+  filter: &RegionalFilter,
+) -> (usize, Duration) {
+  let started = Instant::now();
+
+  let pages = listing_pages(db, seeded, filter).await;
+
+  for plantations in &pages {
+    let ids = plantations.iter().map(|p| p.id).collect::<Vec<_>>();
+    let culture_ids = plantations.iter().map(|p| p.culture_id).collect::<Vec<_>>();
+    let station_ids = plantations
+      .iter()
+      .filter_map(|p| p.station_id)
+      .collect::<Vec<_>>();
+
+    Culture::find_by_ids(db, &culture_ids).await.unwrap();
+    Station::find_by_ids(db, &station_ids).await.unwrap();
+    Plantation::with_ocurrence_last_24h(db, &ids, filter)
+      .await
+      .unwrap();
+  }
+
+  (pages.len() * 4, started.elapsed())
+}
+
+/// Regional occurrences of each plantation shown, as `show` builds them
+async fn regional_per_item(
+  db: &DataBase,
+  shown: &[(Uuid, i64, i64)],
+  filter: &RegionalFilter,
+) -> (usize, Duration) {
+  let started = Instant::now();
+  let mut queries = 0;
+
+  for &(id, _, _) in shown {
+    let occurrences =
+      PlantationPathogenicOccurrences::get_closest_by_plantation_id(db, id, filter)
+        .await
+        .unwrap();
+
+    for occurrence in &occurrences {
+      Pathogenic::find_by_id(db, &occurrence.pathogenic_id)
+        .await
+        .unwrap();
+    }
+
+    queries += 1 + occurrences.len();
+  }
+
+  (queries, started.elapsed())
+}
+
+async fn regional_batched(
+  db: &DataBase,
+  shown: &[(Uuid, i64, i64)],
+  filter: &RegionalFilter,
+) -> (usize, Duration) {
+  let started = Instant::now();
+  let mut queries = 0;
+
+  for &(id, _, _) in shown {
+    let occurrences =
+      PlantationPathogenicOccurrences::get_closest_by_plantation_id(db, id, filter)
+        .await
+        .unwrap();
+    let pathogenic_ids = occurrences
+      .iter()
+      .map(|occurrence| occurrence.pathogenic_id)
+      .collect::<Vec<_>>();
+
+    Pathogenic::find_by_ids(db, &pathogenic_ids).await.unwrap();
+
+    queries += 2;
+  }
+
+  (queries, started.elapsed())
+}
@@ -0,0 +1,56 @@
+use crate::{
+  models::{crawl_run::CrawlRun, stations::Station},
+  utils::{database::DataBase, response},
+};
+use poem::{
+  get,
+  handler,
+  web::{Data, Query},
+  Response,
+  Route,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct CrawlRunsQuery {
+  limit: Option<i64>,
+  script: Option<String>,
+  status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StaleStationsQuery {
+  hours: Option<i32>,
+}
+
+#[handler]
+async fn crawl_runs(db: Data<&DataBase>, query: Query<CrawlRunsQuery>) -> Response {
+  let runs = CrawlRun::recent(
+    &db,
+    query.0.limit.unwrap_or(50).clamp(1, 500),
+    query.0.script,
+    query.0.status,
+  )
+  .await
+  .unwrap();
+
+  response::json_ok(serde_json::json!({ "crawl_runs": runs }))
+}
+
+#[handler]
+async fn stale_stations(db: Data<&DataBase>, query: Query<StaleStationsQuery>) -> Response {
+  let hours = query.0.hours.unwrap_or(48);
+
+  let stations = Station::stale(&db, hours).await.unwrap();
+
+  response::json_ok(serde_json::json!({
+    "hours": hours,
+    "stations": stations
+  }))
+}
+
+pub(crate) fn routes() -> Route {
+  Route::new()
+    .at("/crawl-runs", get(crawl_runs))
+    .at("/stations/stale", get(stale_stations))
+}
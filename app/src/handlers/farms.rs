@@ -0,0 +1,536 @@
+use crate::{
+  mail::SharedMailer,
+  middleware::ensure_json,
+  models::{
+    farm::{Farm, FarmMember, Role},
+    farm_invitation::FarmInvitation,
+    plantation::Plantation,
+    user::User,
+  },
+  utils::{
+    database::{self, DataBase},
+    response::{self, JsonError},
+  },
+};
+use garde::Validate;
+use poem::{
+  get,
+  handler,
+  http::StatusCode,
+  patch,
+  post,
+  web::{Data, Json, Path},
+  EndpointExt,
+  Response,
+  Route,
+  RouteMethod,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, Validate)]
+struct FarmCreate {
+  #[garde(required, length(min = 3, max = 60))]
+  name: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct MemberUpdate {
+  #[garde(required, pattern(r"^(owner|manager|viewer)$"))]
+  role: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct InvitationCreate {
+  #[garde(required, email)]
+  email: Option<String>,
+  #[garde(required, pattern(r"^(owner|manager|viewer)$"))]
+  role: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct InvitationAccept {
+  #[garde(required)]
+  token: Option<String>,
+}
+
+#[handler]
+async fn all(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
+  let farms = Farm::all_by_user_id(&db, user.id).await.unwrap();
+
+  response::json_ok(serde_json::json!({ "farms": farms }))
+}
+
+#[handler]
+async fn create(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  req: Json<FarmCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let farm_id = Farm::insert(&db, &req.0.name.unwrap(), user.id)
+    .await
+    .unwrap();
+
+  response::json(serde_json::json!({ "farm": farm_id }), StatusCode::CREATED)
+}
+
+#[handler]
+async fn show(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(farm_id): Path<String>,
+) -> Response {
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  let members = FarmMember::all_by_farm_id(&db, farm.id).await.unwrap();
+  let plantations = Plantation::all_by_farm_id(&db, farm.id).await.unwrap();
+
+  response::json_ok(serde_json::json!({
+    "farm": farm,
+    "role": role,
+    "members": members,
+    "plantations": plantations
+  }))
+}
+
+#[handler]
+async fn update(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(farm_id): Path<String>,
+  req: Json<FarmCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  if role < Role::Manager {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let _ = Farm::update(&db, farm.id, &req.0.name.unwrap()).await;
+
+  response::json_ok(serde_json::json!({ "farm": Farm::find_by_id(&db, farm.id).await.unwrap() }))
+}
+
+#[handler]
+async fn delete(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(farm_id): Path<String>,
+) -> Response {
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  if role < Role::Owner {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let Ok(plantations) = Farm::plantations_count(&db, farm.id).await else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "could not be deleted".to_string())] }),
+      StatusCode::INTERNAL_SERVER_ERROR,
+    );
+  };
+
+  // Deleted plantations are kept until purged, and restored to their farm
+  if plantations > 0 {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "still has plantations".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  if Farm::delete(&db, farm.id).await.is_err() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "could not be deleted".to_string())] }),
+      StatusCode::INTERNAL_SERVER_ERROR,
+    );
+  }
+
+  response::json_ok(serde_json::json!({ "farm": "ok" }))
+}
+
+#[handler]
+async fn update_member(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((farm_id, member_id)): Path<(String, String)>,
+  req: Json<MemberUpdate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  if role < Role::Owner {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let member_role = match Uuid::parse_str(&member_id) {
+    Ok(member_id) => Farm::role(&db, farm.id, member_id)
+      .await
+      .map(|role| (member_id, role)),
+    Err(_) => None,
+  };
+
+  let Some((member_id, member_role)) = member_role else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  };
+
+  let new_role = Role::parse(&req.0.role.unwrap()).unwrap();
+
+  if member_role == Role::Owner
+    && new_role != Role::Owner
+    && FarmMember::owners_count(&db, farm.id).await.unwrap() == 1
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("role".to_string(), "the farm must keep an owner".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  FarmMember::save(&db, farm.id, member_id, new_role)
+    .await
+    .unwrap();
+
+  response::json_ok(
+    serde_json::json!({ "members": FarmMember::all_by_farm_id(&db, farm.id).await.unwrap() }),
+  )
+}
+
+/// Owners remove anyone, the other members can only leave
+#[handler]
+async fn delete_member(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((farm_id, member_id)): Path<(String, String)>,
+) -> Response {
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  let member_role = match Uuid::parse_str(&member_id) {
+    Ok(member_id) => Farm::role(&db, farm.id, member_id)
+      .await
+      .map(|role| (member_id, role)),
+    Err(_) => None,
+  };
+
+  let Some((member_id, member_role)) = member_role else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  };
+
+  if role < Role::Owner && member_id != user.id {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if member_role == Role::Owner && FarmMember::owners_count(&db, farm.id).await.unwrap() == 1 {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "the farm must keep an owner".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let _ = FarmMember::delete(&db, farm.id, member_id).await;
+
+  response::json_ok(serde_json::json!({ "member": "ok" }))
+}
+
+#[handler]
+async fn all_invitations(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(farm_id): Path<String>,
+) -> Response {
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  if role < Role::Manager {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let invitations = FarmInvitation::pending_by_farm_id(&db, farm.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "invitations": invitations }))
+}
+
+/// Managers invite viewers, owners invite with any role
+#[handler]
+async fn create_invitation(
+  db: Data<&database::DataBase>,
+  mailer: Data<&SharedMailer>,
+  user: Data<&User>,
+  Path(farm_id): Path<String>,
+  req: Json<InvitationCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+  let invited_role = Role::parse(&req.0.role.unwrap()).unwrap();
+
+  if role < Role::Manager || (role < Role::Owner && invited_role > Role::Viewer) {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let email = req.0.email.unwrap();
+
+  if let Ok(invited) = User::find_by_email(db.clone(), &email).await {
+    if Farm::role(&db, farm.id, invited.id).await.is_some() {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("email".to_string(), "already a member".to_string())] }),
+        StatusCode::BAD_REQUEST,
+      );
+    }
+  }
+
+  let invitation = FarmInvitation::insert(&db, farm.id, &email, invited_role, user.id)
+    .await
+    .unwrap();
+
+  let link = std::env::var("FARM_INVITATION_URL")
+    .unwrap_or("https://cropi.app/invitations/{token}".to_string())
+    .replace("{token}", &invitation.token);
+
+  // The invitation is saved and listed to the invited user, a failed email
+  // shouldn't fail the request
+  let _ = mailer
+    .send(
+      &email,
+      &format!("Convite para a fazenda {}", farm.name),
+      &format!(
+        "{} convidou você para acessar a fazenda {} no Cropi.\n\nPara aceitar, acesse: {}\n\nO convite expira em {}.",
+        user.name,
+        farm.name,
+        link,
+        invitation.expire_date.format("%d/%m/%Y")
+      ),
+    )
+    .await;
+
+  response::json(
+    serde_json::json!({ "invitation": invitation }),
+    StatusCode::CREATED,
+  )
+}
+
+#[handler]
+async fn delete_invitation(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((farm_id, invitation_id)): Path<(String, String)>,
+) -> Response {
+  let farm_result = find_farm_by_id(&db, farm_id, user.id).await;
+  if farm_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (farm, role) = farm_result.unwrap();
+
+  if role < Role::Manager {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let invitation = FarmInvitation::pending_by_farm_id(&db, farm.id)
+    .await
+    .unwrap()
+    .into_iter()
+    .find(|invitation| invitation.id.to_string() == invitation_id);
+
+  let Some(invitation) = invitation else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("invitation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  };
+
+  let _ = FarmInvitation::revoke(&db, invitation.id).await;
+
+  response::json_ok(serde_json::json!({ "invitation": "ok" }))
+}
+
+/// Pending invitations to the email of the user
+#[handler]
+async fn my_invitations(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
+  let invitations = FarmInvitation::pending_by_email(&db, &user.email)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "invitations": invitations }))
+}
+
+#[handler]
+async fn accept_invitation(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  req: Json<InvitationAccept>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let invitation = FarmInvitation::find_pending_by_token(&db, &req.0.token.unwrap()).await;
+
+  // Someone else's token is as good as a wrong one
+  let invitation = match invitation {
+    Ok(invitation) if invitation.email.eq_ignore_ascii_case(&user.email) => invitation,
+    _ => {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("invitation".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  };
+
+  let invited_role = Role::parse(&invitation.role).unwrap();
+
+  // Accepting never takes a role away from a member
+  if Farm::role(&db, invitation.farm_id, user.id)
+    .await
+    .is_none_or(|role| role < invited_role)
+  {
+    FarmMember::save(&db, invitation.farm_id, user.id, invited_role)
+      .await
+      .unwrap();
+  }
+
+  let _ = FarmInvitation::accept(&db, invitation.id).await;
+
+  response::json_ok(serde_json::json!({ "farm": invitation.farm_id }))
+}
+
+async fn find_farm_by_id(db: &DataBase, farm_id: String, user_id: Uuid) -> Option<(Farm, Role)> {
+  let farm_id = Uuid::parse_str(&farm_id).ok()?;
+
+  let role = Farm::role(db, farm_id, user_id).await?;
+
+  let farm = Farm::find_by_id(db, farm_id).await.ok()?;
+
+  Some((farm, role))
+}
+
+pub fn routes() -> Route {
+  Route::new()
+    .just_at(get(all).post(create).around(ensure_json::handle))
+    .at("/invitations", get(my_invitations))
+    .at(
+      "/invitations/accept",
+      post(accept_invitation).around(ensure_json::handle),
+    )
+    .at(
+      "/:farm_id",
+      get(show)
+        .patch(update)
+        .delete(delete)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:farm_id/members/:member_id",
+      patch(update_member)
+        .delete(delete_member)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:farm_id/invitations",
+      get(all_invitations)
+        .post(create_invitation)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:farm_id/invitations/:invitation_id",
+      RouteMethod::new().delete(delete_invitation),
+    )
+}
@@ -0,0 +1,75 @@
+use crate::{
+  models::hotspot::{Hotspot, HotspotSnapshot},
+  utils::{
+    database,
+    response::{self, JsonError},
+  },
+};
+use poem::{
+  get,
+  handler,
+  http::StatusCode,
+  web::{Data, Path, Query},
+  Response,
+  Route,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct HotspotsQuery {
+  pathogenic_id: Option<i64>,
+  /// `active`, the default, `closed` or `all`
+  status: Option<String>,
+  emerging: Option<bool>,
+}
+
+#[handler]
+async fn index(db: Data<&database::DataBase>, query: Query<HotspotsQuery>) -> Response {
+  let query = query.0;
+
+  let status = match query.status.as_deref().unwrap_or("active") {
+    "all" => None,
+    status @ ("active" | "closed") => Some(status.to_string()),
+    _ => {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("status".to_string(), "must be active, closed or all".to_string())] }),
+        StatusCode::BAD_REQUEST,
+      );
+    }
+  };
+
+  let hotspots = Hotspot::search(&db, query.pathogenic_id, status, query.emerging)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "hotspots": hotspots }))
+}
+
+/// The hotspot with its centroid and size on every run, to follow its spread
+#[handler]
+async fn show(db: Data<&database::DataBase>, Path(hotspot_id): Path<i64>) -> Response {
+  let hotspot_result = Hotspot::find_by_id(&db, hotspot_id).await.unwrap();
+
+  if hotspot_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("hotspot".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let hotspot = hotspot_result.unwrap();
+  let history = HotspotSnapshot::all_by_hotspot_id(&db, hotspot.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "hotspot": hotspot,
+    "history": history
+  }))
+}
+
+pub fn routes() -> Route {
+  Route::new()
+    .at("/", get(index))
+    .at("/:hotspot_id", get(show))
+}
@@ -0,0 +1,64 @@
+use crate::{
+  storage::SharedStorage,
+  utils::{jwt, response, response::JsonError},
+};
+use poem::{
+  get,
+  handler,
+  http::StatusCode,
+  web::{Data, Path, Query},
+  Response,
+  Route,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ImageQuery {
+  token: Option<String>,
+}
+
+#[handler]
+async fn show(
+  storage: Data<&SharedStorage>,
+  key: Path<String>,
+  query: Query<ImageQuery>,
+) -> Response {
+  // The token is only handed out by the plantation routes, after checking the
+  // plantation belongs to the authenticated user.
+  let authorized = query
+    .0
+    .token
+    .and_then(|token| jwt::decode_image(token).ok())
+    .is_some_and(|token| token.claims.sub == key.0);
+
+  if !authorized {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("image".to_string(), "invalid or expired url".to_string())] }),
+      StatusCode::UNAUTHORIZED,
+    );
+  }
+
+  let bytes = storage.get(&key.0).await;
+
+  if bytes.is_err() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("image".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let bytes = bytes.unwrap();
+
+  Response::builder()
+    .content_type(
+      infer::get(&bytes)
+        .map(|kind| kind.mime_type())
+        .unwrap_or("application/octet-stream"),
+    )
+    .header("Cache-Control", "private, max-age=300")
+    .body(bytes)
+}
+
+pub(crate) fn routes() -> Route {
+  Route::new().at("/*key", get(show))
+}
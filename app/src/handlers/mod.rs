@@ -1,10 +1,17 @@
+pub mod admin;
+pub mod farms;
 pub mod health;
+pub mod hotspots;
+pub mod images;
 pub mod login;
+pub mod organizations;
 pub mod plantations;
+pub mod public;
+pub mod tiles;
 pub mod user;
 
-use crate::middleware::{auth, ensure_json};
-use poem::{endpoint::StaticFilesEndpoint, EndpointExt, Route};
+use crate::middleware::{auth, ensure_admin, ensure_json};
+use poem::{EndpointExt, Route};
 
 pub(crate) fn all() -> Route {
   Route::new()
@@ -12,8 +19,19 @@ pub(crate) fn all() -> Route {
     .nest("/user", user::routes().around(ensure_json::handle))
     .nest("/login", login::routes().around(ensure_json::handle))
     .nest("/plantations", plantations::routes().around(auth::handle))
+    .nest("/farms", farms::routes().around(auth::handle))
     .nest(
-      "/images/ocurrences",
-      StaticFilesEndpoint::new("app/images/ocurrences/").show_files_listing(),
+      "/organizations",
+      organizations::routes().around(auth::handle),
+    )
+    .nest("/hotspots", hotspots::routes().around(auth::handle))
+    .nest("/images", images::routes())
+    .nest("/public", public::routes())
+    .nest("/tiles", tiles::routes().around(auth::handle))
+    .nest(
+      "/admin",
+      admin::routes()
+        .around(ensure_admin::handle)
+        .around(auth::handle),
     )
 }
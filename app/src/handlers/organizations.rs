@@ -0,0 +1,791 @@
+use crate::{
+  jobs::send_ocurrence_notification,
+  middleware::ensure_json,
+  models::{
+    farm::Farm,
+    occurrence_review::OccurrenceReview,
+    organization::{AlertPolicy, Organization, OrganizationMember, OrganizationRole},
+    pathogenic::Pathogenic,
+    plantation::Plantation,
+    plantation_pathogenic_occurrences::PlantationPathogenicOccurrences,
+    user::User,
+  },
+  storage::{self, SharedStorage},
+  utils::{
+    database::{self, DataBase},
+    response::{self, JsonError},
+  },
+};
+use chrono::NaiveDate;
+use garde::Validate;
+use poem::{
+  get,
+  handler,
+  http::StatusCode,
+  patch,
+  post,
+  web::{Data, Json, Path, Query},
+  EndpointExt,
+  Response,
+  Route,
+  RouteMethod,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, Validate)]
+struct OrganizationCreate {
+  #[garde(required, length(min = 3, max = 80))]
+  name: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct OrganizationJoin {
+  #[garde(required)]
+  code: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct MemberUpdate {
+  #[garde(required, pattern(r"^(admin|agronomist|member)$"))]
+  role: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+struct AlertPolicySave {
+  /// The default policy when empty
+  #[garde(skip)]
+  pathogenic_id: Option<i64>,
+  #[garde(required, pattern(r"^(medium|high)$"))]
+  min_level: Option<String>,
+  #[garde(skip)]
+  notify_agronomists: Option<bool>,
+}
+
+#[derive(Deserialize, Validate)]
+struct ReviewCreate {
+  #[garde(required, pattern(r"^(confirmed|rejected|needs_more_info)$"))]
+  status: Option<String>,
+  #[garde(length(max = 500))]
+  comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OccurrencesQuery {
+  /// The last 12 weeks by default
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+}
+
+#[handler]
+async fn all(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
+  let organizations = Organization::all_by_user_id(&db, user.id).await.unwrap();
+
+  response::json_ok(serde_json::json!({ "organizations": organizations }))
+}
+
+#[handler]
+async fn create(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  req: Json<OrganizationCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_id = Organization::insert(&db, &req.0.name.unwrap(), user.id)
+    .await
+    .unwrap();
+
+  response::json(
+    serde_json::json!({ "organization": organization_id }),
+    StatusCode::CREATED,
+  )
+}
+
+/// Growers join with the code the organization shares with them
+#[handler]
+async fn join(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  req: Json<OrganizationJoin>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_result = Organization::find_by_code(&db, &req.0.code.unwrap()).await;
+  if organization_result.is_err() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("code".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let organization = organization_result.unwrap();
+
+  // Joining again never takes a role away from a member
+  if Organization::role(&db, organization.id, user.id)
+    .await
+    .is_none()
+  {
+    OrganizationMember::save(&db, organization.id, user.id, OrganizationRole::Member)
+      .await
+      .unwrap();
+  }
+
+  response::json_ok(serde_json::json!({ "organization": organization.id }))
+}
+
+/// Members see the organization, agronomists and admins also see who its
+/// members are
+#[handler]
+async fn show(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  let members = if role >= OrganizationRole::Agronomist {
+    OrganizationMember::all_by_organization_id(&db, organization.id)
+      .await
+      .unwrap()
+  } else {
+    vec![]
+  };
+
+  response::json_ok(serde_json::json!({
+    "organization": organization,
+    "role": role,
+    "members": members
+  }))
+}
+
+#[handler]
+async fn update(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+  req: Json<OrganizationCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role < OrganizationRole::Admin {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let _ = Organization::update(&db, organization.id, &req.0.name.unwrap()).await;
+
+  response::json_ok(
+    serde_json::json!({ "organization": Organization::find_by_id(&db, organization.id).await.unwrap() }),
+  )
+}
+
+#[handler]
+async fn delete(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role < OrganizationRole::Admin {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let _ = Organization::delete(&db, organization.id).await;
+
+  response::json_ok(serde_json::json!({ "organization": "ok" }))
+}
+
+#[handler]
+async fn update_member(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((organization_id, member_id)): Path<(String, String)>,
+  req: Json<MemberUpdate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role < OrganizationRole::Admin {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let member_role = match Uuid::parse_str(&member_id) {
+    Ok(member_id) => Organization::role(&db, organization.id, member_id)
+      .await
+      .map(|role| (member_id, role)),
+    Err(_) => None,
+  };
+
+  let Some((member_id, member_role)) = member_role else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  };
+
+  let new_role = OrganizationRole::parse(&req.0.role.unwrap()).unwrap();
+
+  if member_role == OrganizationRole::Admin
+    && new_role != OrganizationRole::Admin
+    && OrganizationMember::admins_count(&db, organization.id)
+      .await
+      .unwrap()
+      == 1
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("role".to_string(), "the organization must keep an admin".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  OrganizationMember::save(&db, organization.id, member_id, new_role)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "members": OrganizationMember::all_by_organization_id(&db, organization.id).await.unwrap()
+  }))
+}
+
+/// Admins remove anyone, the other members can only leave
+#[handler]
+async fn delete_member(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((organization_id, member_id)): Path<(String, String)>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  let member_role = match Uuid::parse_str(&member_id) {
+    Ok(member_id) => Organization::role(&db, organization.id, member_id)
+      .await
+      .map(|role| (member_id, role)),
+    Err(_) => None,
+  };
+
+  let Some((member_id, member_role)) = member_role else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  };
+
+  if role < OrganizationRole::Admin && member_id != user.id {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if member_role == OrganizationRole::Admin
+    && OrganizationMember::admins_count(&db, organization.id)
+      .await
+      .unwrap()
+      == 1
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("member".to_string(), "the organization must keep an admin".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let _ = OrganizationMember::delete(&db, organization.id, member_id).await;
+
+  response::json_ok(serde_json::json!({ "member": "ok" }))
+}
+
+#[handler]
+async fn plantations(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role != OrganizationRole::Agronomist {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let plantations = Plantation::all_by_organization_id(&db, organization.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "plantations": plantations }))
+}
+
+/// Occurrences on the members' plantations by pathogen, municipality and week
+#[handler]
+async fn occurrences(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+  query: Query<OccurrencesQuery>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role != OrganizationRole::Agronomist {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let to = query.to.unwrap_or(chrono::Utc::now().date_naive());
+  let from = query.from.unwrap_or(to - chrono::Duration::weeks(12));
+
+  if from > to {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("from".to_string(), "must be before to".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let counts = Organization::occurrence_counts(&db, organization.id, from, to)
+    .await
+    .unwrap();
+
+  let by = |dimension: &str| {
+    counts
+      .iter()
+      .filter(|count| count.dimension == dimension)
+      .collect::<Vec<_>>()
+  };
+
+  response::json_ok(serde_json::json!({
+    "from": from,
+    "to": to,
+    "by_pathogenic": by("pathogenic"),
+    "by_municipality": by("municipality"),
+    "by_week": by("week")
+  }))
+}
+
+#[handler]
+async fn alert_policies(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, _) = organization_result.unwrap();
+
+  let policies = AlertPolicy::all_by_organization_id(&db, organization.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "policies": policies }))
+}
+
+#[handler]
+async fn save_alert_policy(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+  req: Json<AlertPolicySave>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role < OrganizationRole::Admin {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if let Some(pathogenic_id) = req.0.pathogenic_id {
+    if Pathogenic::find_by_id(&db, &pathogenic_id).await.is_err() {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("pathogenic_id".to_string(), "not found".to_string())] }),
+        StatusCode::BAD_REQUEST,
+      );
+    }
+  }
+
+  AlertPolicy::save(
+    &db,
+    organization.id,
+    req.0.pathogenic_id,
+    &req.0.min_level.unwrap(),
+    req.0.notify_agronomists.unwrap_or(false),
+  )
+  .await
+  .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "policies": AlertPolicy::all_by_organization_id(&db, organization.id).await.unwrap()
+  }))
+}
+
+#[handler]
+async fn delete_alert_policy(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((organization_id, policy_id)): Path<(String, String)>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role < OrganizationRole::Admin {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let deleted = match policy_id.parse::<i64>() {
+    Ok(policy_id) => AlertPolicy::delete(&db, organization.id, policy_id)
+      .await
+      .unwrap(),
+    Err(_) => false,
+  };
+
+  if !deleted {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("policy".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  response::json_ok(serde_json::json!({ "policy": "ok" }))
+}
+
+/// Reports of the growers waiting for an agronomist
+#[handler]
+async fn review_queue(
+  db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
+  user: Data<&User>,
+  Path(organization_id): Path<String>,
+) -> Response {
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role != OrganizationRole::Agronomist {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let mut queue = OccurrenceReview::queue(&db, organization.id, user.id)
+    .await
+    .unwrap();
+
+  for ocurrence in queue.iter_mut() {
+    ocurrence.image = storage::image_url(&storage, &ocurrence.image);
+  }
+
+  response::json_ok(serde_json::json!({ "ocurrences": queue }))
+}
+
+/// Confirms or rejects a report, or asks the grower for more about it. The
+/// neighbours are notified once it's confirmed, even if they heard of the
+/// report before.
+#[handler]
+async fn review(
+  db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
+  user: Data<&User>,
+  Path((organization_id, ocurrence_id)): Path<(String, String)>,
+  req: Json<ReviewCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let organization_result = find_organization_by_id(&db, organization_id, user.id).await;
+  if organization_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let (organization, role) = organization_result.unwrap();
+
+  if role != OrganizationRole::Agronomist {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("organization".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let ocurrence = match Uuid::parse_str(&ocurrence_id) {
+    Ok(ocurrence_id) => PlantationPathogenicOccurrences::find_by_id(&db, ocurrence_id)
+      .await
+      .ok(),
+    Err(_) => None,
+  };
+
+  let ocurrence = match ocurrence {
+    Some(ocurrence)
+      if Organization::has_plantation(&db, organization.id, ocurrence.plantation_id).await =>
+    {
+      ocurrence
+    }
+    _ => {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("ocurrence".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  };
+
+  let own_farm = Farm::plantation_role(&db, ocurrence.plantation_id, user.id)
+    .await
+    .is_some();
+
+  if let Some(forbidden) = self_review(user.id, ocurrence.user_id, own_farm) {
+    return forbidden;
+  }
+
+  let status = req.0.status.unwrap();
+  let comment = req.0.comment.filter(|comment| !comment.trim().is_empty());
+
+  // The grower needs to know what was wrong or what's missing
+  if status != "confirmed" && comment.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("comment".to_string(), "required".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  OccurrenceReview::insert(&db, ocurrence.id, user.id, &status, comment)
+    .await
+    .unwrap();
+
+  let pathogenic = Pathogenic::find_by_id(&db, &ocurrence.pathogenic_id)
+    .await
+    .unwrap();
+
+  let message = match status.as_str() {
+    "confirmed" => format!(
+      "Sua ocorrência de {} foi confirmada por um agrônomo.",
+      pathogenic.name
+    ),
+    "rejected" => format!(
+      "Sua ocorrência de {} foi rejeitada por um agrônomo.",
+      pathogenic.name
+    ),
+    _ => format!(
+      "Um agrônomo pediu mais informações sobre sua ocorrência de {}.",
+      pathogenic.name
+    ),
+  };
+
+  let _ = User::notify(&db, ocurrence.user_id, &message).await;
+
+  let mut ocurrence = PlantationPathogenicOccurrences::find_by_id(&db, ocurrence.id)
+    .await
+    .unwrap();
+
+  if ocurrence.status == "confirmed" {
+    send_ocurrence_notification::spawn(&db, ocurrence.clone());
+  }
+
+  ocurrence.image = storage::image_url(&storage, &ocurrence.image);
+
+  response::json_ok(serde_json::json!({ "ocurrence": ocurrence }))
+}
+
+/// The 403 of an agronomist reviewing a report of their own, or one on a farm
+/// they're a member of
+fn self_review(reviewer_id: Uuid, reporter_id: Uuid, own_farm: bool) -> Option<Response> {
+  if reviewer_id != reporter_id && !own_farm {
+    return None;
+  }
+
+  Some(response::json(
+    serde_json::json!({ "errors": vec![JsonError::new("ocurrence".to_string(), "can't review your own ocurrence".to_string())] }),
+    StatusCode::FORBIDDEN,
+  ))
+}
+
+async fn find_organization_by_id(
+  db: &DataBase,
+  organization_id: String,
+  user_id: Uuid,
+) -> Option<(Organization, OrganizationRole)> {
+  let organization_id = Uuid::parse_str(&organization_id).ok()?;
+
+  let role = Organization::role(db, organization_id, user_id).await?;
+
+  let organization = Organization::find_by_id(db, organization_id).await.ok()?;
+
+  Some((organization, role))
+}
+
+pub fn routes() -> Route {
+  Route::new()
+    .just_at(get(all).post(create).around(ensure_json::handle))
+    .at("/join", post(join).around(ensure_json::handle))
+    .at(
+      "/:organization_id",
+      get(show)
+        .patch(update)
+        .delete(delete)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:organization_id/members/:member_id",
+      patch(update_member)
+        .delete(delete_member)
+        .around(ensure_json::handle),
+    )
+    .at("/:organization_id/plantations", get(plantations))
+    .at("/:organization_id/occurrences", get(occurrences))
+    .at(
+      "/:organization_id/alert-policies",
+      get(alert_policies)
+        .put(save_alert_policy)
+        .around(ensure_json::handle),
+    )
+    .at("/:organization_id/reviews", get(review_queue))
+    .at(
+      "/:organization_id/reviews/:ocurrence_id",
+      post(review).around(ensure_json::handle),
+    )
+    .at(
+      "/:organization_id/alert-policies/:policy_id",
+      RouteMethod::new().delete(delete_alert_policy),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reviewing_your_own_report_is_forbidden() {
+    let user_id = Uuid::new_v4();
+
+    let response = self_review(user_id, user_id, false).unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  }
+
+  #[test]
+  fn reviewing_a_report_on_your_farm_is_forbidden() {
+    let response = self_review(Uuid::new_v4(), Uuid::new_v4(), true).unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  }
+
+  #[test]
+  fn reviewing_another_growers_report_is_allowed() {
+    assert!(self_review(Uuid::new_v4(), Uuid::new_v4(), false).is_none());
+  }
+}
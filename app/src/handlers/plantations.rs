@@ -1,16 +1,33 @@
 use crate::{
-  jobs::{send_ocurrence_notification::SendOcurrenceNotification, Job},
+  jobs::send_ocurrence_notification,
   middleware::ensure_json,
   models::{
     culture::Culture,
+    farm::{Farm, Role},
+    occurrence_review::OccurrenceReview,
+    organization::Organization,
     pathogenic::Pathogenic,
-    plantation::Plantation,
-    plantation_pathogenic_occurrences::PlantationPathogenicOccurrences,
+    plantation::{self, Plantation, PlantationFilter},
+    plantation_activity::{ActivityData, PlantationActivity},
+    plantation_pathogenic_occurrences::{
+      self,
+      OccurrenceClimate,
+      OccurrenceFilter,
+      PlantationPathogenicOccurrences,
+      RegionalFilter,
+      RegionalSort,
+    },
+    plantation_risk::PlantationRisk,
+    plantation_season::PlantationSeason,
+    spray_window::{self, SprayRules},
+    station_forecast::StationForecast,
     stations::Station,
     user::User,
   },
+  storage::{self, SharedStorage},
   utils::{
     database::{self, DataBase},
+    pagination::PageQuery,
     response::{self, JsonError},
   },
 };
@@ -21,13 +38,12 @@ use poem::{
   handler,
   http::StatusCode,
   post,
-  web::{Data, Json, Multipart, Path},
+  web::{Data, Json, Multipart, Path, Query},
   EndpointExt,
   Response,
   Route,
 };
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::prelude::*, sync::Arc};
 use uuid::Uuid;
 
 #[derive(Deserialize, Validate, Debug)]
@@ -47,6 +63,9 @@ struct PlantationCreate {
     pattern(r"([12]\d{3}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01]))")
   )]
   planting_date: Option<String>,
+  /// The user's own farm when empty on create, moves the plantation on update
+  #[garde(skip)]
+  farm_id: Option<Uuid>,
 }
 
 #[derive(Serialize)]
@@ -76,13 +95,19 @@ struct PlantationPathogenicOccurrencesResponse {
   occurrence_date: chrono::NaiveDateTime,
   temperature: Option<f64>,
   humidity: Option<f64>,
+  status: String,
   create_date: chrono::NaiveDateTime,
   update_date: Option<chrono::NaiveDateTime>,
+  /// Only on the regional occurrences, from the plantation
+  #[serde(skip_serializing_if = "Option::is_none")]
+  distance_km: Option<f64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  bearing: Option<f64>,
 }
 
 #[derive(Serialize)]
 struct PlantationAllResponse {
-  id: String,
+  id: Uuid,
   alias: Option<String>,
   latitude: Option<f64>,
   longitude: Option<f64>,
@@ -106,27 +131,176 @@ struct PlantationPathogenicOccurrencesCreate {
   occurrence_date: Option<String>,
 }
 
-#[handler]
-async fn all(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
-  let plantations = Plantation::all_by_user_id(&db, user.id).await;
+/// Filters of the regional occurrences, on the listing and on the
+/// `has_ocurrences` flag
+#[derive(Deserialize)]
+struct RegionalQuery {
+  /// Overrides the user's radius, in km
+  radius_km: Option<f64>,
+  pathogenic_id: Option<i64>,
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+  /// `date`, the default, or `distance`
+  sort: Option<String>,
+}
 
-  let mut response: Vec<PlantationAllResponse> = Vec::new();
-  for plantation in plantations {
-    if plantation.delete_at.is_some() {
-      continue;
-    }
+fn regional_filter(user: &User, query: RegionalQuery) -> Result<RegionalFilter, Vec<JsonError>> {
+  let mut errors = vec![];
+
+  if query.radius_km.is_some_and(|radius_km| {
+    !(plantation_pathogenic_occurrences::MIN_RADIUS_KM
+      ..=plantation_pathogenic_occurrences::MAX_RADIUS_KM)
+      .contains(&radius_km)
+  }) {
+    errors.push(JsonError::new(
+      "radius_km".to_string(),
+      format!(
+        "must be between {} and {}",
+        plantation_pathogenic_occurrences::MIN_RADIUS_KM,
+        plantation_pathogenic_occurrences::MAX_RADIUS_KM
+      ),
+    ));
+  }
 
-    let culture = Culture::find_by_id(&db, plantation.culture_id)
-      .await
-      .unwrap();
+  if let (Some(from), Some(to)) = (query.from, query.to) {
+    if from > to {
+      errors.push(JsonError::new(
+        "from".to_string(),
+        "must be before to".to_string(),
+      ));
+    }
+  }
 
-    let mut stations: Option<Station> = None;
-    if plantation.station_id.is_some() {
-      stations = Some(Station::find_by_id(&db, plantation.station_id.unwrap()).await);
+  let sort = match query.sort.as_deref().unwrap_or("date") {
+    "date" => RegionalSort::Date,
+    "distance" => RegionalSort::Distance,
+    _ => {
+      errors.push(JsonError::new(
+        "sort".to_string(),
+        "must be date or distance".to_string(),
+      ));
+      RegionalSort::Date
     }
+  };
+
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+
+  let mut filter = RegionalFilter::for_user(user, query.radius_km);
+  filter.pathogenic_id = query.pathogenic_id;
+  filter.from = query.from.map(start_of_day);
+  filter.to = query.to.map(end_of_day);
+  filter.sort = sort;
+
+  Ok(filter)
+}
+
+/// Fields of the plantations listing items
+const PLANTATION_FIELDS: [&str; 11] = [
+  "id",
+  "alias",
+  "latitude",
+  "longitude",
+  "area",
+  "planting_date",
+  "create_date",
+  "update_date",
+  "culture",
+  "station",
+  "has_ocurrences",
+];
+
+#[derive(Deserialize)]
+struct PlantationsQuery {
+  culture_id: Option<i64>,
+  station_id: Option<i64>,
+  planting_from: Option<NaiveDate>,
+  planting_to: Option<NaiveDate>,
+  created_from: Option<NaiveDate>,
+  created_to: Option<NaiveDate>,
+  has_ocurrences: Option<bool>,
+  /// Radius and pathogen of the regional occurrences behind `has_ocurrences`
+  radius_km: Option<f64>,
+  pathogenic_id: Option<i64>,
+}
+
+#[handler]
+async fn all(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  query: Query<PlantationsQuery>,
+  page: Query<PageQuery>,
+) -> Response {
+  let query = query.0;
+  let mut errors = vec![];
+
+  let regional = regional_filter(
+    &user,
+    RegionalQuery {
+      radius_km: query.radius_km,
+      pathogenic_id: query.pathogenic_id,
+      from: None,
+      to: None,
+      sort: None,
+    },
+  )
+  .map_err(|e| errors.extend(e))
+  .ok();
+  let page = page
+    .0
+    .page(
+      &["create_date", "planting_date", "alias", "area"],
+      "-create_date",
+      &PLANTATION_FIELDS,
+    )
+    .map_err(|e| errors.extend(e))
+    .ok();
+
+  let (Some(regional), Some(page)) = (regional, page) else {
+    return response::json(
+      serde_json::json!({ "errors": errors }),
+      StatusCode::BAD_REQUEST,
+    );
+  };
+
+  let filter = PlantationFilter {
+    culture_id: query.culture_id,
+    station_id: query.station_id,
+    planting_from: query.planting_from.map(start_of_day),
+    planting_to: query.planting_to.map(end_of_day),
+    created_from: query.created_from.map(start_of_day),
+    created_to: query.created_to.map(end_of_day),
+    has_ocurrences: query.has_ocurrences,
+  };
+
+  let plantations = Plantation::search(&db, user.id, &filter, &regional, &page)
+    .await
+    .unwrap();
+
+  let culture_ids = plantations
+    .iter()
+    .map(|plantation| plantation.culture_id)
+    .collect::<Vec<_>>();
+  let station_ids = plantations
+    .iter()
+    .filter_map(|plantation| plantation.station_id)
+    .collect::<Vec<_>>();
+  let plantation_ids = plantations
+    .iter()
+    .map(|plantation| plantation.id)
+    .collect::<Vec<_>>();
+
+  let cultures = Culture::find_by_ids(&db, &culture_ids).await.unwrap();
+  let stations = Station::find_by_ids(&db, &station_ids).await.unwrap();
+  let with_ocurrences = Plantation::with_ocurrence_last_24h(&db, &plantation_ids, &regional)
+    .await
+    .unwrap_or_default();
 
-    response.push(PlantationAllResponse {
-      id: plantation.id.to_string(),
+  let response = plantations
+    .into_iter()
+    .map(|plantation| PlantationAllResponse {
+      id: plantation.id,
       alias: plantation.alias,
       latitude: plantation.latitude,
       longitude: plantation.longitude,
@@ -134,15 +308,23 @@ async fn all(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
       planting_date: plantation.planting_date,
       create_date: plantation.create_date,
       update_date: plantation.update_date,
-      culture,
-      station: stations,
-      has_ocurrences: Plantation::has_ocurrence_last_24h(&db, plantation.id)
-        .await
-        .unwrap_or(false),
-    });
-  }
+      culture: cultures[&plantation.culture_id].clone(),
+      station: plantation
+        .station_id
+        .and_then(|station_id| stations.get(&station_id).cloned()),
+      has_ocurrences: with_ocurrences.contains(&plantation.id),
+    })
+    .collect::<Vec<_>>();
+
+  response::json_ok(page.envelope("plantations", response, |plantation| plantation.id))
+}
 
-  response::json_ok(serde_json::json!({ "plantations": response }))
+fn start_of_day(date: NaiveDate) -> chrono::NaiveDateTime {
+  date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn end_of_day(date: NaiveDate) -> chrono::NaiveDateTime {
+  date.and_hms_micro_opt(23, 59, 59, 999_999).unwrap()
 }
 
 #[handler]
@@ -155,6 +337,21 @@ async fn create(
     return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
   }
 
+  let farm_id = match req.0.farm_id {
+    Some(farm_id) => farm_id,
+    None => Farm::default_for_user(&db, &user).await.unwrap(),
+  };
+
+  if Farm::role(&db, farm_id, user.id)
+    .await
+    .is_none_or(|role| role < Role::Manager)
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
   let culture = Culture::find_by_id(&db, req.0.culture_id.unwrap()).await;
 
   if culture.is_err() {
@@ -176,22 +373,31 @@ async fn create(
     );
   }
 
+  let planting_date = NaiveDate::parse_from_str(&req.0.planting_date.unwrap(), "%Y-%m-%d")
+    .unwrap()
+    .and_hms_opt(0, 0, 0)
+    .unwrap();
+
   let plantation_uuid = Plantation::insert(
     &db,
     user.id,
+    farm_id,
     req.0.culture_id.unwrap(),
     Some(station.ok().unwrap().id),
     req.0.alias.unwrap(),
     latitude,
     longitude,
     req.0.area.unwrap(),
-    NaiveDate::parse_from_str(&req.0.planting_date.unwrap(), "%Y-%m-%d")
-      .unwrap()
-      .and_hms_opt(0, 0, 0)
-      .unwrap(),
+    planting_date,
   )
-  .await
-  .unwrap();
+  .await;
+
+  let Ok(plantation_uuid) = plantation_uuid else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "could not be saved".to_string())] }),
+      StatusCode::INTERNAL_SERVER_ERROR,
+    );
+  };
 
   response::json(
     serde_json::json!({ "plantation": plantation_uuid}),
@@ -200,7 +406,23 @@ async fn create(
 }
 
 #[handler]
-async fn show(db: Data<&database::DataBase>, user: Data<&User>, id: Path<String>) -> Response {
+async fn show(
+  db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
+  user: Data<&User>,
+  id: Path<String>,
+  query: Query<RegionalQuery>,
+) -> Response {
+  let filter = match regional_filter(&user, query.0) {
+    Ok(filter) => filter,
+    Err(errors) => {
+      return response::json(
+        serde_json::json!({ "errors": errors }),
+        StatusCode::BAD_REQUEST,
+      );
+    }
+  };
+
   let plantation_result = find_plantation_by_id(&db, id.0.to_string(), user.id).await;
   if plantation_result.is_none() {
     return response::json(
@@ -220,50 +442,58 @@ async fn show(db: Data<&database::DataBase>, user: Data<&User>, id: Path<String>
     stations = Some(Station::find_by_id(&db, plantation.station_id.unwrap()).await);
   }
 
-  let mut ocurrences_db: Result<Vec<PlantationPathogenicOccurrences>, sqlx::Error> =
-    PlantationPathogenicOccurrences::get_by_plantation_id(&db, plantation.id).await;
-  let mut ocurrences: Vec<PlantationPathogenicOccurrencesResponse> = Vec::new();
-  if ocurrences_db.is_ok() {
-    for ocurrence in ocurrences_db.as_mut().unwrap() {
-      let pathogenic = Pathogenic::find_by_id(&db, &ocurrence.pathogenic_id)
-        .await
-        .unwrap();
-
-      ocurrences.push(PlantationPathogenicOccurrencesResponse {
-        id: ocurrence.id.to_string(),
-        pathogenic,
-        image: ocurrence.image.clone(),
-        occurrence_date: ocurrence.occurrence_date,
-        temperature: ocurrence.temperature,
-        humidity: ocurrence.humidity,
-        create_date: ocurrence.create_date,
-        update_date: ocurrence.update_date,
-      });
-    }
-  }
-
-  let mut region_ocurrences_db: Result<Vec<PlantationPathogenicOccurrences>, sqlx::Error> =
-    PlantationPathogenicOccurrences::get_closest_by_plantation_id(&db, plantation.id).await;
-  let mut region_ocurrences: Vec<PlantationPathogenicOccurrencesResponse> = Vec::new();
-
-  if region_ocurrences_db.is_ok() {
-    for ocurrence in region_ocurrences_db.as_mut().unwrap() {
-      let pathogenic = Pathogenic::find_by_id(&db, &ocurrence.pathogenic_id)
-        .await
-        .unwrap();
-
-      region_ocurrences.push(PlantationPathogenicOccurrencesResponse {
-        id: ocurrence.id.to_string(),
-        pathogenic,
-        image: None,
-        occurrence_date: ocurrence.occurrence_date,
-        temperature: ocurrence.temperature,
-        humidity: ocurrence.humidity,
-        create_date: ocurrence.create_date,
-        update_date: ocurrence.update_date,
-      });
-    }
-  }
+  let ocurrences_db = PlantationPathogenicOccurrences::get_by_plantation_id(&db, plantation.id)
+    .await
+    .unwrap_or_default();
+  let region_ocurrences_db =
+    PlantationPathogenicOccurrences::get_closest_by_plantation_id(&db, plantation.id, &filter)
+      .await
+      .unwrap_or_default();
+
+  let pathogenic_ids = ocurrences_db
+    .iter()
+    .map(|ocurrence| ocurrence.pathogenic_id)
+    .chain(
+      region_ocurrences_db
+        .iter()
+        .map(|ocurrence| ocurrence.pathogenic_id),
+    )
+    .collect::<Vec<_>>();
+  let pathogenics = Pathogenic::find_by_ids(&db, &pathogenic_ids).await.unwrap();
+
+  let ocurrences = ocurrences_db
+    .into_iter()
+    .map(|ocurrence| PlantationPathogenicOccurrencesResponse {
+      id: ocurrence.id.to_string(),
+      pathogenic: pathogenics[&ocurrence.pathogenic_id].clone(),
+      image: storage::image_url(&storage, &ocurrence.image),
+      occurrence_date: ocurrence.occurrence_date,
+      temperature: ocurrence.temperature,
+      humidity: ocurrence.humidity,
+      status: ocurrence.status,
+      create_date: ocurrence.create_date,
+      update_date: ocurrence.update_date,
+      distance_km: None,
+      bearing: None,
+    })
+    .collect::<Vec<_>>();
+
+  let region_ocurrences = region_ocurrences_db
+    .into_iter()
+    .map(|ocurrence| PlantationPathogenicOccurrencesResponse {
+      id: ocurrence.id.to_string(),
+      pathogenic: pathogenics[&ocurrence.pathogenic_id].clone(),
+      image: None,
+      occurrence_date: ocurrence.occurrence_date,
+      temperature: ocurrence.temperature,
+      humidity: ocurrence.humidity,
+      status: ocurrence.status,
+      create_date: ocurrence.create_date,
+      update_date: ocurrence.update_date,
+      distance_km: Some(ocurrence.distance_km),
+      bearing: Some(ocurrence.bearing),
+    })
+    .collect::<Vec<_>>();
 
   response::json_ok(serde_json::json!(PlantationResponse {
     id: plantation.id.to_string(),
@@ -276,7 +506,7 @@ async fn show(db: Data<&database::DataBase>, user: Data<&User>, id: Path<String>
     update_date: plantation.update_date,
     culture,
     station: stations,
-    has_ocurrences: Plantation::has_ocurrence_last_24h(&db, plantation.id)
+    has_ocurrences: Plantation::has_ocurrence_last_24h(&db, plantation.id, &filter)
       .await
       .unwrap_or(false),
     plantation_ocurrences: Some(ocurrences),
@@ -305,6 +535,30 @@ async fn update(
 
   let plantation = plantation_result.unwrap();
 
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let farm_id = req
+    .0
+    .farm_id
+    .filter(|farm_id| Some(*farm_id) != plantation.farm_id);
+
+  if let Some(farm_id) = farm_id {
+    if Farm::role(&db, farm_id, user.id)
+      .await
+      .is_none_or(|role| role < Role::Manager)
+    {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("farm".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  }
+
   let culture = Culture::find_by_id(&db, req.0.culture_id.unwrap()).await;
 
   if culture.is_err() {
@@ -326,22 +580,32 @@ async fn update(
     );
   }
 
-  let _ = Plantation::update(
+  let planting_date = NaiveDate::parse_from_str(&req.0.planting_date.unwrap(), "%Y-%m-%d")
+    .unwrap()
+    .and_hms_opt(0, 0, 0)
+    .unwrap();
+
+  let updated = Plantation::update(
     &db,
     plantation.id,
+    farm_id,
     req.0.culture_id.unwrap(),
     Some(station.ok().unwrap().id),
     req.0.alias.unwrap(),
     latitude,
     longitude,
     req.0.area.unwrap(),
-    NaiveDate::parse_from_str(&req.0.planting_date.unwrap(), "%Y-%m-%d")
-      .unwrap()
-      .and_hms_opt(0, 0, 0)
-      .unwrap(),
+    planting_date,
   )
   .await;
 
+  if updated.is_err() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "could not be saved".to_string())] }),
+      StatusCode::INTERNAL_SERVER_ERROR,
+    );
+  }
+
   response::json_ok(
     serde_json::json!({ "plantation": Plantation::find_by_uuid(&db, plantation.id).await.unwrap() }),
   )
@@ -359,17 +623,150 @@ async fn delete(db: Data<&database::DataBase>, user: Data<&User>, id: Path<Strin
 
   let plantation = plantation_result.unwrap();
 
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
   let _ = Plantation::delete(&db, plantation.id).await;
 
   response::json_ok(serde_json::json!({ "plantation": "ok" }))
 }
 
+#[derive(Serialize)]
+struct PlantationTrashResponse {
+  id: Uuid,
+  alias: Option<String>,
+  latitude: Option<f64>,
+  longitude: Option<f64>,
+  area: f64,
+  planting_date: chrono::NaiveDateTime,
+  culture: Culture,
+  delete_at: chrono::NaiveDateTime,
+  /// When it's purged, after that it can't be restored
+  purge_date: chrono::NaiveDateTime,
+}
+
+/// Deleted plantations the user can still restore
+#[handler]
+async fn trash(db: Data<&database::DataBase>, user: Data<&User>) -> Response {
+  let plantations = Plantation::all_deleted_by_user_id(&db, user.id)
+    .await
+    .unwrap();
+
+  let culture_ids = plantations
+    .iter()
+    .map(|plantation| plantation.culture_id)
+    .collect::<Vec<_>>();
+  let cultures = Culture::find_by_ids(&db, &culture_ids).await.unwrap();
+  let retention = chrono::Duration::days(plantation::retention_days().into());
+
+  let response = plantations
+    .into_iter()
+    .map(|plantation| {
+      let delete_at = plantation.delete_at.unwrap();
+
+      PlantationTrashResponse {
+        id: plantation.id,
+        alias: plantation.alias,
+        latitude: plantation.latitude,
+        longitude: plantation.longitude,
+        area: plantation.area,
+        planting_date: plantation.planting_date,
+        culture: cultures[&plantation.culture_id].clone(),
+        delete_at,
+        purge_date: delete_at + retention,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  response::json_ok(serde_json::json!({ "plantations": response }))
+}
+
+#[handler]
+async fn restore(db: Data<&database::DataBase>, user: Data<&User>, id: Path<String>) -> Response {
+  let plantation_result = match Uuid::parse_str(&id.0) {
+    Ok(id) => Plantation::find_deleted_by_uuid(&db, id).await.unwrap(),
+    Err(_) => None,
+  };
+
+  if plantation_result.is_none()
+    || Farm::plantation_role(&db, plantation_result.as_ref().unwrap().id, user.id)
+      .await
+      .is_none()
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  Plantation::restore(&db, plantation.id).await.unwrap();
+
+  response::json_ok(
+    serde_json::json!({ "plantation": Plantation::find_by_uuid(&db, plantation.id).await.unwrap() }),
+  )
+}
+
+/// Fields of the occurrences listing items
+const OCURRENCE_FIELDS: [&str; 12] = [
+  "id",
+  "user_id",
+  "plantation_id",
+  "pathogenic_id",
+  "image",
+  "occurrence_date",
+  "temperature",
+  "humidity",
+  "status",
+  "review_date",
+  "create_date",
+  "update_date",
+];
+
+#[derive(Deserialize)]
+struct OcurrencesQuery {
+  pathogenic_id: Option<i64>,
+  status: Option<String>,
+  /// Occurrence dates
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+}
+
 #[handler]
 async fn all_ocurrences(
   db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
   user: Data<&User>,
   plantation_id: Path<String>,
+  query: Query<OcurrencesQuery>,
+  page: Query<PageQuery>,
 ) -> Response {
+  let page = match page.0.page(
+    &["create_date", "occurrence_date"],
+    "-create_date",
+    &OCURRENCE_FIELDS,
+  ) {
+    Ok(page) => page,
+    Err(errors) => {
+      return response::json(
+        serde_json::json!({ "errors": errors }),
+        StatusCode::BAD_REQUEST,
+      );
+    }
+  };
+
   let plantation_result = find_plantation_by_id(&db, plantation_id.0.to_string(), user.id).await;
   if plantation_result.is_none() {
     return response::json(
@@ -380,9 +777,23 @@ async fn all_ocurrences(
 
   let plantation = plantation_result.unwrap();
 
-  let ocurrences = PlantationPathogenicOccurrences::get_by_plantation_id(&db, plantation.id).await;
+  let filter = OccurrenceFilter {
+    pathogenic_id: query.0.pathogenic_id,
+    status: query.0.status,
+    from: query.0.from.map(start_of_day),
+    to: query.0.to.map(end_of_day),
+  };
 
-  return response::json_ok(serde_json::json!({ "ocurrences": ocurrences.unwrap() }));
+  let mut ocurrences =
+    PlantationPathogenicOccurrences::search_by_plantation_id(&db, plantation.id, &filter, &page)
+      .await
+      .unwrap();
+
+  for ocurrence in ocurrences.iter_mut() {
+    ocurrence.image = storage::image_url(&storage, &ocurrence.image);
+  }
+
+  response::json_ok(page.envelope("ocurrences", ocurrences, |ocurrence| ocurrence.id))
 }
 
 #[handler]
@@ -406,6 +817,13 @@ async fn create_ocurrence(
 
   let plantation = plantation_result.unwrap();
 
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
   let ocurrence = PlantationPathogenicOccurrences::insert(
     &db,
     user.id,
@@ -422,123 +840,941 @@ async fn create_ocurrence(
   .await
   .unwrap();
 
-  // Send the notification to the all the closes plantations that have the same culture
-  let ocurrence_arc = Arc::new(ocurrence.clone());
-  let db_arc = Arc::new(db.clone());
-
-  tokio::spawn(async move {
-    println!("Sending notification for ocurrence: {:?}", ocurrence_arc);
-    let job = SendOcurrenceNotification {
-      ocurrence: ocurrence_arc.as_ref().clone(),
-      db: db_arc.as_ref(),
-    };
+  let _ = PlantationPathogenicOccurrences::enqueue_climate_task(&db, ocurrence.id).await;
 
-    job.run().await;
-  });
+  // Unconfirmed reports reach the closest plantations only when set to, the
+  // others once an agronomist confirms them
+  if plantation_pathogenic_occurrences::notify_unconfirmed() {
+    send_ocurrence_notification::spawn(&db, ocurrence.clone());
+  }
 
   return response::json_ok(serde_json::json!({ "ocurrence": ocurrence }));
 }
 
+/// What the agronomists said about the occurrence
 #[handler]
-async fn ocurrence_add_image(
+async fn ocurrence_reviews(
   db: Data<&database::DataBase>,
   user: Data<&User>,
   Path((plantation_id, ocurrence_id)): Path<(String, String)>,
-  mut multipart: Multipart,
 ) -> Response {
-  let plantation_result = find_plantation_by_id(&db, plantation_id.to_string(), user.id).await;
-  if plantation_result.is_none() {
+  let ocurrence_result = find_ocurrence_by_id(&db, plantation_id, ocurrence_id, user.id).await;
+  if ocurrence_result.is_none() {
     return response::json(
-      serde_json::json!({ "error": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      serde_json::json!({ "error": vec![JsonError::new("ocurrence".to_string(), "not found".to_string())] }),
       StatusCode::NOT_FOUND,
     );
   }
 
-  let ocurrence_result = PlantationPathogenicOccurrences::find_by_id(
-    &db,
-    uuid::Uuid::parse_str(&ocurrence_id.to_string()).unwrap(),
-  )
-  .await;
+  let ocurrence = ocurrence_result.unwrap();
+
+  let reviews = OccurrenceReview::all_by_occurrence_id(&db, ocurrence.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "status": ocurrence.status,
+    "reviews": reviews
+  }))
+}
 
-  if ocurrence_result.is_err() {
+#[handler]
+async fn ocurrence_climate(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((plantation_id, ocurrence_id)): Path<(String, String)>,
+) -> Response {
+  let ocurrence_result = find_ocurrence_by_id(&db, plantation_id, ocurrence_id, user.id).await;
+  if ocurrence_result.is_none() {
     return response::json(
       serde_json::json!({ "error": vec![JsonError::new("ocurrence".to_string(), "not found".to_string())] }),
       StatusCode::NOT_FOUND,
     );
   }
 
-  let mut file_path = String::new();
-
-  while let Ok(Some(field)) = multipart.next_field().await {
-    let mime = field.content_type().unwrap();
-
-    if mime != "image/png" && mime != "image/jpeg" && mime != "image/jpg" {
-      return response::json(
-        serde_json::json!({ "error": vec![JsonError::new("image".to_string(), "invalid".to_string())] }),
-        StatusCode::BAD_REQUEST,
-      );
-    }
+  let ocurrence = ocurrence_result.unwrap();
 
-    file_path = format!(
-      "/images/ocurrences/{}.{}",
-      ocurrence_result.as_ref().unwrap().id.to_string(),
-      "png".to_string()
-    );
+  let pathogenic = Pathogenic::find_by_id(&db, &ocurrence.pathogenic_id)
+    .await
+    .unwrap();
 
-    if let Ok(bytes) = field.bytes().await {
-      let mut file = File::create(&format!("app/{}", file_path)).unwrap();
-      file.write_all(&bytes).unwrap();
+  let temperatures = PlantationPathogenicOccurrences::get_temperatures(&db, ocurrence.id)
+    .await
+    .unwrap();
 
-      let _ = PlantationPathogenicOccurrences::add_image_by_id(
-        &db,
-        ocurrence_result.as_ref().unwrap().id,
-        file_path.as_mut().to_string(),
-      )
-      .await
-      .unwrap();
-    }
-  }
+  let humidities = PlantationPathogenicOccurrences::get_humidities(&db, ocurrence.id)
+    .await
+    .unwrap();
 
   response::json_ok(serde_json::json!({
-    "image": file_path
+    "ocurrence_id": ocurrence.id,
+    "temperature": ocurrence.temperature,
+    "humidity": ocurrence.humidity,
+    "favourable_range": {
+      "min_temperature": pathogenic.favourable_min_temperature,
+      "max_temperature": pathogenic.favourable_max_temperature,
+      "min_humidity": pathogenic.favourable_min_humidity,
+    },
+    "climate": OccurrenceClimate::new(temperatures, humidities, &pathogenic),
   }))
 }
 
-async fn find_plantation_by_id(
-  db: &DataBase,
-  plantation_id: String,
-  user_id: Uuid,
-) -> Option<Plantation> {
-  let plantation_id = uuid::Uuid::parse_str(&plantation_id);
+#[derive(Deserialize, Validate)]
+struct PlantationActivityCreate {
+  /// fungicide, treatment, scouting or other
+  #[garde(required, pattern(r"^(fungicide|treatment|scouting|other)$"))]
+  kind: Option<String>,
+  #[garde(
+    required,
+    pattern(r"([12]\d{3}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01]))")
+  )]
+  date: Option<String>,
+  /// Pathogen the product protects against, every one when empty
+  #[garde(skip)]
+  pathogenic_id: Option<i64>,
+  #[garde(length(min = 1, max = 100))]
+  product: Option<String>,
+  #[garde(custom(non_negative))]
+  dose: Option<f64>,
+  #[garde(length(min = 1, max = 20))]
+  dose_unit: Option<String>,
+  #[garde(custom(non_negative))]
+  area: Option<f64>,
+  /// Days after `date` the product keeps protecting the plantation
+  #[garde(range(min = 0, max = 90))]
+  protection_days: Option<i32>,
+  #[garde(length(max = 1000))]
+  notes: Option<String>,
+  #[garde(length(min = 1, max = 100))]
+  operator: Option<String>,
+}
 
-  if plantation_id.is_err() {
-    return None;
+fn non_negative(value: &Option<f64>, _: &()) -> garde::Result {
+  match value {
+    Some(value) if *value < 0.0 => Err(garde::Error::new("must not be negative")),
+    _ => Ok(()),
   }
+}
 
-  let plantation_result = Plantation::find_by_uuid(&db, plantation_id.unwrap()).await;
-  if plantation_result.is_err() {
-    return None;
+impl PlantationActivityCreate {
+  fn data(self) -> ActivityData {
+    ActivityData {
+      pathogenic_id: self.pathogenic_id,
+      kind: self.kind.unwrap(),
+      date: NaiveDate::parse_from_str(&self.date.unwrap(), "%Y-%m-%d").unwrap(),
+      product: self.product,
+      dose: self.dose,
+      dose_unit: self.dose_unit,
+      area: self.area,
+      protection_days: self.protection_days,
+      notes: self.notes,
+      operator: self.operator,
+    }
   }
+}
 
-  let plantation_result = plantation_result.unwrap();
+#[derive(Deserialize, Validate)]
+struct PlantationSeasonCreate {
+  #[garde(required)]
+  culture_id: Option<i64>,
+  #[garde(
+    required,
+    pattern(r"([12]\d{3}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01]))")
+  )]
+  planting_date: Option<String>,
+  #[garde(pattern(r"([12]\d{3}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01]))"))]
+  expected_harvest_date: Option<String>,
+  #[garde(length(max = 1000))]
+  notes: Option<String>,
+}
 
-  if plantation_result.user_id != user_id {
-    return None;
-  }
+#[derive(Deserialize, Validate)]
+struct PlantationSeasonClose {
+  #[garde(
+    required,
+    pattern(r"([12]\d{3}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01]))")
+  )]
+  harvest_date: Option<String>,
+  /// kg/ha
+  #[garde(custom(non_negative))]
+  harvest_yield: Option<f64>,
+  #[garde(length(max = 1000))]
+  notes: Option<String>,
+}
 
-  Some(plantation_result)
+#[derive(Deserialize)]
+struct RiskHistoryQuery {
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+  pathogenic_id: Option<i64>,
+  /// `observed` or `predicted`, both when empty
+  kind: Option<String>,
 }
 
-pub fn routes() -> Route {
-  Route::new()
-    .just_at(get(all).post(create).around(ensure_json::handle))
-    .at(
-      "/:id",
-      get(show)
-        .patch(update)
-        .delete(delete)
-        .around(ensure_json::handle),
-    )
+#[handler]
+async fn risk(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  let risks = PlantationRisk::current(&db, plantation.id).await.unwrap();
+  let forecast = PlantationRisk::forecast(&db, plantation.id).await.unwrap();
+
+  response::json_ok(serde_json::json!({
+    "plantation_id": plantation.id,
+    "risks": risks,
+    "forecast": forecast
+  }))
+}
+
+#[handler]
+async fn risk_history(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+  query: Query<RiskHistoryQuery>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  // Last 30 days by default
+  let to = query.0.to.unwrap_or(chrono::Local::now().date_naive());
+  let from = query.0.from.unwrap_or(to - chrono::Duration::days(30));
+
+  if from > to {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("from".to_string(), "must be before to".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let risks = PlantationRisk::history(
+    &db,
+    plantation.id,
+    query.0.pathogenic_id,
+    query.0.kind,
+    from,
+    to,
+  )
+  .await
+  .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "plantation_id": plantation.id,
+    "from": from,
+    "to": to,
+    "risks": risks
+  }))
+}
+
+/// Longest range of days of the spraying windows, the forecasts don't go
+/// further
+const MAX_SPRAY_DAYS: i64 = 16;
+
+#[derive(Deserialize)]
+struct SprayWindowQuery {
+  /// UTC days, today and the next two by default
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+  min_wind_speed: Option<f64>,
+  max_wind_speed: Option<f64>,
+  rain_free_hours_before: Option<i64>,
+  rain_free_hours_after: Option<i64>,
+  rain_threshold: Option<f64>,
+  max_rain_probability: Option<f64>,
+  min_temperature: Option<f64>,
+  max_temperature: Option<f64>,
+  min_humidity: Option<f64>,
+}
+
+#[handler]
+async fn spray_windows(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+  query: Query<SprayWindowQuery>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+  let query = query.0;
+
+  let from = query.from.unwrap_or(chrono::Utc::now().date_naive());
+  let to = query.to.unwrap_or(from + chrono::Duration::days(2));
+
+  if from > to {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("from".to_string(), "must be before to".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  if to - from > chrono::Duration::days(MAX_SPRAY_DAYS) {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("to".to_string(), format!("must be at most {} days after from", MAX_SPRAY_DAYS))] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let defaults = SprayRules::from_env();
+  let rules = SprayRules {
+    min_wind_speed: query.min_wind_speed.unwrap_or(defaults.min_wind_speed),
+    max_wind_speed: query.max_wind_speed.unwrap_or(defaults.max_wind_speed),
+    rain_free_hours_before: query
+      .rain_free_hours_before
+      .unwrap_or(defaults.rain_free_hours_before),
+    rain_free_hours_after: query
+      .rain_free_hours_after
+      .unwrap_or(defaults.rain_free_hours_after),
+    rain_threshold: query.rain_threshold.unwrap_or(defaults.rain_threshold),
+    max_rain_probability: query
+      .max_rain_probability
+      .unwrap_or(defaults.max_rain_probability),
+    min_temperature: query.min_temperature.unwrap_or(defaults.min_temperature),
+    max_temperature: query.max_temperature.unwrap_or(defaults.max_temperature),
+    min_humidity: query.min_humidity.unwrap_or(defaults.min_humidity),
+  };
+
+  let mut errors = vec![];
+  if rules.min_wind_speed > rules.max_wind_speed {
+    errors.push(JsonError::new(
+      "min_wind_speed".to_string(),
+      "must be below max_wind_speed".to_string(),
+    ));
+  }
+  if rules.min_temperature > rules.max_temperature {
+    errors.push(JsonError::new(
+      "min_temperature".to_string(),
+      "must be below max_temperature".to_string(),
+    ));
+  }
+  if !(0..=spray_window::MAX_RAIN_FREE_HOURS).contains(&rules.rain_free_hours_before) {
+    errors.push(JsonError::new(
+      "rain_free_hours_before".to_string(),
+      format!(
+        "must be between 0 and {}",
+        spray_window::MAX_RAIN_FREE_HOURS
+      ),
+    ));
+  }
+  if !(0..=spray_window::MAX_RAIN_FREE_HOURS).contains(&rules.rain_free_hours_after) {
+    errors.push(JsonError::new(
+      "rain_free_hours_after".to_string(),
+      format!(
+        "must be between 0 and {}",
+        spray_window::MAX_RAIN_FREE_HOURS
+      ),
+    ));
+  }
+  if !errors.is_empty() {
+    return response::json(
+      serde_json::json!({ "errors": errors }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let start = from.and_hms_opt(0, 0, 0).unwrap();
+  let end = to.and_hms_opt(23, 0, 0).unwrap();
+
+  let hours = match plantation.station_id {
+    Some(station_id) => {
+      let forecasts = StationForecast::between(
+        &db,
+        station_id,
+        start - chrono::Duration::hours(rules.rain_free_hours_before),
+        end + chrono::Duration::hours(rules.rain_free_hours_after),
+      )
+      .await
+      .unwrap();
+
+      rules.evaluate(&forecasts, start, end)
+    }
+    None => vec![],
+  };
+
+  response::json_ok(serde_json::json!({
+    "plantation_id": plantation.id,
+    "station_id": plantation.station_id,
+    "from": from,
+    "to": to,
+    "rules": rules,
+    "windows": spray_window::windows(&hours),
+    "hours": hours
+  }))
+}
+
+#[handler]
+async fn all_activities(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  let activities = PlantationActivity::all_by_plantation_id(&db, plantation.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "activities": activities }))
+}
+
+#[handler]
+async fn create_activity(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+  req: Json<PlantationActivityCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if let Some(pathogenic_id) = req.0.pathogenic_id {
+    if Pathogenic::find_by_id(&db, &pathogenic_id).await.is_err() {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("pathogenic".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  }
+
+  let id = PlantationActivity::insert(&db, plantation.id, &req.0.data())
+    .await
+    .unwrap();
+
+  response::json(
+    serde_json::json!({ "activity": PlantationActivity::find_by_id(&db, id).await.unwrap() }),
+    StatusCode::CREATED,
+  )
+}
+
+#[handler]
+async fn show_activity(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((plantation_id, activity_id)): Path<(String, String)>,
+) -> Response {
+  let activity_result = find_activity_by_id(&db, plantation_id, activity_id, user.id).await;
+  if activity_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("activity".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  response::json_ok(serde_json::json!({ "activity": activity_result.unwrap() }))
+}
+
+#[handler]
+async fn update_activity(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((plantation_id, activity_id)): Path<(String, String)>,
+  req: Json<PlantationActivityCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let activity_result = find_activity_by_id(&db, plantation_id, activity_id, user.id).await;
+  if activity_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("activity".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let activity = activity_result.unwrap();
+
+  if !can_edit(&db, activity.plantation_id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if let Some(pathogenic_id) = req.0.pathogenic_id {
+    if Pathogenic::find_by_id(&db, &pathogenic_id).await.is_err() {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("pathogenic".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  }
+
+  PlantationActivity::update(&db, activity.id, &req.0.data())
+    .await
+    .unwrap();
+
+  response::json_ok(
+    serde_json::json!({ "activity": PlantationActivity::find_by_id(&db, activity.id).await.unwrap() }),
+  )
+}
+
+#[handler]
+async fn delete_activity(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((plantation_id, activity_id)): Path<(String, String)>,
+) -> Response {
+  let activity_result = find_activity_by_id(&db, plantation_id, activity_id, user.id).await;
+  if activity_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("activity".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let activity = activity_result.unwrap();
+
+  if !can_edit(&db, activity.plantation_id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let _ = PlantationActivity::delete(&db, activity.id).await;
+
+  response::json_ok(serde_json::json!({ "activity": "ok" }))
+}
+
+#[handler]
+async fn all_seasons(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  let seasons = PlantationSeason::all_by_plantation_id(&db, plantation.id)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({ "seasons": seasons }))
+}
+
+#[handler]
+async fn start_season(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+  req: Json<PlantationSeasonCreate>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  if PlantationSeason::active(&db, plantation.id).await.is_ok() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("season".to_string(), "the current season must be closed first".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  if Culture::find_by_id(&db, req.0.culture_id.unwrap())
+    .await
+    .is_err()
+  {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("culture".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let season_id = PlantationSeason::start(
+    &db,
+    plantation.id,
+    req.0.culture_id.unwrap(),
+    NaiveDate::parse_from_str(&req.0.planting_date.unwrap(), "%Y-%m-%d")
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap(),
+    req
+      .0
+      .expected_harvest_date
+      .map(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap()),
+    req.0.notes,
+  )
+  .await
+  .unwrap();
+
+  response::json(
+    serde_json::json!({ "season": season_id }),
+    StatusCode::CREATED,
+  )
+}
+
+#[handler]
+async fn close_season(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+  req: Json<PlantationSeasonClose>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  if !can_edit(&db, plantation.id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let season_result = PlantationSeason::active(&db, plantation.id).await;
+  if season_result.is_err() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("season".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let season = season_result.unwrap();
+
+  let harvest_date = NaiveDate::parse_from_str(&req.0.harvest_date.unwrap(), "%Y-%m-%d").unwrap();
+
+  if harvest_date < season.planting_date.date() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("harvest_date".to_string(), "must be after the planting date".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  PlantationSeason::close(
+    &db,
+    season.id,
+    harvest_date,
+    req.0.harvest_yield,
+    req.0.notes,
+  )
+  .await
+  .unwrap();
+
+  response::json_ok(serde_json::json!({ "season": season.id }))
+}
+
+#[handler]
+async fn compare_seasons(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path(plantation_id): Path<String>,
+) -> Response {
+  let plantation_result = find_plantation_by_id(&db, plantation_id, user.id).await;
+  if plantation_result.is_none() {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let plantation = plantation_result.unwrap();
+
+  let summaries = PlantationSeason::summaries(&db, plantation.id)
+    .await
+    .unwrap();
+  let occurrences = PlantationSeason::occurrences(&db, plantation.id)
+    .await
+    .unwrap();
+
+  let seasons = summaries
+    .into_iter()
+    .map(|summary| {
+      let pathogenics = occurrences
+        .iter()
+        .filter(|occurrence| occurrence.season_id == summary.id)
+        .collect::<Vec<_>>();
+
+      serde_json::json!({
+        "season": summary,
+        "occurrences_by_pathogenic": pathogenics,
+      })
+    })
+    .collect::<Vec<_>>();
+
+  response::json_ok(serde_json::json!({
+    "plantation_id": plantation.id,
+    "seasons": seasons
+  }))
+}
+
+#[handler]
+async fn ocurrence_image(
+  db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
+  user: Data<&User>,
+  Path((plantation_id, ocurrence_id)): Path<(String, String)>,
+) -> Response {
+  let ocurrence_result = find_ocurrence_by_id(&db, plantation_id, ocurrence_id, user.id).await;
+  if ocurrence_result.is_none() {
+    return response::json(
+      serde_json::json!({ "error": vec![JsonError::new("ocurrence".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let ocurrence = ocurrence_result.unwrap();
+
+  if ocurrence.image.is_none() {
+    return response::json(
+      serde_json::json!({ "error": vec![JsonError::new("image".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  response::json_ok(serde_json::json!({
+    "image": storage::image_url(&storage, &ocurrence.image)
+  }))
+}
+
+#[handler]
+async fn ocurrence_add_image(
+  db: Data<&database::DataBase>,
+  storage: Data<&SharedStorage>,
+  user: Data<&User>,
+  Path((plantation_id, ocurrence_id)): Path<(String, String)>,
+  mut multipart: Multipart,
+) -> Response {
+  let ocurrence_result = find_ocurrence_by_id(&db, plantation_id, ocurrence_id, user.id).await;
+  if ocurrence_result.is_none() {
+    return response::json(
+      serde_json::json!({ "error": vec![JsonError::new("ocurrence".to_string(), "not found".to_string())] }),
+      StatusCode::NOT_FOUND,
+    );
+  }
+
+  let ocurrence = ocurrence_result.unwrap();
+
+  if !can_edit(&db, ocurrence.plantation_id, user.id).await {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("plantation".to_string(), "forbidden".to_string())] }),
+      StatusCode::FORBIDDEN,
+    );
+  }
+
+  let mut image: Option<String> = None;
+
+  while let Ok(Some(field)) = multipart.next_field().await {
+    if let Ok(bytes) = field.bytes().await {
+      // The format comes from the file itself, the Content-Type is the client's word
+      let (mime, extension) = match infer::get(&bytes).map(|kind| kind.mime_type()) {
+        Some("image/png") => ("image/png", "png"),
+        Some("image/jpeg") => ("image/jpeg", "jpg"),
+        _ => {
+          return response::json(
+            serde_json::json!({ "error": vec![JsonError::new("image".to_string(), "invalid".to_string())] }),
+            StatusCode::BAD_REQUEST,
+          );
+        }
+      };
+
+      let key = format!("ocurrences/{}.{}", ocurrence.id, extension);
+
+      if storage.put(&key, bytes, mime).await.is_err() {
+        return response::json(
+          serde_json::json!({ "error": vec![JsonError::new("image".to_string(), "could not be saved".to_string())] }),
+          StatusCode::INTERNAL_SERVER_ERROR,
+        );
+      }
+
+      // A previous upload with another extension would be left orphan
+      if let Some(previous) = ocurrence.image.as_ref() {
+        let previous = storage::key_from_image(previous);
+        if previous != key {
+          let _ = storage.delete(&previous).await;
+        }
+      }
+
+      PlantationPathogenicOccurrences::add_image_by_id(&db, ocurrence.id, key.clone())
+        .await
+        .unwrap();
+
+      // The photo is usually what the agronomist asked for
+      let _ = PlantationPathogenicOccurrences::reopen(&db, ocurrence.id).await;
+
+      image = Some(key);
+    }
+  }
+
+  response::json_ok(serde_json::json!({
+    "image": storage::image_url(&storage, &image)
+  }))
+}
+
+/// Plantation on a farm the user is a member of, or of a grower of an
+/// organization the user is an agronomist of
+async fn find_plantation_by_id(
+  db: &DataBase,
+  plantation_id: String,
+  user_id: Uuid,
+) -> Option<Plantation> {
+  let plantation_id = uuid::Uuid::parse_str(&plantation_id);
+
+  if plantation_id.is_err() {
+    return None;
+  }
+
+  let plantation_result = Plantation::find_by_uuid(&db, plantation_id.unwrap()).await;
+  if plantation_result.is_err() {
+    return None;
+  }
+
+  let plantation_result = plantation_result.unwrap();
+
+  if Farm::plantation_role(db, plantation_result.id, user_id)
+    .await
+    .is_none()
+    && !Organization::supports_plantation(db, plantation_result.id, user_id).await
+  {
+    return None;
+  }
+
+  Some(plantation_result)
+}
+
+/// Whether the user can change the plantation, viewers only read it
+async fn can_edit(db: &DataBase, plantation_id: Uuid, user_id: Uuid) -> bool {
+  Farm::plantation_role(db, plantation_id, user_id)
+    .await
+    .is_some_and(|role| role >= Role::Manager)
+}
+
+async fn find_ocurrence_by_id(
+  db: &DataBase,
+  plantation_id: String,
+  ocurrence_id: String,
+  user_id: Uuid,
+) -> Option<PlantationPathogenicOccurrences> {
+  let plantation = find_plantation_by_id(db, plantation_id, user_id).await?;
+
+  let ocurrence_id = uuid::Uuid::parse_str(&ocurrence_id).ok()?;
+
+  let ocurrence = PlantationPathogenicOccurrences::find_by_id(db, ocurrence_id)
+    .await
+    .ok()?;
+
+  if ocurrence.plantation_id != plantation.id {
+    return None;
+  }
+
+  Some(ocurrence)
+}
+
+async fn find_activity_by_id(
+  db: &DataBase,
+  plantation_id: String,
+  activity_id: String,
+  user_id: Uuid,
+) -> Option<PlantationActivity> {
+  let plantation = find_plantation_by_id(db, plantation_id, user_id).await?;
+
+  let activity_id = uuid::Uuid::parse_str(&activity_id).ok()?;
+
+  let activity = PlantationActivity::find_by_id(db, activity_id).await.ok()?;
+
+  if activity.plantation_id != plantation.id {
+    return None;
+  }
+
+  Some(activity)
+}
+
+pub fn routes() -> Route {
+  Route::new()
+    .just_at(get(all).post(create).around(ensure_json::handle))
+    .at("/trash", get(trash))
+    .at("/:id/restore", post(restore))
+    .at(
+      "/:id",
+      get(show)
+        .patch(update)
+        .delete(delete)
+        .around(ensure_json::handle),
+    )
     .at(
       "/:plantation_id/ocurrences",
       get(all_ocurrences)
@@ -547,6 +1783,41 @@ pub fn routes() -> Route {
     )
     .at(
       "/:plantation_id/ocurrences/:ocurrence_id/image",
-      post(ocurrence_add_image),
+      get(ocurrence_image).post(ocurrence_add_image),
+    )
+    .at(
+      "/:plantation_id/ocurrences/:ocurrence_id/climate",
+      get(ocurrence_climate),
+    )
+    .at(
+      "/:plantation_id/ocurrences/:ocurrence_id/reviews",
+      get(ocurrence_reviews),
+    )
+    .at("/:plantation_id/risk", get(risk))
+    .at("/:plantation_id/risk/history", get(risk_history))
+    .at("/:plantation_id/spray-windows", get(spray_windows))
+    .at(
+      "/:plantation_id/seasons",
+      get(all_seasons)
+        .post(start_season)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:plantation_id/seasons/close",
+      post(close_season).around(ensure_json::handle),
+    )
+    .at("/:plantation_id/seasons/compare", get(compare_seasons))
+    .at(
+      "/:plantation_id/activities",
+      get(all_activities)
+        .post(create_activity)
+        .around(ensure_json::handle),
+    )
+    .at(
+      "/:plantation_id/activities/:activity_id",
+      get(show_activity)
+        .patch(update_activity)
+        .delete(delete_activity)
+        .around(ensure_json::handle),
     )
 }
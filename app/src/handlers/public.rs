@@ -0,0 +1,162 @@
+use crate::{
+  models::public_occurrence::{PublicFilter, PublicOccurrence, Snap, DEFAULT_CELL},
+  utils::{
+    database,
+    response::{self, JsonError},
+  },
+};
+use chrono::NaiveDate;
+use poem::{
+  get,
+  handler,
+  http::StatusCode,
+  web::{Data, Query},
+  Response,
+  Route,
+};
+use serde::Deserialize;
+
+/// Smallest grid cell, in degrees (about 5 km): finer ones would point to the
+/// plantation
+const MIN_CELL: f64 = 0.05;
+const MAX_CELL: f64 = 5.0;
+
+const MAX_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+struct OccurrencesQuery {
+  /// `min_longitude,min_latitude,max_longitude,max_latitude`
+  bbox: Option<String>,
+  pathogenic_id: Option<i64>,
+  culture_id: Option<i64>,
+  /// The last 30 days by default
+  from: Option<NaiveDate>,
+  to: Option<NaiveDate>,
+  /// `grid`, the default, or `municipality`
+  snap: Option<String>,
+  /// Size of the grid cells in degrees
+  cell: Option<f64>,
+}
+
+/// Confirmed occurrences as GeoJSON, coarsened to a grid or to the
+/// municipalities, for anyone following the outbreaks.
+#[handler]
+async fn occurrences(db: Data<&database::DataBase>, query: Query<OccurrencesQuery>) -> Response {
+  let query = query.0;
+  let mut errors = vec![];
+
+  let bbox = match query.bbox.as_deref().map(parse_bbox) {
+    Some(Some(bbox)) => Some(bbox),
+    Some(None) => {
+      errors.push(JsonError::new(
+        "bbox".to_string(),
+        "must be min_longitude,min_latitude,max_longitude,max_latitude".to_string(),
+      ));
+      None
+    }
+    None => None,
+  };
+
+  let snap = match query.snap.as_deref().unwrap_or("grid") {
+    "grid" => {
+      let cell = query.cell.unwrap_or(DEFAULT_CELL);
+      if !(MIN_CELL..=MAX_CELL).contains(&cell) {
+        errors.push(JsonError::new(
+          "cell".to_string(),
+          format!("must be between {} and {}", MIN_CELL, MAX_CELL),
+        ));
+      }
+      Snap::Grid(cell)
+    }
+    "municipality" => Snap::Municipality,
+    _ => {
+      errors.push(JsonError::new(
+        "snap".to_string(),
+        "must be grid or municipality".to_string(),
+      ));
+      Snap::Municipality
+    }
+  };
+
+  let to = query.to.unwrap_or(chrono::Utc::now().date_naive());
+  let from = query.from.unwrap_or(to - chrono::Duration::days(30));
+
+  if from > to {
+    errors.push(JsonError::new(
+      "from".to_string(),
+      "must be before to".to_string(),
+    ));
+  } else if (to - from).num_days() > MAX_DAYS {
+    errors.push(JsonError::new(
+      "from".to_string(),
+      format!("must be at most {} days before to", MAX_DAYS),
+    ));
+  }
+
+  if !errors.is_empty() {
+    return response::json(
+      serde_json::json!({ "errors": errors }),
+      StatusCode::BAD_REQUEST,
+    );
+  }
+
+  let filter = PublicFilter {
+    bbox,
+    pathogenic_id: query.pathogenic_id,
+    culture_id: query.culture_id,
+    from,
+    to,
+    snap,
+  };
+
+  let occurrences = PublicOccurrence::search(&db, &filter).await.unwrap();
+
+  response::json_ok(serde_json::json!({
+    "type": "FeatureCollection",
+    "features": occurrences
+      .into_iter()
+      .map(|occurrence| serde_json::json!({
+        "type": "Feature",
+        "geometry": {
+          "type": "Point",
+          "coordinates": [occurrence.longitude, occurrence.latitude]
+        },
+        "properties": {
+          "pathogenic_id": occurrence.pathogenic_id,
+          "pathogenic_name": occurrence.pathogenic_name,
+          "culture_id": occurrence.culture_id,
+          "culture_name": occurrence.culture_name,
+          "city": occurrence.city,
+          "uf": occurrence.uf,
+          "occurrences": occurrence.occurrences,
+          "first_date": occurrence.first_date,
+          "last_date": occurrence.last_date
+        }
+      }))
+      .collect::<Vec<_>>()
+  }))
+}
+
+fn parse_bbox(bbox: &str) -> Option<[f64; 4]> {
+  let values = bbox
+    .split(',')
+    .map(|value| value.trim().parse::<f64>().ok())
+    .collect::<Option<Vec<_>>>()?;
+
+  let [min_longitude, min_latitude, max_longitude, max_latitude] = values[..] else {
+    return None;
+  };
+
+  let valid = (-180.0..=180.0).contains(&min_longitude)
+    && (-180.0..=180.0).contains(&max_longitude)
+    && (-90.0..=90.0).contains(&min_latitude)
+    && (-90.0..=90.0).contains(&max_latitude)
+    && min_longitude < max_longitude
+    && min_latitude < max_latitude;
+
+  valid.then_some([min_longitude, min_latitude, max_longitude, max_latitude])
+}
+
+pub fn routes() -> Route {
+  Route::new().at("/occurrences", get(occurrences))
+}
@@ -0,0 +1,81 @@
+use crate::{
+  models::{plantation_pathogenic_occurrences::RegionalFilter, tile::Tile, user::User},
+  utils::{
+    database,
+    response::{self, JsonError},
+  },
+};
+use poem::{
+  get,
+  handler,
+  http::{header, StatusCode},
+  web::{Data, Path},
+  Response,
+  Route,
+};
+
+/// Stations seldom change, the layers of the user do as they report. Every
+/// layer is behind the login, so none of them is left on shared caches.
+const STATIONS_CACHE: &str = "private, max-age=86400";
+const USER_CACHE: &str = "private, max-age=60";
+
+/// Mapbox Vector Tile of a layer: `occurrences`, `plantations` or `stations`.
+#[handler]
+async fn tile(
+  db: Data<&database::DataBase>,
+  user: Data<&User>,
+  Path((layer, z, x, y)): Path<(String, i32, i32, String)>,
+) -> Response {
+  let tile = y
+    .strip_suffix(".mvt")
+    .and_then(|y| y.parse::<i32>().ok())
+    .and_then(|y| Tile::new(z, x, y));
+
+  let Some(tile) = tile else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("tile".to_string(), "invalid".to_string())] }),
+      StatusCode::BAD_REQUEST,
+    );
+  };
+
+  let (bytes, cache) = match layer.as_str() {
+    "occurrences" => (
+      tile
+        .occurrences(&db, user.id, &RegionalFilter::for_user(&user, None))
+        .await,
+      USER_CACHE,
+    ),
+    "plantations" => (tile.plantations(&db, user.id).await, USER_CACHE),
+    "stations" => (tile.stations(&db).await, STATIONS_CACHE),
+    _ => {
+      return response::json(
+        serde_json::json!({ "errors": vec![JsonError::new("layer".to_string(), "not found".to_string())] }),
+        StatusCode::NOT_FOUND,
+      );
+    }
+  };
+
+  let Ok(bytes) = bytes else {
+    return response::json(
+      serde_json::json!({ "errors": vec![JsonError::new("tile".to_string(), "could not be loaded".to_string())] }),
+      StatusCode::INTERNAL_SERVER_ERROR,
+    );
+  };
+
+  // An empty tile is a valid answer, the map just has nothing to draw there
+  let status = if bytes.is_empty() {
+    StatusCode::NO_CONTENT
+  } else {
+    StatusCode::OK
+  };
+
+  Response::builder()
+    .status(status)
+    .content_type("application/vnd.mapbox-vector-tile")
+    .header(header::CACHE_CONTROL, cache)
+    .body(bytes)
+}
+
+pub fn routes() -> Route {
+  Route::new().at("/:layer/:z/:x/:y", get(tile))
+}
@@ -1,6 +1,9 @@
 use crate::{
   middleware::auth,
-  models::user::User,
+  models::{
+    plantation_pathogenic_occurrences::{MAX_RADIUS_KM, MIN_RADIUS_KM},
+    user::User,
+  },
   utils::{database::DataBase, response, response::JsonError},
 };
 use bcrypt::{hash, DEFAULT_COST};
@@ -11,6 +14,7 @@ use poem::{
   handler,
   http::StatusCode,
   post,
+  put,
   web::{Data, Json},
   EndpointExt,
   Response,
@@ -39,6 +43,22 @@ struct UserAddNotificationToken {
   notification_token: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Clone, Validate)]
+struct UserSettings {
+  /// Radius of the regional occurrences in km, the default one when empty
+  #[garde(custom(radius))]
+  occurrence_radius_km: Option<f64>,
+}
+
+fn radius(value: &Option<f64>, _: &()) -> garde::Result {
+  match value {
+    Some(value) if !(MIN_RADIUS_KM..=MAX_RADIUS_KM).contains(value) => Err(garde::Error::new(
+      format!("must be between {} and {}", MIN_RADIUS_KM, MAX_RADIUS_KM),
+    )),
+    _ => Ok(()),
+  }
+}
+
 #[handler]
 async fn create(req: Json<UserCreate>, pool: Data<&DataBase>) -> Response {
   if let Err(e) = req.0.validate(&()) {
@@ -94,10 +114,30 @@ async fn get_authenticated_user(user: Data<&User>, pool: Data<&DataBase>) -> Res
       "has_unviewed_notifications": has_unviewed_notifications,
       "born_date": user.born_date,
       "created_at": user.create_date,
+      "occurrence_radius_km": user.occurrence_radius_km,
     }
   }))
 }
 
+#[handler]
+async fn update_settings(
+  req: Json<UserSettings>,
+  user: Data<&User>,
+  pool: Data<&DataBase>,
+) -> Response {
+  if let Err(e) = req.0.validate(&()) {
+    return response::json(response::garde_error_to_json(e), StatusCode::BAD_REQUEST);
+  }
+
+  User::update_occurrence_radius(&pool, user.id, req.0.occurrence_radius_km)
+    .await
+    .unwrap();
+
+  response::json_ok(serde_json::json!({
+    "occurrence_radius_km": req.0.occurrence_radius_km,
+  }))
+}
+
 #[handler]
 async fn add_notification_token(
   req: Json<UserAddNotificationToken>,
@@ -147,6 +187,7 @@ pub fn routes() -> Route {
   Route::new()
     .just_at(get(get_authenticated_user).around(auth::handle))
     .at("/register", post(create))
+    .at("/settings", put(update_settings).around(auth::handle))
     .at("/notification", get(get_notifications).around(auth::handle))
     .at(
       "/notification-token",
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 
+pub mod purge_deleted_plantations;
 pub mod send_ocurrence_notification;
 
 #[async_trait]
@@ -0,0 +1,73 @@
+use super::Job;
+use crate::{
+  models::plantation::{self, Plantation},
+  storage::{self, SharedStorage},
+  utils::database::DataBase,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// How often the trash is checked for plantations past their retention
+const INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+pub(crate) struct PurgeDeletedPlantations<'a> {
+  pub db: &'a DataBase,
+  pub storage: &'a SharedStorage,
+}
+
+/// Runs the job when the app starts and every day after, in the background
+pub(crate) fn schedule(db: &DataBase, storage: &SharedStorage) {
+  let db = db.clone();
+  let storage = storage.clone();
+
+  tokio::spawn(async move {
+    let job = PurgeDeletedPlantations {
+      db: &db,
+      storage: &storage,
+    };
+    let mut interval = tokio::time::interval(INTERVAL);
+
+    loop {
+      interval.tick().await;
+      job.run().await;
+    }
+  });
+}
+
+#[async_trait]
+impl Job for PurgeDeletedPlantations<'_> {
+  async fn run(&self) {
+    let retention_days = plantation::retention_days();
+
+    let plantations = match Plantation::all_expired(self.db, retention_days).await {
+      Ok(plantations) => plantations,
+      Err(e) => {
+        println!("Error listing the deleted plantations: {:?}", e);
+        return;
+      }
+    };
+
+    for id in plantations {
+      // The rows go first, an image left behind is only a stray file while a
+      // missing one would break the plantation if the purge failed midway
+      let images = match Plantation::purge(self.db, id, retention_days).await {
+        Ok(Some(images)) => images,
+        Ok(None) => continue,
+        Err(e) => {
+          println!("Error purging plantation {}: {:?}", id, e);
+          continue;
+        }
+      };
+
+      for image in images {
+        let key = storage::key_from_image(&image);
+
+        if let Err(e) = self.storage.delete(&key).await {
+          println!("Error deleting image {} of plantation {}: {:?}", key, id, e);
+        }
+      }
+
+      println!("Purged plantation: {}", id);
+    }
+  }
+}
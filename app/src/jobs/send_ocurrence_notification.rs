@@ -3,7 +3,7 @@ use crate::{
   models::{
     pathogenic::Pathogenic,
     plantation::Plantation,
-    plantation_pathogenic_occurrences::PlantationPathogenicOccurrences,
+    plantation_pathogenic_occurrences::{self, PlantationPathogenicOccurrences, RegionalFilter},
     user::User,
   },
   utils::database::DataBase,
@@ -16,33 +16,62 @@ pub(crate) struct SendOcurrenceNotification<'a> {
   pub db: &'a DataBase,
 }
 
+/// Runs the job in the background, the request doesn't wait for the
+/// neighbours to be notified
+pub(crate) fn spawn(db: &DataBase, ocurrence: PlantationPathogenicOccurrences) {
+  let db = db.clone();
+
+  tokio::spawn(async move {
+    let job = SendOcurrenceNotification { ocurrence, db: &db };
+
+    job.run().await;
+  });
+}
+
 #[async_trait]
 impl Job for SendOcurrenceNotification<'_> {
   async fn run(&self) {
+    // The same filter of the regional occurrences, each neighbour within the
+    // radius they set
+    let filter = RegionalFilter::new(plantation_pathogenic_occurrences::default_radius_km());
+
+    if !filter.matches(&self.ocurrence) {
+      return;
+    }
+
+    // Once when it's reported, and once more when it's confirmed if they were
+    // told of it unconfirmed. Reviewed again, it isn't sent again.
+    if !PlantationPathogenicOccurrences::mark_notified(
+      self.db,
+      self.ocurrence.id,
+      &self.ocurrence.status,
+    )
+    .await
+    .unwrap_or(false)
+    {
+      return;
+    }
+
     println!("Sending notification for ocurrence: {:?}", self.ocurrence);
 
-    let plantation_ocurrence = Plantation::find_by_uuid(&self.db, self.ocurrence.plantation_id)
-      .await
-      .unwrap();
+    // Deleted since it was reported, it's no longer a neighbour
+    let Ok(plantation_ocurrence) =
+      Plantation::find_by_uuid(self.db, self.ocurrence.plantation_id).await
+    else {
+      return;
+    };
 
     let pathogenic = Pathogenic::find_by_id(&self.db, &self.ocurrence.pathogenic_id)
       .await
       .unwrap();
 
-    let plantation_closest_users =
-      Plantation::all_closest_to_plantation(&self.db, &plantation_ocurrence).await;
+    let closest_owners =
+      Plantation::owners_closest_to_plantation(&self.db, &plantation_ocurrence, &filter).await;
 
     let google_jwt_token = crate::utils::google_jwt::get_firebase_jwt().await;
 
-    let mut already_notified_users: Vec<uuid::Uuid> = Vec::new();
-
-    for plantation in plantation_closest_users {
-      if already_notified_users.contains(&plantation.user_id) {
-        println!("User already notified: {:?}", plantation.user_id);
-        continue;
-      }
-
-      let user_db = User::find_by_uuid(&self.db, plantation.user_id).await;
+    for owner_id in closest_owners {
+      let user_db = User::find_by_uuid(&self.db, owner_id).await;
 
       if user_db.is_err() {
         continue;
@@ -54,10 +83,17 @@ impl Job for SendOcurrenceNotification<'_> {
         continue;
       }
 
-      let message = format!(
-        "Uma ocorrência de {} foi registrada em uma plantação próxima.",
-        pathogenic.name
-      );
+      let message = if self.ocurrence.status == "confirmed" {
+        format!(
+          "Uma ocorrência de {} foi confirmada em uma plantação próxima.",
+          pathogenic.name
+        )
+      } else {
+        format!(
+          "Uma ocorrência de {} foi registrada em uma plantação próxima, ainda não confirmada por um agrônomo.",
+          pathogenic.name
+        )
+      };
 
       let body = serde_json::json!({
         "message": {
@@ -85,8 +121,6 @@ impl Job for SendOcurrenceNotification<'_> {
         .body(body.to_string())
         .send()
         .await;
-
-      already_notified_users.push(user.id);
     }
   }
 }
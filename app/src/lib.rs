@@ -0,0 +1,51 @@
+//! The API. A library so the benches call the same models the handlers do,
+//! the binary only serves it.
+pub mod handlers;
+pub mod jobs;
+pub mod mail;
+pub mod middleware;
+pub mod models;
+pub mod storage;
+pub mod utils;
+
+use dotenv::dotenv;
+use poem::{
+  error::NotFoundError,
+  listener::TcpListener,
+  middleware::{CatchPanic, Cors, Tracing},
+  EndpointExt,
+  Route,
+  Server,
+};
+use utils::database::DataBase;
+
+/// Runs the API on `APP_URL`, with the jobs it schedules
+pub async fn serve() -> Result<(), std::io::Error> {
+  dotenv().ok();
+
+  if std::env::var_os("RUST_LOG").is_none() {
+    std::env::set_var("RUST_LOG", "poem=debug");
+  }
+  tracing_subscriber::fmt::init();
+
+  let db: DataBase = DataBase::new().await;
+  let storage = storage::from_env();
+
+  jobs::purge_deleted_plantations::schedule(&db, &storage);
+
+  let app = Route::new()
+    .nest("/", handlers::all())
+    .with(Tracing)
+    .with(Cors::new())
+    .catch_error(|_: NotFoundError| async move { utils::request_error::catch_not_found_error() })
+    .catch_all_error(utils::request_error::catch_all_errors)
+    .with(CatchPanic::new().with_handler(|_| utils::request_error::catch_panic()))
+    .data(db)
+    .data(storage)
+    .data(mail::from_env());
+
+  Server::new(TcpListener::bind(std::env::var("APP_URL").unwrap()))
+    .name("cropi")
+    .run(app)
+    .await
+}
@@ -0,0 +1,15 @@
+use super::Mailer;
+use async_trait::async_trait;
+use std::io;
+
+/// Only logs the emails, for development.
+pub(crate) struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+  async fn send(&self, to: &str, subject: &str, body: &str) -> io::Result<()> {
+    println!("Email to {}: {}\n{}", to, subject, body);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use std::{io, sync::Arc};
+
+pub mod log;
+pub mod smtp;
+
+/// Sends the plain text emails of the app, like farm invitations.
+#[async_trait]
+pub(crate) trait Mailer: Send + Sync {
+  async fn send(&self, to: &str, subject: &str, body: &str) -> io::Result<()>;
+}
+
+pub(crate) type SharedMailer = Arc<dyn Mailer>;
+
+/// Builds the backend configured by `MAIL_DRIVER` (`log` or `smtp`).
+pub(crate) fn from_env() -> SharedMailer {
+  match std::env::var("MAIL_DRIVER")
+    .unwrap_or("log".to_string())
+    .as_str()
+  {
+    "smtp" => Arc::new(smtp::SmtpMailer::from_env()),
+    "log" => Arc::new(log::LogMailer),
+    driver => panic!("mail driver not supported: {}", driver),
+  }
+}
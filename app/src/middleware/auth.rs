@@ -32,11 +32,13 @@ pub(crate) async fn handle<E: Endpoint>(
     return Err(Error::from_status(StatusCode::UNAUTHORIZED));
   }
 
-  let user = User::find_by_uuid(
-    &req.data::<DataBase>().unwrap().clone(),
-    Uuid::from_str(claims.unwrap().claims.sub.as_str()).unwrap(),
-  )
-  .await;
+  let user_id = Uuid::from_str(claims.unwrap().claims.sub.as_str());
+
+  if user_id.is_err() {
+    return Err(Error::from_status(StatusCode::UNAUTHORIZED));
+  }
+
+  let user = User::find_by_uuid(&req.data::<DataBase>().unwrap().clone(), user_id.unwrap()).await;
 
   if user.is_err() {
     return Err(Error::from_status(StatusCode::UNAUTHORIZED));
@@ -0,0 +1,13 @@
+use crate::models::user::User;
+use poem::{http::StatusCode, Endpoint, Error, Request, Result};
+
+/// Must run after `auth::handle`, which sets the authenticated user.
+pub(crate) async fn handle<E: Endpoint>(next: E, req: Request) -> Result<<E as Endpoint>::Output> {
+  let is_admin = req.data::<User>().is_some_and(|user| user.role == "admin");
+
+  if !is_admin {
+    return Err(Error::from_status(StatusCode::FORBIDDEN));
+  }
+
+  return next.call(req).await;
+}
@@ -1,2 +1,3 @@
 pub mod auth;
-pub mod ensure_json;
+pub mod ensure_admin;
+pub mod ensure_json;
\ No newline at end of file
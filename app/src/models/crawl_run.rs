@@ -0,0 +1,54 @@
+use crate::utils::database::DataBase;
+use chrono::NaiveDateTime;
+use sqlx::Result;
+
+/// A crawler script execution, written by the crawler.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct CrawlRun {
+  pub id: i64,
+  pub script: String,
+  pub args: Option<String>,
+  pub status: String,
+  pub rows_inserted: i64,
+  pub rows_updated: i64,
+  pub rows_skipped: i64,
+  pub error: Option<String>,
+  pub start_date: NaiveDateTime,
+  pub end_date: Option<NaiveDateTime>,
+}
+
+impl CrawlRun {
+  pub(crate) async fn recent(
+    db: &DataBase,
+    limit: i64,
+    script: Option<String>,
+    status: Option<String>,
+  ) -> Result<Vec<CrawlRun>> {
+    sqlx::query_as!(
+      CrawlRun,
+      "
+      SELECT id,
+            script,
+            args,
+            status,
+            rows_inserted,
+            rows_updated,
+            rows_skipped,
+            error,
+            start_date,
+            end_date
+      FROM crawl_runs
+      WHERE ($1::varchar IS NULL OR script = $1)
+        AND ($2::varchar IS NULL OR status = $2)
+      ORDER BY start_date DESC
+      LIMIT $3
+      ",
+      script,
+      status,
+      limit
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -1,8 +1,9 @@
 use crate::utils::database::DataBase;
 use sqlx::Result;
+use std::collections::HashMap;
 
 #[derive(Debug, serde::Serialize, Clone)]
-pub(crate) struct Culture {
+pub struct Culture {
   pub id: i64,
   pub name: String,
   pub scientific_name: String,
@@ -11,7 +12,7 @@ pub(crate) struct Culture {
 }
 
 impl Culture {
-  pub(crate) async fn find_by_id(db: &DataBase, id: i64) -> Result<Culture> {
+  pub async fn find_by_id(db: &DataBase, id: i64) -> Result<Culture> {
     sqlx::query_as!(
       Culture,
       "
@@ -28,4 +29,30 @@ impl Culture {
     .fetch_one(&db.pool)
     .await
   }
+
+  /// The cultures of `ids` by id, in a single query
+  pub async fn find_by_ids(db: &DataBase, ids: &[i64]) -> Result<HashMap<i64, Culture>> {
+    let cultures = sqlx::query_as!(
+      Culture,
+      "
+            SELECT id,
+                   name,
+                   scientific_name,
+                   description,
+                   create_date
+            FROM cultures
+            WHERE id = ANY($1)
+        ",
+      ids
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    Ok(
+      cultures
+        .into_iter()
+        .map(|culture| (culture.id, culture))
+        .collect(),
+    )
+  }
 }
@@ -0,0 +1,307 @@
+use crate::{models::user::User, utils::database::DataBase};
+use chrono::NaiveDateTime;
+use sqlx::Result;
+use uuid::Uuid;
+
+/// What a member can do on a farm and its plantations: viewers read, managers
+/// also edit the plantations and invite viewers, owners manage the members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+  Viewer,
+  Manager,
+  Owner,
+}
+
+impl Role {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Role::Viewer => "viewer",
+      Role::Manager => "manager",
+      Role::Owner => "owner",
+    }
+  }
+
+  pub(crate) fn parse(role: &str) -> Option<Role> {
+    match role {
+      "viewer" => Some(Role::Viewer),
+      "manager" => Some(Role::Manager),
+      "owner" => Some(Role::Owner),
+      _ => None,
+    }
+  }
+}
+
+/// Group of plantations shared by its members.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct Farm {
+  pub id: Uuid,
+  pub name: String,
+  pub created_by: Uuid,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+}
+
+/// A farm as seen by one of its members
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct MemberFarm {
+  pub id: Uuid,
+  pub name: String,
+  pub role: String,
+  pub plantations: i64,
+  pub create_date: NaiveDateTime,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct FarmMember {
+  pub user_id: Uuid,
+  pub name: String,
+  pub email: String,
+  pub role: String,
+  pub create_date: NaiveDateTime,
+}
+
+impl Farm {
+  pub(crate) async fn all_by_user_id(db: &DataBase, user_id: Uuid) -> Result<Vec<MemberFarm>> {
+    sqlx::query_as!(
+      MemberFarm,
+      r#"
+      SELECT f.id,
+            f.name,
+            m.role,
+            (SELECT COUNT(*)
+             FROM plantations p
+             WHERE p.farm_id = f.id
+               AND p.delete_at IS NULL) AS "plantations!",
+            f.create_date
+      FROM farms f
+              JOIN farm_members m ON m.farm_id = f.id
+      WHERE m.user_id = $1
+        AND f.delete_at IS NULL
+      ORDER BY f.create_date
+      "#,
+      user_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_by_id(db: &DataBase, id: Uuid) -> Result<Farm> {
+    sqlx::query_as!(
+      Farm,
+      "
+      SELECT id, name, created_by, create_date, update_date
+      FROM farms
+      WHERE id = $1
+        AND delete_at IS NULL
+      ",
+      id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Creates the farm with `user_id` as its owner.
+  pub(crate) async fn insert(db: &DataBase, name: &str, user_id: Uuid) -> Result<Uuid> {
+    let mut transaction = db.pool.begin().await?;
+
+    let result = sqlx::query!(
+      "INSERT INTO farms (id, name, created_by) VALUES ($1, $2, $3) RETURNING id",
+      Uuid::new_v4(),
+      name,
+      user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "INSERT INTO farm_members (farm_id, user_id, role) VALUES ($1, $2, $3)",
+      result.id,
+      user_id,
+      Role::Owner.as_str()
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(result.id)
+  }
+
+  pub(crate) async fn update(db: &DataBase, id: Uuid, name: &str) -> Result<()> {
+    sqlx::query!(
+      "UPDATE farms SET name = $2, update_date = NOW() WHERE id = $1",
+      id,
+      name
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!("UPDATE farms SET delete_at = NOW() WHERE id = $1", id)
+      .execute(&db.pool)
+      .await
+      .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  /// First farm the user owns, a new one named after them when there's none.
+  pub(crate) async fn default_for_user(db: &DataBase, user: &User) -> Result<Uuid> {
+    let owned = sqlx::query_scalar!(
+      "
+      SELECT f.id
+      FROM farms f
+              JOIN farm_members m ON m.farm_id = f.id
+      WHERE m.user_id = $1
+        AND m.role = $2
+        AND f.delete_at IS NULL
+      ORDER BY f.create_date
+      LIMIT 1
+      ",
+      user.id,
+      Role::Owner.as_str()
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    match owned {
+      Some(id) => Ok(id),
+      None => Farm::insert(db, &format!("Fazenda de {}", user.name), user.id).await,
+    }
+  }
+
+  /// Plantations on the farm, the ones in the trash too: they can only be
+  /// restored to a farm that still exists
+  pub(crate) async fn plantations_count(db: &DataBase, id: Uuid) -> Result<i64> {
+    sqlx::query_scalar!(
+      r#"SELECT COUNT(*) AS "count!" FROM plantations WHERE farm_id = $1"#,
+      id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Role of the user on the farm, `None` when they aren't a member
+  pub(crate) async fn role(db: &DataBase, id: Uuid, user_id: Uuid) -> Option<Role> {
+    sqlx::query_scalar!(
+      "
+      SELECT m.role
+      FROM farm_members m
+              JOIN farms f ON f.id = m.farm_id
+      WHERE m.farm_id = $1
+        AND m.user_id = $2
+        AND f.delete_at IS NULL
+      ",
+      id,
+      user_id
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .ok()
+    .flatten()
+    .and_then(|role| Role::parse(&role))
+  }
+
+  /// Role of the user on the farm of the plantation
+  pub(crate) async fn plantation_role(
+    db: &DataBase,
+    plantation_id: Uuid,
+    user_id: Uuid,
+  ) -> Option<Role> {
+    sqlx::query_scalar!(
+      "
+      SELECT m.role
+      FROM plantations p
+              JOIN farm_members m ON m.farm_id = p.farm_id
+              JOIN farms f ON f.id = p.farm_id
+      WHERE p.id = $1
+        AND m.user_id = $2
+        AND f.delete_at IS NULL
+      ",
+      plantation_id,
+      user_id
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .ok()
+    .flatten()
+    .and_then(|role| Role::parse(&role))
+  }
+}
+
+impl FarmMember {
+  pub(crate) async fn all_by_farm_id(db: &DataBase, farm_id: Uuid) -> Result<Vec<FarmMember>> {
+    sqlx::query_as!(
+      FarmMember,
+      "
+      SELECT m.user_id,
+            u.name,
+            u.email,
+            m.role,
+            m.create_date
+      FROM farm_members m
+              JOIN users u ON u.id = m.user_id
+      WHERE m.farm_id = $1
+      ORDER BY m.create_date
+      ",
+      farm_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Adds the user to the farm, or changes their role if they're a member.
+  pub(crate) async fn save(db: &DataBase, farm_id: Uuid, user_id: Uuid, role: Role) -> Result<()> {
+    sqlx::query!(
+      "INSERT INTO farm_members (farm_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (farm_id, user_id) DO UPDATE
+            SET role        = $3,
+                update_date = NOW()",
+      farm_id,
+      user_id,
+      role.as_str()
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, farm_id: Uuid, user_id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "DELETE FROM farm_members WHERE farm_id = $1 AND user_id = $2",
+      farm_id,
+      user_id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn owners_count(db: &DataBase, farm_id: Uuid) -> Result<i64> {
+    sqlx::query_scalar!(
+      r#"SELECT COUNT(*) AS "count!" FROM farm_members WHERE farm_id = $1 AND role = $2"#,
+      farm_id,
+      Role::Owner.as_str()
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -0,0 +1,198 @@
+use super::farm::Role;
+use crate::utils::database::DataBase;
+use chrono::NaiveDateTime;
+use sqlx::Result;
+use uuid::Uuid;
+
+/// Days an invitation can be accepted
+const INVITATION_DAYS: i32 = 7;
+
+/// Invitation sent by email to join a farm. It's accepted by the user with the
+/// same email presenting the token.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct FarmInvitation {
+  pub id: Uuid,
+  pub farm_id: Uuid,
+  pub farm_name: String,
+  pub email: String,
+  pub role: String,
+  #[serde(skip_serializing)]
+  pub token: String,
+  pub invited_by: Uuid,
+  pub create_date: NaiveDateTime,
+  pub expire_date: NaiveDateTime,
+}
+
+impl FarmInvitation {
+  /// Invites `email`, replacing a pending invitation to the same farm.
+  pub(crate) async fn insert(
+    db: &DataBase,
+    farm_id: Uuid,
+    email: &str,
+    role: Role,
+    invited_by: Uuid,
+  ) -> Result<FarmInvitation> {
+    let mut transaction = db.pool.begin().await?;
+
+    sqlx::query!(
+      "UPDATE farm_invitations
+        SET revoke_date = NOW()
+        WHERE farm_id = $1
+          AND LOWER(email) = LOWER($2)
+          AND accept_date IS NULL
+          AND revoke_date IS NULL",
+      farm_id,
+      email
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    let invitation = sqlx::query_as!(
+      FarmInvitation,
+      r#"
+      WITH invitation AS (
+          INSERT INTO farm_invitations (id, farm_id, email, role, token, invited_by, expire_date)
+              VALUES ($1, $2, $3, $4, $5, $6, NOW() + MAKE_INTERVAL(days => $7))
+              RETURNING *)
+      SELECT i.id,
+            i.farm_id,
+            f.name AS farm_name,
+            i.email,
+            i.role,
+            i.token,
+            i.invited_by,
+            i.create_date,
+            i.expire_date
+      FROM invitation i
+              JOIN farms f ON f.id = i.farm_id
+      "#,
+      Uuid::new_v4(),
+      farm_id,
+      email,
+      role.as_str(),
+      Uuid::new_v4().simple().to_string(),
+      invited_by,
+      INVITATION_DAYS
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(invitation)
+  }
+
+  pub(crate) async fn pending_by_farm_id(
+    db: &DataBase,
+    farm_id: Uuid,
+  ) -> Result<Vec<FarmInvitation>> {
+    sqlx::query_as!(
+      FarmInvitation,
+      "
+      SELECT i.id,
+            i.farm_id,
+            f.name AS farm_name,
+            i.email,
+            i.role,
+            i.token,
+            i.invited_by,
+            i.create_date,
+            i.expire_date
+      FROM farm_invitations i
+              JOIN farms f ON f.id = i.farm_id
+      WHERE i.farm_id = $1
+        AND i.accept_date IS NULL
+        AND i.revoke_date IS NULL
+        AND i.expire_date > NOW()
+      ORDER BY i.create_date DESC
+      ",
+      farm_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn pending_by_email(db: &DataBase, email: &str) -> Result<Vec<FarmInvitation>> {
+    sqlx::query_as!(
+      FarmInvitation,
+      "
+      SELECT i.id,
+            i.farm_id,
+            f.name AS farm_name,
+            i.email,
+            i.role,
+            i.token,
+            i.invited_by,
+            i.create_date,
+            i.expire_date
+      FROM farm_invitations i
+              JOIN farms f ON f.id = i.farm_id
+      WHERE LOWER(i.email) = LOWER($1)
+        AND i.accept_date IS NULL
+        AND i.revoke_date IS NULL
+        AND i.expire_date > NOW()
+        AND f.delete_at IS NULL
+      ORDER BY i.create_date DESC
+      ",
+      email
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_pending_by_token(db: &DataBase, token: &str) -> Result<FarmInvitation> {
+    sqlx::query_as!(
+      FarmInvitation,
+      "
+      SELECT i.id,
+            i.farm_id,
+            f.name AS farm_name,
+            i.email,
+            i.role,
+            i.token,
+            i.invited_by,
+            i.create_date,
+            i.expire_date
+      FROM farm_invitations i
+              JOIN farms f ON f.id = i.farm_id
+      WHERE i.token = $1
+        AND i.accept_date IS NULL
+        AND i.revoke_date IS NULL
+        AND i.expire_date > NOW()
+        AND f.delete_at IS NULL
+      ",
+      token
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn accept(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE farm_invitations SET accept_date = NOW() WHERE id = $1",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn revoke(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE farm_invitations SET revoke_date = NOW() WHERE id = $1",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,133 @@
+use crate::{models::public_occurrence, utils::database::DataBase};
+use chrono::NaiveDateTime;
+use sqlx::Result;
+
+/// A cluster of recent confirmed occurrences of a pathogen, written by the
+/// crawler's hotspots script. Only the centroid, at the center of its grid
+/// cell like the public occurrences, and the extent in whole km are exposed,
+/// never the occurrences.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct Hotspot {
+  pub id: i64,
+  pub pathogenic_id: i64,
+  pub pathogenic_name: String,
+  pub status: String,
+  pub latitude: f64,
+  pub longitude: f64,
+  pub radius_km: f64,
+  pub size: i32,
+  /// Occurrences gained since the previous run
+  pub growth: i32,
+  pub emerging: bool,
+  pub create_date: NaiveDateTime,
+  pub update_date: NaiveDateTime,
+  pub close_date: Option<NaiveDateTime>,
+}
+
+/// Where a hotspot was and how big it was on a run
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct HotspotSnapshot {
+  pub latitude: f64,
+  pub longitude: f64,
+  pub radius_km: f64,
+  pub size: i32,
+  pub create_date: NaiveDateTime,
+}
+
+impl Hotspot {
+  /// Hotspots with `status`, all of them when `None`, the most recent first
+  pub(crate) async fn search(
+    db: &DataBase,
+    pathogenic_id: Option<i64>,
+    status: Option<String>,
+    emerging: Option<bool>,
+  ) -> Result<Vec<Hotspot>> {
+    sqlx::query_as!(
+      Hotspot,
+      r#"
+      SELECT h.id,
+            h.pathogenic_id,
+            pa.name                    AS pathogenic_name,
+            h.status,
+            (FLOOR(st_x(h.location::geometry) / $4) + 0.5) * $4 AS "latitude!",
+            (FLOOR(st_y(h.location::geometry) / $4) + 0.5) * $4 AS "longitude!",
+            ROUND(h.radius_km)                                     AS "radius_km!",
+            h.size,
+            h.growth,
+            h.emerging,
+            h.create_date,
+            h.update_date,
+            h.close_date
+      FROM hotspots h
+              JOIN pathogenics pa ON pa.id = h.pathogenic_id
+      WHERE ($1::bigint IS NULL OR h.pathogenic_id = $1)
+        AND ($2::varchar IS NULL OR h.status = $2)
+        AND ($3::boolean IS NULL OR h.emerging = $3)
+      ORDER BY h.update_date DESC
+      "#,
+      pathogenic_id,
+      status,
+      emerging,
+      public_occurrence::DEFAULT_CELL
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_by_id(db: &DataBase, id: i64) -> Result<Option<Hotspot>> {
+    sqlx::query_as!(
+      Hotspot,
+      r#"
+      SELECT h.id,
+            h.pathogenic_id,
+            pa.name                    AS pathogenic_name,
+            h.status,
+            (FLOOR(st_x(h.location::geometry) / $2) + 0.5) * $2 AS "latitude!",
+            (FLOOR(st_y(h.location::geometry) / $2) + 0.5) * $2 AS "longitude!",
+            ROUND(h.radius_km)                                     AS "radius_km!",
+            h.size,
+            h.growth,
+            h.emerging,
+            h.create_date,
+            h.update_date,
+            h.close_date
+      FROM hotspots h
+              JOIN pathogenics pa ON pa.id = h.pathogenic_id
+      WHERE h.id = $1
+      "#,
+      id,
+      public_occurrence::DEFAULT_CELL
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
+
+impl HotspotSnapshot {
+  /// Every run of the hotspot, the first one first
+  pub(crate) async fn all_by_hotspot_id(
+    db: &DataBase,
+    hotspot_id: i64,
+  ) -> Result<Vec<HotspotSnapshot>> {
+    sqlx::query_as!(
+      HotspotSnapshot,
+      r#"
+      SELECT (FLOOR(st_x(location::geometry) / $2) + 0.5) * $2 AS "latitude!",
+            (FLOOR(st_y(location::geometry) / $2) + 0.5) * $2 AS "longitude!",
+            ROUND(radius_km)                                 AS "radius_km!",
+            size,
+            create_date
+      FROM hotspot_snapshots
+      WHERE hotspot_id = $1
+      ORDER BY create_date
+      "#,
+      hotspot_id,
+      public_occurrence::DEFAULT_CELL
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -1,6 +1,19 @@
-pub(crate) mod culture;
-pub(crate) mod pathogenic;
-pub(crate) mod plantation;
-pub(crate) mod plantation_pathogenic_occurrences;
-pub(crate) mod stations;
+pub(crate) mod crawl_run;
+pub mod culture;
+pub(crate) mod farm;
+pub(crate) mod farm_invitation;
+pub(crate) mod hotspot;
+pub(crate) mod occurrence_review;
+pub(crate) mod organization;
+pub mod pathogenic;
+pub mod plantation;
+pub(crate) mod plantation_activity;
+pub mod plantation_pathogenic_occurrences;
+pub(crate) mod plantation_risk;
+pub(crate) mod plantation_season;
+pub(crate) mod public_occurrence;
+pub(crate) mod spray_window;
+pub(crate) mod station_forecast;
+pub mod stations;
+pub(crate) mod tile;
 pub(crate) mod user;
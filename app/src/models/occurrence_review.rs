@@ -0,0 +1,147 @@
+use crate::utils::database::DataBase;
+use chrono::NaiveDateTime;
+use sqlx::Result;
+use uuid::Uuid;
+
+/// An agronomist's verdict on a reported occurrence, the last one is the
+/// status of the occurrence.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OccurrenceReview {
+  pub id: i64,
+  pub occurrence_id: Uuid,
+  pub reviewer_id: Uuid,
+  pub reviewer_name: String,
+  pub status: String,
+  pub comment: Option<String>,
+  pub create_date: NaiveDateTime,
+}
+
+/// An occurrence waiting for an agronomist of the organization
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct ReviewQueueItem {
+  pub id: Uuid,
+  pub plantation_id: Uuid,
+  pub plantation_alias: Option<String>,
+  pub user_id: Uuid,
+  pub user_name: String,
+  pub pathogenic_id: i64,
+  pub pathogenic_name: String,
+  pub image: Option<String>,
+  pub occurrence_date: NaiveDateTime,
+  pub status: String,
+  pub create_date: NaiveDateTime,
+}
+
+impl OccurrenceReview {
+  pub(crate) async fn all_by_occurrence_id(
+    db: &DataBase,
+    occurrence_id: Uuid,
+  ) -> Result<Vec<OccurrenceReview>> {
+    sqlx::query_as!(
+      OccurrenceReview,
+      "
+      SELECT r.id,
+            r.occurrence_id,
+            r.reviewer_id,
+            u.name AS reviewer_name,
+            r.status,
+            r.comment,
+            r.create_date
+      FROM plantation_pathogenic_occurrence_reviews r
+              JOIN users u ON u.id = r.reviewer_id
+      WHERE r.occurrence_id = $1
+      ORDER BY r.create_date
+      ",
+      occurrence_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Saves the review and moves the occurrence to its status.
+  pub(crate) async fn insert(
+    db: &DataBase,
+    occurrence_id: Uuid,
+    reviewer_id: Uuid,
+    status: &str,
+    comment: Option<String>,
+  ) -> Result<()> {
+    let mut transaction = db.pool.begin().await?;
+
+    sqlx::query!(
+      "INSERT INTO plantation_pathogenic_occurrence_reviews (occurrence_id, reviewer_id, status, comment)
+        VALUES ($1, $2, $3, $4)",
+      occurrence_id,
+      reviewer_id,
+      status,
+      comment
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "UPDATE plantation_pathogenic_occurrences
+        SET status = $2, review_date = NOW(), update_date = NOW()
+        WHERE id = $1",
+      occurrence_id,
+      status
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(())
+  }
+
+  /// Occurrences on the plantations of the organization's growers still to be
+  /// confirmed or rejected, the oldest first. The reviewer's own reports and
+  /// the ones on their farms are left out.
+  pub(crate) async fn queue(
+    db: &DataBase,
+    organization_id: Uuid,
+    reviewer_id: Uuid,
+  ) -> Result<Vec<ReviewQueueItem>> {
+    sqlx::query_as!(
+      ReviewQueueItem,
+      "
+      SELECT o.id,
+            o.plantation_id,
+            p.alias AS plantation_alias,
+            o.user_id,
+            u.name  AS user_name,
+            o.pathogenic_id,
+            pa.name AS pathogenic_name,
+            o.image,
+            o.occurrence_date,
+            o.status,
+            o.create_date
+      FROM plantation_pathogenic_occurrences o
+              JOIN plantations p ON p.id = o.plantation_id
+              JOIN users u ON u.id = o.user_id
+              JOIN pathogenics pa ON pa.id = o.pathogenic_id
+      WHERE o.status IN ('reported', 'needs_more_info')
+        AND p.delete_at IS NULL
+        AND p.farm_id IN (SELECT fm.farm_id
+                          FROM farm_members fm
+                                  JOIN organization_members m ON m.user_id = fm.user_id
+                          WHERE m.organization_id = $1
+                            AND fm.role = 'owner')
+        AND o.user_id <> $2
+        AND NOT EXISTS(SELECT 1
+                       FROM farm_members own
+                       WHERE own.farm_id = p.farm_id
+                         AND own.user_id = $2)
+      ORDER BY o.create_date
+      ",
+      organization_id,
+      reviewer_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -0,0 +1,473 @@
+use crate::utils::database::DataBase;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::Result;
+use uuid::Uuid;
+
+/// What a member does in an organization: growers are members, agronomists
+/// also read the plantations of every member and review their reports, admins
+/// manage the members and the alert policies. Being an admin doesn't make one
+/// an agronomist, the role is granted on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OrganizationRole {
+  Member,
+  Agronomist,
+  Admin,
+}
+
+impl OrganizationRole {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      OrganizationRole::Member => "member",
+      OrganizationRole::Agronomist => "agronomist",
+      OrganizationRole::Admin => "admin",
+    }
+  }
+
+  pub(crate) fn parse(role: &str) -> Option<OrganizationRole> {
+    match role {
+      "member" => Some(OrganizationRole::Member),
+      "agronomist" => Some(OrganizationRole::Agronomist),
+      "admin" => Some(OrganizationRole::Admin),
+      _ => None,
+    }
+  }
+}
+
+/// A cooperative or association giving technical support to its growers.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct Organization {
+  pub id: Uuid,
+  pub name: String,
+  /// Shared with the growers to join
+  pub code: String,
+  pub created_by: Uuid,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+}
+
+/// An organization as seen by one of its members
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct MemberOrganization {
+  pub id: Uuid,
+  pub name: String,
+  pub role: String,
+  pub members: i64,
+  pub create_date: NaiveDateTime,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OrganizationMember {
+  pub user_id: Uuid,
+  pub name: String,
+  pub email: String,
+  pub role: String,
+  pub create_date: NaiveDateTime,
+}
+
+/// How the organization wants its members alerted of a pathogen, on top of
+/// the alerts they get anyway.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct AlertPolicy {
+  pub id: i64,
+  /// Every pathogen without a policy of its own when empty
+  pub pathogenic_id: Option<i64>,
+  /// `medium` also alerts the growers of plantations on medium risk
+  pub min_level: String,
+  /// The agronomists get the alerts of every member too
+  pub notify_agronomists: bool,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+}
+
+/// Occurrences on the plantations of the members, counted by pathogen, by
+/// municipality of the station or by week. Only the fields of the `dimension`
+/// are filled.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OccurrenceCount {
+  #[serde(skip_serializing)]
+  pub dimension: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub pathogenic_id: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub pathogenic_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub city: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub uf: Option<String>,
+  /// Monday of the week
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub week: Option<NaiveDate>,
+  pub occurrences: i64,
+  pub plantations: i64,
+}
+
+impl Organization {
+  pub(crate) async fn all_by_user_id(
+    db: &DataBase,
+    user_id: Uuid,
+  ) -> Result<Vec<MemberOrganization>> {
+    sqlx::query_as!(
+      MemberOrganization,
+      r#"
+      SELECT o.id,
+            o.name,
+            m.role,
+            (SELECT COUNT(*)
+             FROM organization_members om
+             WHERE om.organization_id = o.id) AS "members!",
+            o.create_date
+      FROM organizations o
+              JOIN organization_members m ON m.organization_id = o.id
+      WHERE m.user_id = $1
+        AND o.delete_at IS NULL
+      ORDER BY o.create_date
+      "#,
+      user_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_by_id(db: &DataBase, id: Uuid) -> Result<Organization> {
+    sqlx::query_as!(
+      Organization,
+      "
+      SELECT id, name, code, created_by, create_date, update_date
+      FROM organizations
+      WHERE id = $1
+        AND delete_at IS NULL
+      ",
+      id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_by_code(db: &DataBase, code: &str) -> Result<Organization> {
+    sqlx::query_as!(
+      Organization,
+      "
+      SELECT id, name, code, created_by, create_date, update_date
+      FROM organizations
+      WHERE UPPER(code) = UPPER($1)
+        AND delete_at IS NULL
+      ",
+      code
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Creates the organization with `user_id` as its admin.
+  pub(crate) async fn insert(db: &DataBase, name: &str, user_id: Uuid) -> Result<Uuid> {
+    let mut transaction = db.pool.begin().await?;
+
+    let result = sqlx::query!(
+      "INSERT INTO organizations (id, name, code, created_by) VALUES ($1, $2, $3, $4) RETURNING id",
+      Uuid::new_v4(),
+      name,
+      Uuid::new_v4().simple().to_string()[..8].to_uppercase(),
+      user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)",
+      result.id,
+      user_id,
+      OrganizationRole::Admin.as_str()
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(result.id)
+  }
+
+  pub(crate) async fn update(db: &DataBase, id: Uuid, name: &str) -> Result<()> {
+    sqlx::query!(
+      "UPDATE organizations SET name = $2, update_date = NOW() WHERE id = $1",
+      id,
+      name
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE organizations SET delete_at = NOW() WHERE id = $1",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  /// Role of the user on the organization, `None` when they aren't a member
+  pub(crate) async fn role(db: &DataBase, id: Uuid, user_id: Uuid) -> Option<OrganizationRole> {
+    sqlx::query_scalar!(
+      "
+      SELECT m.role
+      FROM organization_members m
+              JOIN organizations o ON o.id = m.organization_id
+      WHERE m.organization_id = $1
+        AND m.user_id = $2
+        AND o.delete_at IS NULL
+      ",
+      id,
+      user_id
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .ok()
+    .flatten()
+    .and_then(|role| OrganizationRole::parse(&role))
+  }
+
+  /// Whether the user is an agronomist of an organization the owner of the
+  /// plantation's farm is a member of
+  pub(crate) async fn supports_plantation(
+    db: &DataBase,
+    plantation_id: Uuid,
+    user_id: Uuid,
+  ) -> bool {
+    sqlx::query_scalar!(
+      r#"
+      SELECT EXISTS(SELECT 1
+                    FROM plantations p
+                            JOIN farm_members fm ON fm.farm_id = p.farm_id
+                            JOIN organization_members grower ON grower.user_id = fm.user_id
+                            JOIN organization_members agronomist
+                                 ON agronomist.organization_id = grower.organization_id
+                            JOIN organizations o ON o.id = grower.organization_id
+                    WHERE p.id = $1
+                      AND fm.role = 'owner'
+                      AND agronomist.user_id = $2
+                      AND agronomist.role = 'agronomist'
+                      AND o.delete_at IS NULL) AS "exists!"
+      "#,
+      plantation_id,
+      user_id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .unwrap_or(false)
+  }
+
+  /// Whether the plantation is on a farm owned by a member
+  pub(crate) async fn has_plantation(db: &DataBase, id: Uuid, plantation_id: Uuid) -> bool {
+    sqlx::query_scalar!(
+      r#"
+      SELECT EXISTS(SELECT 1
+                    FROM plantations p
+                            JOIN farm_members fm ON fm.farm_id = p.farm_id
+                            JOIN organization_members m ON m.user_id = fm.user_id
+                    WHERE p.id = $2
+                      AND p.delete_at IS NULL
+                      AND fm.role = 'owner'
+                      AND m.organization_id = $1) AS "exists!"
+      "#,
+      id,
+      plantation_id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .unwrap_or(false)
+  }
+
+  /// Occurrences between `from` and `to` on the farms owned by the members
+  pub(crate) async fn occurrence_counts(
+    db: &DataBase,
+    id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+  ) -> Result<Vec<OccurrenceCount>> {
+    sqlx::query_as!(
+      OccurrenceCount,
+      r#"
+      SELECT CASE
+                 WHEN GROUPING(pa.id) = 0 THEN 'pathogenic'
+                 WHEN GROUPING(s.city) = 0 THEN 'municipality'
+                 ELSE 'week'
+                 END                                        AS "dimension!",
+            pa.id                                          AS "pathogenic_id?",
+            pa.name                                        AS "pathogenic_name?",
+            s.city                                         AS "city?",
+            s.uf                                           AS "uf?",
+            DATE_TRUNC('week', o.occurrence_date)::date    AS "week?",
+            COUNT(*)                                       AS "occurrences!",
+            COUNT(DISTINCT o.plantation_id)                AS "plantations!"
+      FROM plantation_pathogenic_occurrences o
+              JOIN plantations p ON p.id = o.plantation_id
+              JOIN pathogenics pa ON pa.id = o.pathogenic_id
+              LEFT JOIN stations s ON s.id = p.station_id
+      WHERE p.delete_at IS NULL
+        AND o.occurrence_date >= $2
+        AND o.occurrence_date < $3::date + 1
+        AND p.farm_id IN (SELECT fm.farm_id
+                          FROM farm_members fm
+                                  JOIN organization_members m ON m.user_id = fm.user_id
+                          WHERE m.organization_id = $1
+                            AND fm.role = 'owner')
+      GROUP BY GROUPING SETS ((pa.id, pa.name), (s.city, s.uf), (DATE_TRUNC('week', o.occurrence_date)))
+      ORDER BY 1, 7 DESC, 6
+      "#,
+      id,
+      from.and_hms_opt(0, 0, 0).unwrap(),
+      to
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
+
+impl OrganizationMember {
+  pub(crate) async fn all_by_organization_id(
+    db: &DataBase,
+    organization_id: Uuid,
+  ) -> Result<Vec<OrganizationMember>> {
+    sqlx::query_as!(
+      OrganizationMember,
+      "
+      SELECT m.user_id,
+            u.name,
+            u.email,
+            m.role,
+            m.create_date
+      FROM organization_members m
+              JOIN users u ON u.id = m.user_id
+      WHERE m.organization_id = $1
+      ORDER BY m.create_date
+      ",
+      organization_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Adds the user to the organization, or changes their role if they're a
+  /// member.
+  pub(crate) async fn save(
+    db: &DataBase,
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: OrganizationRole,
+  ) -> Result<()> {
+    sqlx::query!(
+      "INSERT INTO organization_members (organization_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (organization_id, user_id) DO UPDATE
+            SET role        = $3,
+                update_date = NOW()",
+      organization_id,
+      user_id,
+      role.as_str()
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, organization_id: Uuid, user_id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+      organization_id,
+      user_id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn admins_count(db: &DataBase, organization_id: Uuid) -> Result<i64> {
+    sqlx::query_scalar!(
+      r#"SELECT COUNT(*) AS "count!" FROM organization_members WHERE organization_id = $1 AND role = $2"#,
+      organization_id,
+      OrganizationRole::Admin.as_str()
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
+
+impl AlertPolicy {
+  pub(crate) async fn all_by_organization_id(
+    db: &DataBase,
+    organization_id: Uuid,
+  ) -> Result<Vec<AlertPolicy>> {
+    sqlx::query_as!(
+      AlertPolicy,
+      "
+      SELECT id, pathogenic_id, min_level, notify_agronomists, create_date, update_date
+      FROM organization_alert_policies
+      WHERE organization_id = $1
+      ORDER BY pathogenic_id NULLS FIRST
+      ",
+      organization_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Sets the policy of the pathogen, or the default one when `None`.
+  pub(crate) async fn save(
+    db: &DataBase,
+    organization_id: Uuid,
+    pathogenic_id: Option<i64>,
+    min_level: &str,
+    notify_agronomists: bool,
+  ) -> Result<()> {
+    sqlx::query!(
+      "INSERT INTO organization_alert_policies (organization_id, pathogenic_id, min_level, notify_agronomists)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (organization_id, pathogenic_id) DO UPDATE
+            SET min_level          = $3,
+                notify_agronomists = $4,
+                update_date        = NOW()",
+      organization_id,
+      pathogenic_id,
+      min_level,
+      notify_agronomists
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, organization_id: Uuid, id: i64) -> Result<bool> {
+    let result = sqlx::query!(
+      "DELETE FROM organization_alert_policies WHERE organization_id = $1 AND id = $2",
+      organization_id,
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(result.rows_affected() > 0)
+  }
+}
@@ -1,18 +1,31 @@
 use crate::utils::database::DataBase;
 use chrono::NaiveDateTime;
+use favourable::Favourable;
 use sqlx::Result;
+use std::collections::HashMap;
 
 #[derive(Debug, serde::Serialize, Clone)]
 
-pub(crate) struct Pathogenic {
+pub struct Pathogenic {
   pub(crate) id: i64,
   pub(crate) name: String,
   pub(crate) scientific_name: String,
   pub(crate) description: Option<String>,
   pub(crate) create_date: Option<NaiveDateTime>,
+  pub(crate) favourable_min_temperature: f64,
+  pub(crate) favourable_max_temperature: f64,
+  pub(crate) favourable_min_humidity: f64,
 }
 
 impl Pathogenic {
+  pub(crate) fn favourable(&self) -> Favourable {
+    Favourable {
+      min_temperature: self.favourable_min_temperature,
+      max_temperature: self.favourable_max_temperature,
+      min_humidity: self.favourable_min_humidity,
+    }
+  }
+
   //   pub(crate) async fn insert(
   //     database: DataBase,
   //     name: &String,
@@ -32,7 +45,7 @@ impl Pathogenic {
   //     Ok(result.id.to_string())
   //   }
 
-  pub(crate) async fn find_by_id(database: &DataBase, id: &i64) -> Result<Pathogenic> {
+  pub async fn find_by_id(database: &DataBase, id: &i64) -> Result<Pathogenic> {
     Ok(
       sqlx::query_as!(
         Pathogenic,
@@ -44,4 +57,23 @@ impl Pathogenic {
       .map_err(DataBase::database_error)?,
     )
   }
+
+  /// The pathogenics of `ids` by id, in a single query
+  pub async fn find_by_ids(database: &DataBase, ids: &[i64]) -> Result<HashMap<i64, Pathogenic>> {
+    let pathogenics = sqlx::query_as!(
+      Pathogenic,
+      "SELECT * FROM pathogenics WHERE id = ANY($1)",
+      ids
+    )
+    .fetch_all(&database.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(
+      pathogenics
+        .into_iter()
+        .map(|pathogenic| (pathogenic.id, pathogenic))
+        .collect(),
+    )
+  }
 }
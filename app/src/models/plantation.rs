@@ -1,12 +1,18 @@
-use crate::utils::database::DataBase;
+use crate::{
+  models::plantation_pathogenic_occurrences::RegionalFilter,
+  utils::{database::DataBase, pagination::Page},
+};
+use chrono::NaiveDateTime;
 use sqlx::{types::Uuid, Result};
+use std::collections::HashSet;
 
 #[derive(Debug, serde::Serialize, Clone)]
-pub(crate) struct Plantation {
+pub struct Plantation {
   pub id: Uuid,
   pub user_id: Uuid,
   pub culture_id: i64,
   pub station_id: Option<i64>,
+  pub farm_id: Option<Uuid>,
   pub alias: Option<String>,
   pub latitude: Option<f64>,
   pub longitude: Option<f64>,
@@ -17,8 +23,136 @@ pub(crate) struct Plantation {
   pub delete_at: Option<chrono::NaiveDateTime>,
 }
 
+/// Days a deleted plantation stays in the trash before it's purged, from
+/// `PLANTATION_RETENTION_DAYS`
+pub(crate) fn retention_days() -> i32 {
+  std::env::var("PLANTATION_RETENTION_DAYS")
+    .ok()
+    .and_then(|days| days.parse::<i32>().ok())
+    .unwrap_or(30)
+    .max(1)
+}
+
+/// Filters of the plantations listing
+#[derive(Debug, Clone, Default)]
+pub struct PlantationFilter {
+  pub culture_id: Option<i64>,
+  pub station_id: Option<i64>,
+  pub planting_from: Option<NaiveDateTime>,
+  pub planting_to: Option<NaiveDateTime>,
+  pub created_from: Option<NaiveDateTime>,
+  pub created_to: Option<NaiveDateTime>,
+  /// With or without regional occurrences on the last 24 hours
+  pub has_ocurrences: Option<bool>,
+}
+
 impl Plantation {
-  pub async fn all_by_user_id(db: &DataBase, user_id: Uuid) -> Vec<Plantation> {
+  /// A page of the plantations of every farm the user is a member of, deleted
+  /// ones left out
+  pub async fn search(
+    db: &DataBase,
+    user_id: Uuid,
+    filter: &PlantationFilter,
+    regional: &RegionalFilter,
+    page: &Page,
+  ) -> Result<Vec<Plantation>> {
+    sqlx::query_as!(
+      Plantation,
+      r#"
+      WITH listed AS (SELECT p.id,
+                             p.user_id,
+                             p.culture_id,
+                             p.station_id,
+                             p.farm_id,
+                             p.alias,
+                             p.area,
+                             p.planting_date,
+                             p.create_date,
+                             p.update_date,
+                             p.delete_at,
+                             p.location,
+                             CASE WHEN $2 = 'alias' THEN LOWER(COALESCE(p.alias, '')) ELSE '' END AS sort_text,
+                             CASE $2
+                                 WHEN 'area' THEN p.area
+                                 WHEN 'planting_date' THEN EXTRACT(EPOCH FROM p.planting_date)::float8
+                                 WHEN 'create_date' THEN EXTRACT(EPOCH FROM p.create_date)::float8
+                                 ELSE 0
+                                 END                                                            AS sort_number
+                      FROM plantations p
+                      WHERE p.farm_id IN (SELECT fm.farm_id
+                                          FROM farm_members fm
+                                                  JOIN farms f ON f.id = fm.farm_id
+                                          WHERE fm.user_id = $1
+                                            AND f.delete_at IS NULL))
+      SELECT listed.id,
+            listed.user_id,
+            listed.culture_id,
+            listed.station_id,
+            listed.farm_id,
+            listed.alias,
+            listed.area,
+            listed.planting_date,
+            listed.create_date,
+            listed.update_date,
+            listed.delete_at,
+            st_x(listed.location::geometry) AS latitude,
+            st_y(listed.location::geometry) AS longitude
+      FROM listed
+      WHERE listed.delete_at IS NULL
+        AND ($4::bigint IS NULL OR listed.culture_id = $4)
+        AND ($5::bigint IS NULL OR listed.station_id = $5)
+        AND ($6::timestamp IS NULL OR listed.planting_date >= $6)
+        AND ($7::timestamp IS NULL OR listed.planting_date <= $7)
+        AND ($8::timestamp IS NULL OR listed.create_date >= $8)
+        AND ($9::timestamp IS NULL OR listed.create_date <= $9)
+        AND ($10::boolean IS NULL
+          OR $10 = EXISTS(SELECT 1
+                          FROM plantation_pathogenic_occurrences ppo
+                                   JOIN plantations p ON ppo.plantation_id = p.id
+                          WHERE st_distancesphere(p.location::geometry,
+                                                  listed.location::geometry) < $11::float8 * 1000
+                            AND ppo.occurrence_date >= NOW() - INTERVAL '24 hours'
+                            AND ppo.plantation_id != listed.id
+                            AND p.delete_at IS NULL
+                            AND ppo.status = ANY ($12)
+                            AND ($13::bigint IS NULL OR ppo.pathogenic_id = $13)))
+        AND ($14::uuid IS NULL OR
+             CASE
+                 WHEN $3 THEN (listed.sort_text, listed.sort_number, listed.id) <
+                              (SELECT c.sort_text, c.sort_number, c.id FROM listed c WHERE c.id = $14)
+                 ELSE (listed.sort_text, listed.sort_number, listed.id) >
+                      (SELECT c.sort_text, c.sort_number, c.id FROM listed c WHERE c.id = $14)
+                 END)
+      ORDER BY CASE WHEN $3 THEN listed.sort_text END DESC,
+               CASE WHEN $3 THEN listed.sort_number END DESC,
+               CASE WHEN $3 THEN listed.id END DESC,
+               listed.sort_text,
+               listed.sort_number,
+               listed.id
+      LIMIT $15
+    "#,
+      user_id,
+      page.sort,
+      page.descending,
+      filter.culture_id,
+      filter.station_id,
+      filter.planting_from,
+      filter.planting_to,
+      filter.created_from,
+      filter.created_to,
+      filter.has_ocurrences,
+      regional.radius_km,
+      regional.statuses(),
+      regional.pathogenic_id,
+      page.cursor,
+      page.fetch_limit()
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn all_by_farm_id(db: &DataBase, farm_id: Uuid) -> Result<Vec<Plantation>> {
     sqlx::query_as!(
       Plantation,
       "
@@ -26,6 +160,7 @@ impl Plantation {
             plantations.user_id,
             plantations.culture_id,
             plantations.station_id,
+            plantations.farm_id,
             plantations.alias,
             plantations.area,
             plantations.planting_date,
@@ -35,14 +170,54 @@ impl Plantation {
             st_x(plantations.location::geometry) AS latitude,
             st_y(plantations.location::geometry) AS longitude
       FROM plantations
-      WHERE user_id = $1
+      WHERE farm_id = $1
+        AND delete_at IS NULL
       ORDER BY plantations.create_date DESC
     ",
-      user_id
+      farm_id
     )
     .fetch_all(&db.pool)
     .await
-    .unwrap()
+    .map_err(DataBase::database_error)
+  }
+
+  /// Plantations on the farms owned by the members of the organization
+  pub(crate) async fn all_by_organization_id(
+    db: &DataBase,
+    organization_id: Uuid,
+  ) -> Result<Vec<Plantation>> {
+    sqlx::query_as!(
+      Plantation,
+      "
+      SELECT plantations.id,
+            plantations.user_id,
+            plantations.culture_id,
+            plantations.station_id,
+            plantations.farm_id,
+            plantations.alias,
+            plantations.area,
+            plantations.planting_date,
+            plantations.create_date,
+            plantations.update_date,
+            plantations.delete_at,
+            st_x(plantations.location::geometry) AS latitude,
+            st_y(plantations.location::geometry) AS longitude
+      FROM plantations
+      WHERE farm_id IN (SELECT fm.farm_id
+                        FROM farm_members fm
+                                JOIN farms f ON f.id = fm.farm_id
+                                JOIN organization_members m ON m.user_id = fm.user_id
+                        WHERE m.organization_id = $1
+                          AND fm.role = 'owner'
+                          AND f.delete_at IS NULL)
+        AND delete_at IS NULL
+      ORDER BY plantations.create_date DESC
+    ",
+      organization_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
   }
 
   pub async fn find_by_uuid(db: &DataBase, uuid: Uuid) -> Result<Plantation> {
@@ -53,6 +228,7 @@ impl Plantation {
             plantations.user_id,
             plantations.culture_id,
             plantations.station_id,
+            plantations.farm_id,
             plantations.alias,
             plantations.area,
             plantations.planting_date,
@@ -63,6 +239,7 @@ impl Plantation {
             st_y(plantations.location::geometry) AS longitude
       FROM plantations
       WHERE plantations.id = $1
+        AND plantations.delete_at IS NULL
     ",
       uuid
     )
@@ -70,9 +247,82 @@ impl Plantation {
     .await
   }
 
+  /// A deleted plantation not purged yet
+  pub(crate) async fn find_deleted_by_uuid(
+    db: &DataBase,
+    uuid: Uuid,
+  ) -> Result<Option<Plantation>> {
+    sqlx::query_as!(
+      Plantation,
+      "
+      SELECT plantations.id,
+            plantations.user_id,
+            plantations.culture_id,
+            plantations.station_id,
+            plantations.farm_id,
+            plantations.alias,
+            plantations.area,
+            plantations.planting_date,
+            plantations.create_date,
+            plantations.update_date,
+            plantations.delete_at,
+            st_x(plantations.location::geometry) AS latitude,
+            st_y(plantations.location::geometry) AS longitude
+      FROM plantations
+      WHERE plantations.id = $1
+        AND plantations.delete_at IS NOT NULL
+    ",
+      uuid
+    )
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Deleted plantations of the farms the user manages, the last deleted first
+  pub(crate) async fn all_deleted_by_user_id(
+    db: &DataBase,
+    user_id: Uuid,
+  ) -> Result<Vec<Plantation>> {
+    sqlx::query_as!(
+      Plantation,
+      "
+      SELECT plantations.id,
+            plantations.user_id,
+            plantations.culture_id,
+            plantations.station_id,
+            plantations.farm_id,
+            plantations.alias,
+            plantations.area,
+            plantations.planting_date,
+            plantations.create_date,
+            plantations.update_date,
+            plantations.delete_at,
+            st_x(plantations.location::geometry) AS latitude,
+            st_y(plantations.location::geometry) AS longitude
+      FROM plantations
+      WHERE plantations.farm_id IN (SELECT fm.farm_id
+                                    FROM farm_members fm
+                                            JOIN farms f ON f.id = fm.farm_id
+                                    WHERE fm.user_id = $1
+                                      AND fm.role IN ('manager', 'owner')
+                                      AND f.delete_at IS NULL)
+        AND plantations.delete_at IS NOT NULL
+      ORDER BY plantations.delete_at DESC
+    ",
+      user_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Creates the plantation with its first season open on the culture and the
+  /// planting date.
   pub async fn insert(
     db: &DataBase,
     user_id: Uuid,
+    farm_id: Uuid,
     culture_id: i64,
     station_id: Option<i64>,
     alias: String,
@@ -87,10 +337,13 @@ impl Plantation {
       longitude.to_string().replace(",", ".")
     );
 
+    let mut transaction = db.pool.begin().await?;
+
     let result = sqlx::query!(
-      "INSERT INTO plantations (id, user_id, culture_id, station_id, alias, location, area, planting_date) VALUES ($1, $2, $3, $4, $5, ST_PointFromText($6)::point, $7, $8) RETURNING id",
+      "INSERT INTO plantations (id, user_id, farm_id, culture_id, station_id, alias, location, area, planting_date) VALUES ($1, $2, $3, $4, $5, $6, ST_PointFromText($7)::point, $8, $9) RETURNING id",
       Uuid::new_v4(),
       user_id,
+      farm_id,
       culture_id,
       station_id,
       alias,
@@ -98,16 +351,32 @@ impl Plantation {
       area,
       planting_date
     )
-    .fetch_one(&db.pool)
+    .fetch_one(&mut *transaction)
     .await
     .map_err(DataBase::database_error)?;
 
+    sqlx::query!(
+      "INSERT INTO plantation_seasons (id, plantation_id, culture_id, planting_date) VALUES ($1, $2, $3, $4)",
+      Uuid::new_v4(),
+      result.id,
+      culture_id,
+      planting_date
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
     Ok(result.id)
   }
 
+  /// Edits the plantation with its open season, moving it to `farm_id` when
+  /// set.
   pub async fn update(
     db: &DataBase,
     id: Uuid,
+    farm_id: Option<Uuid>,
     culture_id: i64,
     station_id: Option<i64>,
     alias: String,
@@ -122,20 +391,38 @@ impl Plantation {
       longitude.to_string().replace(",", ".")
     );
 
+    let mut transaction = db.pool.begin().await?;
+
     sqlx::query!(
-      "UPDATE plantations SET culture_id = $2, station_id = $3, alias = $4, location = ST_PointFromText($5)::point, area = $6, planting_date = $7 WHERE id = $1",
+      "UPDATE plantations SET culture_id = $2, station_id = $3, alias = $4, location = ST_PointFromText($5)::point, area = $6, planting_date = $7, farm_id = COALESCE($8, farm_id) WHERE id = $1",
       id,
       culture_id,
       station_id,
       alias,
       point,
       area,
+      planting_date,
+      farm_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "UPDATE plantation_seasons
+        SET culture_id = $2, planting_date = $3, update_date = NOW()
+        WHERE plantation_id = $1
+          AND close_date IS NULL",
+      id,
+      culture_id,
       planting_date
     )
-    .execute(&db.pool)
+    .execute(&mut *transaction)
     .await
     .map_err(DataBase::database_error)?;
 
+    transaction.commit().await?;
+
     Ok(())
   }
 
@@ -148,7 +435,141 @@ impl Plantation {
     Ok(())
   }
 
-  pub(crate) async fn has_ocurrence_last_24h(db: &DataBase, id: Uuid) -> Result<bool> {
+  pub(crate) async fn restore(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE plantations SET delete_at = NULL, update_date = NOW() WHERE id = $1",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  /// Plantations deleted more than `retention_days` ago, due to be purged
+  pub(crate) async fn all_expired(db: &DataBase, retention_days: i32) -> Result<Vec<Uuid>> {
+    sqlx::query_scalar!(
+      "SELECT id FROM plantations WHERE delete_at < NOW() - MAKE_INTERVAL(days => $1)",
+      retention_days
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Removes the plantation and everything recorded on it, if it's still
+  /// deleted more than `retention_days` ago. The images of its occurrences
+  /// are returned for the storage, `None` when it was restored meanwhile.
+  pub(crate) async fn purge(
+    db: &DataBase,
+    id: Uuid,
+    retention_days: i32,
+  ) -> Result<Option<Vec<String>>> {
+    let mut transaction = db.pool.begin().await?;
+
+    let purged = sqlx::query!(
+      "DELETE FROM plantations WHERE id = $1 AND delete_at < NOW() - MAKE_INTERVAL(days => $2)",
+      id,
+      retention_days
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    if purged.rows_affected() == 0 {
+      return Ok(None);
+    }
+
+    let ocurrences = sqlx::query!(
+      "DELETE FROM plantation_pathogenic_occurrences WHERE plantation_id = $1 RETURNING id, image",
+      id
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    let ocurrence_ids = ocurrences
+      .iter()
+      .map(|ocurrence| ocurrence.id)
+      .collect::<Vec<_>>();
+
+    sqlx::query!(
+      "DELETE FROM plantation_pathogenic_occurrences_temperatures
+      WHERE plantation_pathogenic_occurrence_id = ANY($1)",
+      &ocurrence_ids[..]
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "DELETE FROM plantation_pathogenic_occurrences_humidities
+      WHERE plantation_pathogenic_occurrence_id = ANY($1)",
+      &ocurrence_ids[..]
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "DELETE FROM plantation_pathogenic_occurrence_reviews WHERE occurrence_id = ANY($1)",
+      &ocurrence_ids[..]
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "DELETE FROM ocurrence_climate_tasks WHERE ocurrence_id = ANY($1)",
+      &ocurrence_ids[..]
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "DELETE FROM plantation_activities WHERE plantation_id = $1",
+      id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!("DELETE FROM plantation_risks WHERE plantation_id = $1", id)
+      .execute(&mut *transaction)
+      .await
+      .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "DELETE FROM plantation_seasons WHERE plantation_id = $1",
+      id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!("DELETE FROM hotspot_alerts WHERE plantation_id = $1", id)
+      .execute(&mut *transaction)
+      .await
+      .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(Some(
+      ocurrences
+        .into_iter()
+        .filter_map(|ocurrence| ocurrence.image)
+        .collect(),
+    ))
+  }
+
+  /// Whether a regional occurrence was reported on the last 24 hours
+  pub async fn has_ocurrence_last_24h(
+    db: &DataBase,
+    id: Uuid,
+    filter: &RegionalFilter,
+  ) -> Result<bool> {
     let result = sqlx::query!(
       "SELECT EXISTS(SELECT *
         FROM plantation_pathogenic_occurrences ppo
@@ -156,11 +577,20 @@ impl Plantation {
         WHERE st_distancesphere(p.location::geometry,
                                 (SELECT location
                                  FROM plantations
-                                 WHERE id = $1)::geometry) < 100000
+                                 WHERE id = $1)::geometry) < $2::float8 * 1000
           AND ppo.occurrence_date >= NOW() - INTERVAL '24 hours'
-          AND ppo.plantation_id != $2) AS has_ocurrence_last_24h;",
+          AND ppo.plantation_id != $1
+          AND p.delete_at IS NULL
+          AND ppo.status = ANY($3)
+          AND ($4::bigint IS NULL OR ppo.pathogenic_id = $4)
+          AND ($5::timestamp IS NULL OR ppo.occurrence_date >= $5)
+          AND ($6::timestamp IS NULL OR ppo.occurrence_date <= $6)) AS has_ocurrence_last_24h;",
       id,
-      id
+      filter.radius_km,
+      filter.statuses(),
+      filter.pathogenic_id,
+      filter.from,
+      filter.to
     )
     .fetch_one(&db.pool)
     .await
@@ -169,31 +599,75 @@ impl Plantation {
     Ok(result.has_ocurrence_last_24h.unwrap())
   }
 
-  pub async fn all_closest_to_plantation(
+  /// Which of the plantations of `ids` have had regional occurrences in the
+  /// last 24 hours, in a single query
+  pub async fn with_ocurrence_last_24h(
+    db: &DataBase,
+    ids: &[Uuid],
+    filter: &RegionalFilter,
+  ) -> Result<HashSet<Uuid>> {
+    let ids = sqlx::query_scalar!(
+      "SELECT plantation.id
+        FROM plantations plantation
+        WHERE plantation.id = ANY($1)
+          AND EXISTS(SELECT *
+                     FROM plantation_pathogenic_occurrences ppo
+                              JOIN plantations p ON ppo.plantation_id = p.id
+                     WHERE st_distancesphere(p.location::geometry,
+                                             plantation.location::geometry) < $2::float8 * 1000
+                       AND ppo.occurrence_date >= NOW() - INTERVAL '24 hours'
+                       AND ppo.plantation_id != plantation.id
+                       AND p.delete_at IS NULL
+                       AND ppo.status = ANY($3)
+                       AND ($4::bigint IS NULL OR ppo.pathogenic_id = $4)
+                       AND ($5::timestamp IS NULL OR ppo.occurrence_date >= $5)
+                       AND ($6::timestamp IS NULL OR ppo.occurrence_date <= $6));",
+      ids,
+      filter.radius_km,
+      filter.statuses(),
+      filter.pathogenic_id,
+      filter.from,
+      filter.to
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(ids.into_iter().collect())
+  }
+
+  /// Owners of the other farms with plantations within the radius they set of
+  /// `plantation`, or `filter.radius_km` for the owners without one. The
+  /// owner of a farm is its first one, as on the crawler alerts, or the
+  /// creator of a plantation without a farm.
+  pub async fn owners_closest_to_plantation(
     db: &DataBase,
     plantation: &Plantation,
-  ) -> Vec<Plantation> {
-    sqlx::query_as!(
-      Plantation,
-      "
-        SELECT plantations.id,
-              plantations.user_id,
-              plantations.culture_id,
-              plantations.station_id,
-              plantations.alias,
-              plantations.area,
-              plantations.planting_date,
-              plantations.create_date,
-              plantations.update_date,
-              plantations.delete_at,
-              st_x(plantations.location::geometry) AS latitude,
-              st_y(plantations.location::geometry) AS longitude
+    filter: &RegionalFilter,
+  ) -> Vec<Uuid> {
+    sqlx::query_scalar!(
+      r#"
+        SELECT DISTINCT users.id
         FROM plantations
+                LEFT JOIN LATERAL (SELECT user_id
+                                   FROM farm_members
+                                   WHERE farm_id = plantations.farm_id
+                                     AND role = 'owner'
+                                   ORDER BY create_date, id
+                                   LIMIT 1) owner ON TRUE
+                JOIN users ON users.id = COALESCE(owner.user_id, plantations.user_id)
         WHERE st_distancesphere(location::geometry,
-                                POINT($1, $2)::geometry) < 100000
-    ",
+                                POINT($1, $2)::geometry)
+                  < COALESCE(users.occurrence_radius_km, $3) * 1000
+          AND plantations.delete_at IS NULL
+          AND plantations.id != $4
+          AND ($5::uuid IS NULL OR plantations.farm_id IS DISTINCT FROM $5)
+    "#,
       plantation.latitude.unwrap(),
       plantation.longitude.unwrap(),
+      filter.radius_km,
+      plantation.id,
+      plantation.farm_id
     )
     .fetch_all(&db.pool)
     .await
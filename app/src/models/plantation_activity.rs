@@ -0,0 +1,189 @@
+use crate::utils::database::DataBase;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::Result;
+use uuid::Uuid;
+
+/// Field work logged by the grower, `kind` is `fungicide`, `treatment`,
+/// `scouting` or `other`. For `protection_days` after a fungicide or treatment
+/// the plantation is protected against `pathogenic_id`, or every pathogen when
+/// empty: the crawler lowers its risk level and doesn't alert.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct PlantationActivity {
+  pub id: Uuid,
+  pub plantation_id: Uuid,
+  pub season_id: Option<Uuid>,
+  pub pathogenic_id: Option<i64>,
+  pub kind: String,
+  pub date: NaiveDate,
+  pub product: Option<String>,
+  pub dose: Option<f64>,
+  pub dose_unit: Option<String>,
+  pub area: Option<f64>,
+  pub protection_days: Option<i32>,
+  /// Last day protected, `date + protection_days`
+  pub protected_until: Option<NaiveDate>,
+  pub notes: Option<String>,
+  pub operator: Option<String>,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+}
+
+/// Fields set by the grower on create and update.
+#[derive(Debug, Clone)]
+pub(crate) struct ActivityData {
+  pub pathogenic_id: Option<i64>,
+  pub kind: String,
+  pub date: NaiveDate,
+  pub product: Option<String>,
+  pub dose: Option<f64>,
+  pub dose_unit: Option<String>,
+  pub area: Option<f64>,
+  pub protection_days: Option<i32>,
+  pub notes: Option<String>,
+  pub operator: Option<String>,
+}
+
+impl PlantationActivity {
+  pub(crate) async fn all_by_plantation_id(
+    db: &DataBase,
+    plantation_id: Uuid,
+  ) -> Result<Vec<PlantationActivity>> {
+    sqlx::query_as!(
+      PlantationActivity,
+      "
+      SELECT id,
+            plantation_id,
+            season_id,
+            pathogenic_id,
+            kind,
+            date,
+            product,
+            dose,
+            dose_unit,
+            area,
+            protection_days,
+            date + protection_days AS protected_until,
+            notes,
+            operator,
+            create_date,
+            update_date
+      FROM plantation_activities
+      WHERE plantation_id = $1
+        AND delete_at IS NULL
+      ORDER BY date DESC, create_date DESC
+      ",
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn find_by_id(db: &DataBase, id: Uuid) -> Result<PlantationActivity> {
+    sqlx::query_as!(
+      PlantationActivity,
+      "
+      SELECT id,
+            plantation_id,
+            season_id,
+            pathogenic_id,
+            kind,
+            date,
+            product,
+            dose,
+            dose_unit,
+            area,
+            protection_days,
+            date + protection_days AS protected_until,
+            notes,
+            operator,
+            create_date,
+            update_date
+      FROM plantation_activities
+      WHERE id = $1
+        AND delete_at IS NULL
+      ",
+      id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn insert(
+    db: &DataBase,
+    plantation_id: Uuid,
+    data: &ActivityData,
+  ) -> Result<Uuid> {
+    let result = sqlx::query!(
+      "INSERT INTO plantation_activities (id, plantation_id, pathogenic_id, kind, date, product, dose,
+                                         dose_unit, area, protection_days, notes, operator, season_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                (SELECT id FROM plantation_seasons WHERE plantation_id = $2 AND close_date IS NULL))
+        RETURNING id",
+      Uuid::new_v4(),
+      plantation_id,
+      data.pathogenic_id,
+      data.kind,
+      data.date,
+      data.product,
+      data.dose,
+      data.dose_unit,
+      data.area,
+      data.protection_days,
+      data.notes,
+      data.operator
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(result.id)
+  }
+
+  pub(crate) async fn update(db: &DataBase, id: Uuid, data: &ActivityData) -> Result<()> {
+    sqlx::query!(
+      "UPDATE plantation_activities
+        SET pathogenic_id   = $2,
+            kind            = $3,
+            date            = $4,
+            product         = $5,
+            dose            = $6,
+            dose_unit       = $7,
+            area            = $8,
+            protection_days = $9,
+            notes           = $10,
+            operator        = $11,
+            update_date     = NOW()
+        WHERE id = $1",
+      id,
+      data.pathogenic_id,
+      data.kind,
+      data.date,
+      data.product,
+      data.dose,
+      data.dose_unit,
+      data.area,
+      data.protection_days,
+      data.notes,
+      data.operator
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn delete(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE plantation_activities SET delete_at = NOW() WHERE id = $1",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+}
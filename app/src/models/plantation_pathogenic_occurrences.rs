@@ -1,10 +1,14 @@
-use crate::utils::database::DataBase;
+use crate::{
+  models::{pathogenic::Pathogenic, user::User},
+  utils::{database::DataBase, pagination::Page},
+};
 use chrono::NaiveDateTime;
 use sqlx::Result;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, serde::Serialize, Clone)]
-pub(crate) struct PlantationPathogenicOccurrences {
+pub struct PlantationPathogenicOccurrences {
   pub id: Uuid,
   pub user_id: Uuid,
   pub plantation_id: Uuid,
@@ -13,10 +17,253 @@ pub(crate) struct PlantationPathogenicOccurrences {
   pub occurrence_date: NaiveDateTime,
   pub temperature: Option<f64>,
   pub humidity: Option<f64>,
+  /// `reported`, `confirmed`, `rejected` or `needs_more_info`
+  pub status: String,
+  pub review_date: Option<NaiveDateTime>,
   pub create_date: NaiveDateTime,
   pub update_date: Option<NaiveDateTime>,
 }
 
+/// Whether the neighbours hear of reports before an agronomist confirms them,
+/// set by `NOTIFY_UNCONFIRMED_OCCURRENCES`
+pub(crate) fn notify_unconfirmed() -> bool {
+  std::env::var("NOTIFY_UNCONFIRMED_OCCURRENCES")
+    .map(|value| value == "true")
+    .unwrap_or(false)
+}
+
+/// Statuses of the occurrences shown to the neighbours
+pub(crate) fn regional_statuses() -> Vec<String> {
+  let mut statuses = vec!["confirmed".to_string()];
+
+  if notify_unconfirmed() {
+    statuses.extend(["reported".to_string(), "needs_more_info".to_string()]);
+  }
+
+  statuses
+}
+
+/// Smallest and largest radius of the regional occurrences, in km
+pub(crate) const MIN_RADIUS_KM: f64 = 1.0;
+pub(crate) const MAX_RADIUS_KM: f64 = 500.0;
+
+/// Radius of the regional occurrences when neither the request nor the user
+/// set one, from `OCCURRENCE_RADIUS_KM`
+pub(crate) fn default_radius_km() -> f64 {
+  std::env::var("OCCURRENCE_RADIUS_KM")
+    .ok()
+    .and_then(|radius| radius.parse::<f64>().ok())
+    .unwrap_or(100.0)
+    .clamp(MIN_RADIUS_KM, MAX_RADIUS_KM)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionalSort {
+  /// The latest reports first
+  Date,
+  /// The closest reports first
+  Distance,
+}
+
+/// Which occurrences of the neighbours are regional to a plantation, the same
+/// for the listing, the 24 hours flag and the notifications.
+#[derive(Debug, Clone)]
+pub struct RegionalFilter {
+  pub radius_km: f64,
+  pub pathogenic_id: Option<i64>,
+  pub from: Option<NaiveDateTime>,
+  pub to: Option<NaiveDateTime>,
+  /// Always the regional statuses, the 24 hours flag and the notifications
+  /// must never count a pending report
+  statuses: Vec<String>,
+  pub sort: RegionalSort,
+}
+
+impl RegionalFilter {
+  /// The statuses shown to the neighbours within `radius_km`
+  pub fn new(radius_km: f64) -> RegionalFilter {
+    RegionalFilter {
+      radius_km,
+      pathogenic_id: None,
+      from: None,
+      to: None,
+      statuses: regional_statuses(),
+      sort: RegionalSort::Date,
+    }
+  }
+
+  /// Within `radius_km`, or the user's radius, or the default one
+  pub(crate) fn for_user(user: &User, radius_km: Option<f64>) -> RegionalFilter {
+    RegionalFilter::new(
+      radius_km
+        .or(user.occurrence_radius_km)
+        .unwrap_or_else(default_radius_km),
+    )
+  }
+
+  pub(crate) fn statuses(&self) -> &[String] {
+    &self.statuses
+  }
+
+  pub(crate) fn matches(&self, occurrence: &PlantationPathogenicOccurrences) -> bool {
+    self.statuses.contains(&occurrence.status)
+      && self
+        .pathogenic_id
+        .is_none_or(|pathogenic_id| occurrence.pathogenic_id == pathogenic_id)
+      && self
+        .from
+        .is_none_or(|from| occurrence.occurrence_date >= from)
+      && self.to.is_none_or(|to| occurrence.occurrence_date <= to)
+  }
+}
+
+/// Filters of the occurrences listing of a plantation
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OccurrenceFilter {
+  pub pathogenic_id: Option<i64>,
+  pub status: Option<String>,
+  pub from: Option<NaiveDateTime>,
+  pub to: Option<NaiveDateTime>,
+}
+
+/// An occurrence on a neighbour plantation, how far and in which direction
+/// from the plantation it's regional to
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct RegionalOccurrence {
+  pub id: Uuid,
+  pub plantation_id: Uuid,
+  pub pathogenic_id: i64,
+  pub occurrence_date: NaiveDateTime,
+  pub temperature: Option<f64>,
+  pub humidity: Option<f64>,
+  pub status: String,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+  /// Rounded to whole km, so the plantation can't be located from it
+  pub distance_km: f64,
+  /// Degrees clockwise from the north, rounded to 10°
+  pub bearing: f64,
+}
+
+/// Hours a given temperature was observed on a day, filled by the crawler.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OccurrenceTemperature {
+  pub date: NaiveDateTime,
+  pub temperature: f64,
+  pub quantity: i32,
+}
+
+/// Hours a given relative humidity was observed on a day, filled by the crawler.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OccurrenceHumidity {
+  pub date: NaiveDateTime,
+  pub humidity: f64,
+  pub quantity: i32,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct ClimateSummary {
+  pub hours_temperature: i32,
+  pub hours_humidity: i32,
+  pub hours_favourable_temperature: i32,
+  pub hours_favourable_humidity: i32,
+  pub mean_temperature: Option<f64>,
+  pub mean_humidity: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct ClimateDay {
+  pub date: NaiveDateTime,
+  pub temperatures: Vec<OccurrenceTemperature>,
+  pub humidities: Vec<OccurrenceHumidity>,
+  pub summary: ClimateSummary,
+}
+
+/// Climate conditions on the days before an occurrence, compared against the
+/// pathogenic favourable range.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct OccurrenceClimate {
+  pub days: Vec<ClimateDay>,
+  pub summary: ClimateSummary,
+}
+
+impl ClimateSummary {
+  fn new(
+    temperatures: &[OccurrenceTemperature],
+    humidities: &[OccurrenceHumidity],
+    pathogenic: &Pathogenic,
+  ) -> ClimateSummary {
+    let hours_temperature: i32 = temperatures.iter().map(|t| t.quantity).sum();
+    let hours_humidity: i32 = humidities.iter().map(|h| h.quantity).sum();
+
+    let temperature_sum: f64 = temperatures
+      .iter()
+      .map(|t| t.temperature * t.quantity as f64)
+      .sum();
+    let humidity_sum: f64 = humidities
+      .iter()
+      .map(|h| h.humidity * h.quantity as f64)
+      .sum();
+
+    let favourable = pathogenic.favourable();
+
+    ClimateSummary {
+      hours_temperature,
+      hours_humidity,
+      hours_favourable_temperature: temperatures
+        .iter()
+        .filter(|t| favourable.temperature(t.temperature))
+        .map(|t| t.quantity)
+        .sum(),
+      hours_favourable_humidity: humidities
+        .iter()
+        .filter(|h| favourable.humidity(h.humidity))
+        .map(|h| h.quantity)
+        .sum(),
+      mean_temperature: (hours_temperature > 0).then(|| temperature_sum / hours_temperature as f64),
+      mean_humidity: (hours_humidity > 0).then(|| humidity_sum / hours_humidity as f64),
+    }
+  }
+}
+
+impl OccurrenceClimate {
+  pub(crate) fn new(
+    temperatures: Vec<OccurrenceTemperature>,
+    humidities: Vec<OccurrenceHumidity>,
+    pathogenic: &Pathogenic,
+  ) -> OccurrenceClimate {
+    let summary = ClimateSummary::new(&temperatures, &humidities, pathogenic);
+
+    let mut days: BTreeMap<NaiveDateTime, (Vec<OccurrenceTemperature>, Vec<OccurrenceHumidity>)> =
+      BTreeMap::new();
+
+    for temperature in temperatures {
+      days
+        .entry(temperature.date)
+        .or_default()
+        .0
+        .push(temperature);
+    }
+
+    for humidity in humidities {
+      days.entry(humidity.date).or_default().1.push(humidity);
+    }
+
+    OccurrenceClimate {
+      days: days
+        .into_iter()
+        .map(|(date, (temperatures, humidities))| ClimateDay {
+          date,
+          summary: ClimateSummary::new(&temperatures, &humidities, pathogenic),
+          temperatures,
+          humidities,
+        })
+        .collect(),
+      summary,
+    }
+  }
+}
+
 impl PlantationPathogenicOccurrences {
   pub(crate) async fn get_by_plantation_id(
     db: &DataBase,
@@ -33,6 +280,8 @@ impl PlantationPathogenicOccurrences {
                 occurrence_date,
                 temperature,
                 humidity,
+                status,
+                review_date,
                 create_date,
                 update_date
             FROM plantation_pathogenic_occurrences
@@ -45,34 +294,130 @@ impl PlantationPathogenicOccurrences {
     .await
   }
 
-  pub(crate) async fn get_closest_by_plantation_id(
+  /// A page of the occurrences reported on the plantation
+  pub(crate) async fn search_by_plantation_id(
     db: &DataBase,
     plantation_id: Uuid,
+    filter: &OccurrenceFilter,
+    page: &Page,
   ) -> Result<Vec<PlantationPathogenicOccurrences>> {
     sqlx::query_as!(
       PlantationPathogenicOccurrences,
-      "
-            SELECT ppo.id,
-                   ppo.user_id,
-                   ppo.plantation_id,
-                   ppo.pathogenic_id,
-                   ppo.image,
-                   ppo.occurrence_date,
-                   ppo.temperature,
-                   ppo.humidity,
-                   ppo.create_date,
-                   ppo.update_date
-            FROM plantation_pathogenic_occurrences ppo
-                    JOIN plantations p ON p.id = ppo.plantation_id
-            WHERE st_distancesphere(p.location::geometry,
-                                  (SELECT location
-                                    FROM plantations
-                                    WHERE id = $1)::geometry) < 100000
-            AND ppo.plantation_id != $2
-            ORDER BY create_date DESC
-            ",
+      r#"
+            WITH listed AS (SELECT *,
+                                   EXTRACT(EPOCH FROM CASE $2
+                                                          WHEN 'occurrence_date' THEN occurrence_date
+                                                          ELSE create_date
+                                       END)::float8 AS sort_number
+                            FROM plantation_pathogenic_occurrences
+                            WHERE plantation_id = $1)
+            SELECT listed.id,
+                listed.user_id,
+                listed.plantation_id,
+                listed.pathogenic_id,
+                listed.image,
+                listed.occurrence_date,
+                listed.temperature,
+                listed.humidity,
+                listed.status,
+                listed.review_date,
+                listed.create_date,
+                listed.update_date
+            FROM listed
+            WHERE ($4::bigint IS NULL OR listed.pathogenic_id = $4)
+              AND ($5::varchar IS NULL OR listed.status = $5)
+              AND ($6::timestamp IS NULL OR listed.occurrence_date >= $6)
+              AND ($7::timestamp IS NULL OR listed.occurrence_date <= $7)
+              AND ($8::uuid IS NULL OR
+                   CASE
+                       WHEN $3 THEN (listed.sort_number, listed.id) <
+                                    (SELECT c.sort_number, c.id FROM listed c WHERE c.id = $8)
+                       ELSE (listed.sort_number, listed.id) >
+                            (SELECT c.sort_number, c.id FROM listed c WHERE c.id = $8)
+                       END)
+            ORDER BY CASE WHEN $3 THEN listed.sort_number END DESC,
+                     CASE WHEN $3 THEN listed.id END DESC,
+                     listed.sort_number,
+                     listed.id
+            LIMIT $9
+            "#,
       plantation_id,
-      plantation_id
+      page.sort,
+      page.descending,
+      filter.pathogenic_id,
+      filter.status,
+      filter.from,
+      filter.to,
+      page.cursor,
+      page.fetch_limit()
+    )
+    .fetch_all(&db.pool)
+    .await
+  }
+
+  pub async fn get_closest_by_plantation_id(
+    db: &DataBase,
+    plantation_id: Uuid,
+    filter: &RegionalFilter,
+  ) -> Result<Vec<RegionalOccurrence>> {
+    sqlx::query_as!(
+      RegionalOccurrence,
+      r#"
+            WITH origin AS (SELECT location,
+                                   RADIANS(st_x(location::geometry)) AS latitude,
+                                   RADIANS(st_y(location::geometry)) AS longitude,
+                                   -- The radius in degrees around the plantation, for the
+                                   -- location index, a degree of longitude shrinking
+                                   -- towards the poles
+                                   box(point(st_x(location::geometry) - $2::float8 / 111.32,
+                                             st_y(location::geometry) - $2::float8 / (111.32 * GREATEST(COS(RADIANS(st_x(location::geometry))), 0.01))),
+                                       point(st_x(location::geometry) + $2::float8 / 111.32,
+                                             st_y(location::geometry) + $2::float8 / (111.32 * GREATEST(COS(RADIANS(st_x(location::geometry))), 0.01))))
+                                                                     AS area
+                            FROM plantations
+                            WHERE id = $1),
+                 regional AS (SELECT ppo.*,
+                                     st_distancesphere(p.location::geometry,
+                                                       origin.location::geometry) / 1000 AS distance_km,
+                                     DEGREES(ATAN2(
+                                             SIN(RADIANS(st_y(p.location::geometry)) - origin.longitude)
+                                                 * COS(RADIANS(st_x(p.location::geometry))),
+                                             COS(origin.latitude) * SIN(RADIANS(st_x(p.location::geometry)))
+                                                 - SIN(origin.latitude) * COS(RADIANS(st_x(p.location::geometry)))
+                                                 * COS(RADIANS(st_y(p.location::geometry)) - origin.longitude)
+                                           ))                                             AS bearing
+                              FROM plantation_pathogenic_occurrences ppo
+                                      JOIN plantations p ON p.id = ppo.plantation_id,
+                                   origin
+                              WHERE ppo.plantation_id != $1
+                                AND p.location <@ origin.area
+                                AND p.delete_at IS NULL)
+            SELECT id,
+                   plantation_id,
+                   pathogenic_id,
+                   occurrence_date,
+                   temperature,
+                   humidity,
+                   status,
+                   create_date,
+                   update_date,
+                   ROUND(distance_km)                                     AS "distance_km!",
+                   ((ROUND(bearing / 10) * 10 + 360)::numeric % 360)::float8 AS "bearing!"
+            FROM regional
+            WHERE distance_km < $2
+              AND status = ANY($3)
+              AND ($4::bigint IS NULL OR pathogenic_id = $4)
+              AND ($5::timestamp IS NULL OR occurrence_date >= $5)
+              AND ($6::timestamp IS NULL OR occurrence_date <= $6)
+            ORDER BY CASE WHEN $7 THEN ROUND(distance_km) END, create_date DESC
+            "#,
+      plantation_id,
+      filter.radius_km,
+      filter.statuses(),
+      filter.pathogenic_id,
+      filter.from,
+      filter.to,
+      filter.sort == RegionalSort::Distance
     )
     .fetch_all(&db.pool)
     .await
@@ -91,9 +436,9 @@ impl PlantationPathogenicOccurrences {
     sqlx::query_as!(
       PlantationPathogenicOccurrences,
       "
-            INSERT INTO plantation_pathogenic_occurrences (id, user_id, plantation_id, pathogenic_id, image, occurrence_date, temperature, humidity)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, user_id, plantation_id, pathogenic_id, image, occurrence_date, temperature, humidity, create_date, update_date
+            INSERT INTO plantation_pathogenic_occurrences (id, user_id, plantation_id, pathogenic_id, image, occurrence_date, temperature, humidity, season_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, (SELECT id FROM plantation_seasons WHERE plantation_id = $3 AND close_date IS NULL))
+            RETURNING id, user_id, plantation_id, pathogenic_id, image, occurrence_date, temperature, humidity, status, review_date, create_date, update_date
             ",
       Uuid::new_v4(),
       user_id,
@@ -123,6 +468,8 @@ impl PlantationPathogenicOccurrences {
                   occurrence_date,
                   temperature,
                   humidity,
+                  status,
+                  review_date,
                   create_date,
                   update_date
               FROM plantation_pathogenic_occurrences
@@ -153,6 +500,8 @@ impl PlantationPathogenicOccurrences {
                   occurrence_date,
                   temperature,
                   humidity,
+                  status,
+                  review_date,
                   create_date,
                   update_date
               ",
@@ -162,4 +511,89 @@ impl PlantationPathogenicOccurrences {
     .fetch_one(&db.pool)
     .await
   }
+
+  pub(crate) async fn get_temperatures(
+    db: &DataBase,
+    id: Uuid,
+  ) -> Result<Vec<OccurrenceTemperature>> {
+    sqlx::query_as!(
+      OccurrenceTemperature,
+      "
+              SELECT date, temperature, quantity
+              FROM plantation_pathogenic_occurrences_temperatures
+              WHERE plantation_pathogenic_occurrence_id = $1
+              ORDER BY date, temperature
+              ",
+      id
+    )
+    .fetch_all(&db.pool)
+    .await
+  }
+
+  pub(crate) async fn get_humidities(db: &DataBase, id: Uuid) -> Result<Vec<OccurrenceHumidity>> {
+    sqlx::query_as!(
+      OccurrenceHumidity,
+      "
+              SELECT date, humidity, quantity
+              FROM plantation_pathogenic_occurrences_humidities
+              WHERE plantation_pathogenic_occurrence_id = $1
+              ORDER BY date, humidity
+              ",
+      id
+    )
+    .fetch_all(&db.pool)
+    .await
+  }
+
+  /// Puts an occurrence the agronomist asked more about back on the review
+  /// queue
+  pub(crate) async fn reopen(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "UPDATE plantation_pathogenic_occurrences
+        SET status = 'reported', update_date = NOW()
+        WHERE id = $1
+          AND status = 'needs_more_info'",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  /// Marks the neighbours as notified of the occurrence on `status`, `false`
+  /// when they already were. Told of an unconfirmed report, they're notified
+  /// again once it's confirmed.
+  pub(crate) async fn mark_notified(db: &DataBase, id: Uuid, status: &str) -> Result<bool> {
+    let result = sqlx::query!(
+      "UPDATE plantation_pathogenic_occurrences
+        SET notify_date     = NOW(),
+            notified_status = $2
+        WHERE id = $1
+          AND (notify_date IS NULL
+            OR ($2 = 'confirmed' AND notified_status IS DISTINCT FROM 'confirmed'))",
+      id,
+      status
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(result.rows_affected() > 0)
+  }
+
+  /// Queues the ocurrence for the crawler, which fills the climate buckets of
+  /// the days before it (`ocurrence-immet-climate-data-pending` script).
+  pub(crate) async fn enqueue_climate_task(db: &DataBase, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      "INSERT INTO ocurrence_climate_tasks (ocurrence_id) VALUES ($1) ON CONFLICT DO NOTHING",
+      id
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
 }
@@ -0,0 +1,169 @@
+use crate::utils::database::DataBase;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::Result;
+use uuid::Uuid;
+
+/// Daily risk of a pathogen on a plantation, written by the crawler with the
+/// readings and thresholds it was calculated from. `kind` is `observed`, from
+/// the station readings, or `predicted`, from the weather forecast.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct PlantationRisk {
+  pub pathogenic_id: i64,
+  pub pathogenic_name: String,
+  pub date: NaiveDate,
+  pub kind: String,
+  pub season_id: Option<Uuid>,
+  pub level: String,
+  pub score: f64,
+  pub at_risk: bool,
+  pub hours: i32,
+  pub temperature_hours: i32,
+  pub humidity_hours: i32,
+  pub favourable_hours: i32,
+  pub mean_temperature: Option<f64>,
+  pub mean_humidity: Option<f64>,
+  pub favourable_min_temperature: Option<f64>,
+  pub favourable_max_temperature: Option<f64>,
+  pub favourable_min_humidity: Option<f64>,
+  pub confidence: f64,
+  /// Fungicide or treatment that lowered the level and held the alert
+  pub protected_by: Option<Uuid>,
+  pub period_start: NaiveDateTime,
+  pub period_end: NaiveDateTime,
+  pub update_date: NaiveDateTime,
+}
+
+impl PlantationRisk {
+  /// Latest observed risk of each pathogen
+  pub(crate) async fn current(db: &DataBase, plantation_id: Uuid) -> Result<Vec<PlantationRisk>> {
+    sqlx::query_as!(
+      PlantationRisk,
+      "
+      SELECT DISTINCT ON (pr.pathogenic_id) pr.pathogenic_id,
+            p.name AS pathogenic_name,
+            pr.date,
+            pr.kind,
+            pr.season_id,
+            pr.level,
+            pr.score,
+            pr.at_risk,
+            pr.hours,
+            pr.temperature_hours,
+            pr.humidity_hours,
+            pr.favourable_hours,
+            pr.mean_temperature,
+            pr.mean_humidity,
+            pr.favourable_min_temperature,
+            pr.favourable_max_temperature,
+            pr.favourable_min_humidity,
+            pr.confidence,
+            pr.protected_by,
+            pr.period_start,
+            pr.period_end,
+            pr.update_date
+      FROM plantation_risks pr
+              JOIN pathogenics p ON p.id = pr.pathogenic_id
+      WHERE pr.plantation_id = $1
+        AND pr.kind = 'observed'
+      ORDER BY pr.pathogenic_id, pr.date DESC
+      ",
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Predicted risk of the next days
+  pub(crate) async fn forecast(db: &DataBase, plantation_id: Uuid) -> Result<Vec<PlantationRisk>> {
+    sqlx::query_as!(
+      PlantationRisk,
+      "
+      SELECT pr.pathogenic_id,
+            p.name AS pathogenic_name,
+            pr.date,
+            pr.kind,
+            pr.season_id,
+            pr.level,
+            pr.score,
+            pr.at_risk,
+            pr.hours,
+            pr.temperature_hours,
+            pr.humidity_hours,
+            pr.favourable_hours,
+            pr.mean_temperature,
+            pr.mean_humidity,
+            pr.favourable_min_temperature,
+            pr.favourable_max_temperature,
+            pr.favourable_min_humidity,
+            pr.confidence,
+            pr.protected_by,
+            pr.period_start,
+            pr.period_end,
+            pr.update_date
+      FROM plantation_risks pr
+              JOIN pathogenics p ON p.id = pr.pathogenic_id
+      WHERE pr.plantation_id = $1
+        AND pr.kind = 'predicted'
+        AND pr.date > CURRENT_DATE
+      ORDER BY pr.date, pr.pathogenic_id
+      ",
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn history(
+    db: &DataBase,
+    plantation_id: Uuid,
+    pathogenic_id: Option<i64>,
+    kind: Option<String>,
+    from: NaiveDate,
+    to: NaiveDate,
+  ) -> Result<Vec<PlantationRisk>> {
+    sqlx::query_as!(
+      PlantationRisk,
+      "
+      SELECT pr.pathogenic_id,
+            p.name AS pathogenic_name,
+            pr.date,
+            pr.kind,
+            pr.season_id,
+            pr.level,
+            pr.score,
+            pr.at_risk,
+            pr.hours,
+            pr.temperature_hours,
+            pr.humidity_hours,
+            pr.favourable_hours,
+            pr.mean_temperature,
+            pr.mean_humidity,
+            pr.favourable_min_temperature,
+            pr.favourable_max_temperature,
+            pr.favourable_min_humidity,
+            pr.confidence,
+            pr.protected_by,
+            pr.period_start,
+            pr.period_end,
+            pr.update_date
+      FROM plantation_risks pr
+              JOIN pathogenics p ON p.id = pr.pathogenic_id
+      WHERE pr.plantation_id = $1
+        AND ($2::bigint IS NULL OR pr.pathogenic_id = $2)
+        AND ($3::varchar IS NULL OR pr.kind = $3)
+        AND pr.date BETWEEN $4 AND $5
+      ORDER BY pr.date, pr.pathogenic_id, pr.kind
+      ",
+      plantation_id,
+      pathogenic_id,
+      kind,
+      from,
+      to
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
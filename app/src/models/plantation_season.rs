@@ -0,0 +1,242 @@
+use crate::utils::database::DataBase;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::Result;
+use uuid::Uuid;
+
+/// A crop cycle of a plantation, from planting to harvest. Only one season is
+/// open at a time and new occurrences, activities and risks are linked to it;
+/// the plantation keeps the culture and planting date of the open one.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct PlantationSeason {
+  pub id: Uuid,
+  pub plantation_id: Uuid,
+  pub culture_id: i64,
+  pub planting_date: NaiveDateTime,
+  pub expected_harvest_date: Option<NaiveDate>,
+  pub harvest_date: Option<NaiveDate>,
+  /// kg/ha
+  pub harvest_yield: Option<f64>,
+  pub notes: Option<String>,
+  pub create_date: NaiveDateTime,
+  pub update_date: Option<NaiveDateTime>,
+  pub close_date: Option<NaiveDateTime>,
+}
+
+/// What happened over a season, to compare it with the others of the field.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct SeasonSummary {
+  pub id: Uuid,
+  pub culture_id: i64,
+  pub culture_name: String,
+  pub planting_date: NaiveDateTime,
+  pub harvest_date: Option<NaiveDate>,
+  pub harvest_yield: Option<f64>,
+  /// Planting to harvest, or to today on the open season
+  pub days: Option<i32>,
+  pub occurrences: i64,
+  pub treatments: i64,
+  pub high_risk_days: i64,
+  pub mean_risk_score: Option<f64>,
+  pub close_date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct SeasonOccurrences {
+  pub season_id: Uuid,
+  pub pathogenic_id: i64,
+  pub pathogenic_name: String,
+  pub occurrences: i64,
+}
+
+impl PlantationSeason {
+  pub(crate) async fn all_by_plantation_id(
+    db: &DataBase,
+    plantation_id: Uuid,
+  ) -> Result<Vec<PlantationSeason>> {
+    sqlx::query_as!(
+      PlantationSeason,
+      "
+      SELECT id,
+            plantation_id,
+            culture_id,
+            planting_date,
+            expected_harvest_date,
+            harvest_date,
+            harvest_yield,
+            notes,
+            create_date,
+            update_date,
+            close_date
+      FROM plantation_seasons
+      WHERE plantation_id = $1
+      ORDER BY planting_date DESC
+      ",
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  pub(crate) async fn active(db: &DataBase, plantation_id: Uuid) -> Result<PlantationSeason> {
+    sqlx::query_as!(
+      PlantationSeason,
+      "
+      SELECT id,
+            plantation_id,
+            culture_id,
+            planting_date,
+            expected_harvest_date,
+            harvest_date,
+            harvest_yield,
+            notes,
+            create_date,
+            update_date,
+            close_date
+      FROM plantation_seasons
+      WHERE plantation_id = $1
+        AND close_date IS NULL
+      ",
+      plantation_id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Opens a season and moves the plantation to its culture and planting date.
+  pub(crate) async fn start(
+    db: &DataBase,
+    plantation_id: Uuid,
+    culture_id: i64,
+    planting_date: NaiveDateTime,
+    expected_harvest_date: Option<NaiveDate>,
+    notes: Option<String>,
+  ) -> Result<Uuid> {
+    let mut transaction = db.pool.begin().await?;
+
+    let result = sqlx::query!(
+      "INSERT INTO plantation_seasons (id, plantation_id, culture_id, planting_date, expected_harvest_date, notes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id",
+      Uuid::new_v4(),
+      plantation_id,
+      culture_id,
+      planting_date,
+      expected_harvest_date,
+      notes
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    sqlx::query!(
+      "UPDATE plantations SET culture_id = $2, planting_date = $3, update_date = NOW() WHERE id = $1",
+      plantation_id,
+      culture_id,
+      planting_date
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    transaction.commit().await?;
+
+    Ok(result.id)
+  }
+
+  /// Closes the open season with its harvest, the notes are kept when empty.
+  pub(crate) async fn close(
+    db: &DataBase,
+    id: Uuid,
+    harvest_date: NaiveDate,
+    harvest_yield: Option<f64>,
+    notes: Option<String>,
+  ) -> Result<()> {
+    sqlx::query!(
+      "UPDATE plantation_seasons
+        SET harvest_date  = $2,
+            harvest_yield = $3,
+            notes         = COALESCE($4, notes),
+            close_date    = NOW(),
+            update_date   = NOW()
+        WHERE id = $1",
+      id,
+      harvest_date,
+      harvest_yield,
+      notes
+    )
+    .execute(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
+  pub(crate) async fn summaries(db: &DataBase, plantation_id: Uuid) -> Result<Vec<SeasonSummary>> {
+    sqlx::query_as!(
+      SeasonSummary,
+      r#"
+      SELECT s.id,
+            s.culture_id,
+            c.name AS culture_name,
+            s.planting_date,
+            s.harvest_date,
+            s.harvest_yield,
+            GREATEST(COALESCE(s.harvest_date, CURRENT_DATE) - s.planting_date::date, 0) AS days,
+            (SELECT COUNT(*)
+             FROM plantation_pathogenic_occurrences o
+             WHERE o.season_id = s.id) AS "occurrences!",
+            (SELECT COUNT(*)
+             FROM plantation_activities a
+             WHERE a.season_id = s.id
+               AND a.delete_at IS NULL
+               AND a.kind IN ('fungicide', 'treatment')) AS "treatments!",
+            (SELECT COUNT(*)
+             FROM plantation_risks r
+             WHERE r.season_id = s.id
+               AND r.kind = 'observed'
+               AND r.level = 'high') AS "high_risk_days!",
+            (SELECT AVG(r.score)
+             FROM plantation_risks r
+             WHERE r.season_id = s.id
+               AND r.kind = 'observed') AS mean_risk_score,
+            s.close_date
+      FROM plantation_seasons s
+              JOIN cultures c ON c.id = s.culture_id
+      WHERE s.plantation_id = $1
+      ORDER BY s.planting_date
+      "#,
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+
+  /// Occurrences of each pathogen on each season of the plantation
+  pub(crate) async fn occurrences(
+    db: &DataBase,
+    plantation_id: Uuid,
+  ) -> Result<Vec<SeasonOccurrences>> {
+    sqlx::query_as!(
+      SeasonOccurrences,
+      r#"
+      SELECT o.season_id AS "season_id!",
+            p.id         AS pathogenic_id,
+            p.name       AS pathogenic_name,
+            COUNT(*)     AS "occurrences!"
+      FROM plantation_pathogenic_occurrences o
+              JOIN pathogenics p ON p.id = o.pathogenic_id
+      WHERE o.plantation_id = $1
+        AND o.season_id IS NOT NULL
+      GROUP BY o.season_id, p.id, p.name
+      ORDER BY o.season_id, p.name
+      "#,
+      plantation_id
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -0,0 +1,130 @@
+use crate::utils::database::DataBase;
+use chrono::NaiveDate;
+use sqlx::Result;
+
+/// Confirmed occurrences of a pathogen on a culture around a place, without
+/// anything pointing to the grower or the plantation. The place is the center
+/// of a grid cell, or the station of the municipality, and only places with
+/// occurrences on `MIN_PLANTATIONS` plantations are shown.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct PublicOccurrence {
+  pub pathogenic_id: i64,
+  pub pathogenic_name: String,
+  pub culture_id: i64,
+  pub culture_name: String,
+  pub latitude: f64,
+  pub longitude: f64,
+  pub city: Option<String>,
+  pub uf: Option<String>,
+  pub occurrences: i64,
+  pub first_date: NaiveDate,
+  pub last_date: NaiveDate,
+}
+
+/// Fewest plantations with occurrences a place needs to be shown, so its
+/// dates don't tell the reports of a single grower
+pub(crate) const MIN_PLANTATIONS: i64 = 3;
+
+/// Grid cell of the public occurrences when the request doesn't set one, in
+/// degrees, also where the map places the plantations the user can't read
+pub(crate) const DEFAULT_CELL: f64 = 0.1;
+
+/// Where the public occurrences are placed
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Snap {
+  /// Cells of the given size, in degrees
+  Grid(f64),
+  Municipality,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PublicFilter {
+  /// `min_longitude, min_latitude, max_longitude, max_latitude`
+  pub bbox: Option<[f64; 4]>,
+  pub pathogenic_id: Option<i64>,
+  pub culture_id: Option<i64>,
+  pub from: NaiveDate,
+  pub to: NaiveDate,
+  pub snap: Snap,
+}
+
+impl PublicOccurrence {
+  pub(crate) async fn search(
+    db: &DataBase,
+    filter: &PublicFilter,
+  ) -> Result<Vec<PublicOccurrence>> {
+    let cell = match filter.snap {
+      Snap::Grid(cell) => Some(cell),
+      Snap::Municipality => None,
+    };
+    let [min_longitude, min_latitude, max_longitude, max_latitude] = match filter.bbox {
+      Some(bbox) => bbox.map(Some),
+      None => [None; 4],
+    };
+
+    sqlx::query_as!(
+      PublicOccurrence,
+      r#"
+      WITH occurrences AS (
+          SELECT o.pathogenic_id,
+                 o.plantation_id,
+                 COALESCE(s.culture_id, p.culture_id)                      AS culture_id,
+                 o.occurrence_date,
+                 CASE
+                     WHEN $1::float8 IS NULL THEN st_x(st.location::geometry)
+                     ELSE (FLOOR(st_x(p.location::geometry) / $1) + 0.5) * $1
+                     END                                                   AS latitude,
+                 CASE
+                     WHEN $1::float8 IS NULL THEN st_y(st.location::geometry)
+                     ELSE (FLOOR(st_y(p.location::geometry) / $1) + 0.5) * $1
+                     END                                                   AS longitude,
+                 CASE WHEN $1::float8 IS NULL THEN st.city END             AS city,
+                 CASE WHEN $1::float8 IS NULL THEN st.uf END               AS uf
+          FROM plantation_pathogenic_occurrences o
+                  JOIN plantations p ON p.id = o.plantation_id
+                  LEFT JOIN plantation_seasons s ON s.id = o.season_id
+                  LEFT JOIN stations st ON st.id = p.station_id
+          WHERE o.status = 'confirmed'
+            AND p.delete_at IS NULL
+            AND ($1::float8 IS NOT NULL OR st.id IS NOT NULL)
+            AND o.occurrence_date >= $6
+            AND o.occurrence_date < $7::date + 1)
+      SELECT o.pathogenic_id,
+            pa.name                       AS pathogenic_name,
+            o.culture_id                  AS "culture_id!",
+            c.name                        AS culture_name,
+            o.latitude                    AS "latitude!",
+            o.longitude                   AS "longitude!",
+            o.city,
+            o.uf,
+            COUNT(*)                      AS "occurrences!",
+            MIN(o.occurrence_date)::date  AS "first_date!",
+            MAX(o.occurrence_date)::date  AS "last_date!"
+      FROM occurrences o
+              JOIN pathogenics pa ON pa.id = o.pathogenic_id
+              JOIN cultures c ON c.id = o.culture_id
+      WHERE ($8::bigint IS NULL OR o.pathogenic_id = $8)
+        AND ($9::bigint IS NULL OR o.culture_id = $9)
+        -- On the snapped place, the exact one would show through a shrinking bbox
+        AND ($2::float8 IS NULL OR o.longitude BETWEEN $2 AND $4)
+        AND ($3::float8 IS NULL OR o.latitude BETWEEN $3 AND $5)
+      GROUP BY o.pathogenic_id, pa.name, o.culture_id, c.name, o.latitude, o.longitude, o.city, o.uf
+      HAVING COUNT(DISTINCT o.plantation_id) >= $10
+      ORDER BY MAX(o.occurrence_date) DESC
+      "#,
+      cell,
+      min_longitude,
+      min_latitude,
+      max_longitude,
+      max_latitude,
+      filter.from.and_hms_opt(0, 0, 0).unwrap(),
+      filter.to,
+      filter.pathogenic_id,
+      filter.culture_id,
+      MIN_PLANTATIONS
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
@@ -0,0 +1,384 @@
+use super::station_forecast::StationForecast;
+use chrono::{Duration, NaiveDateTime};
+use std::str::FromStr;
+
+/// Most rain free hours asked before or after an application
+pub(crate) const MAX_RAIN_FREE_HOURS: i64 = 48;
+
+/// Weather limits for applying fungicide. Defaults come from the `SPRAY_*`
+/// environment variables and can be overridden per request.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct SprayRules {
+  /// m/s, calm air hints at temperature inversion and drift
+  pub min_wind_speed: f64,
+  /// m/s
+  pub max_wind_speed: f64,
+  /// Hours without rain before the application, so the leaves are dry
+  pub rain_free_hours_before: i64,
+  /// Hours without rain after the application, so the product isn't washed off
+  pub rain_free_hours_after: i64,
+  /// mm in the hour above which it's considered raining
+  pub rain_threshold: f64,
+  /// %, a likelier rain is considered rain
+  pub max_rain_probability: f64,
+  pub min_temperature: f64,
+  /// Above it the droplets evaporate before reaching the leaves
+  pub max_temperature: f64,
+  pub min_humidity: f64,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Reason {
+  MissingData,
+  WindTooLow,
+  WindTooHigh,
+  Rain,
+  RainBefore,
+  RainAfter,
+  TemperatureTooLow,
+  TemperatureTooHigh,
+  HumidityTooLow,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct SprayHour {
+  pub date: NaiveDateTime,
+  pub suitable: bool,
+  pub reasons: Vec<Reason>,
+  pub temperature: Option<f64>,
+  pub humidity: Option<f64>,
+  pub wind_speed: Option<f64>,
+  pub precipitation: Option<f64>,
+  pub precipitation_probability: Option<f64>,
+  /// Read by the station, a recent hour, rather than forecasted
+  pub observed: bool,
+}
+
+/// Consecutive suitable hours.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct SprayWindow {
+  pub start: NaiveDateTime,
+  pub end: NaiveDateTime,
+  pub hours: i64,
+}
+
+fn env<T: FromStr>(name: &str, default: T) -> T {
+  std::env::var(name)
+    .ok()
+    .and_then(|value| value.parse::<T>().ok())
+    .unwrap_or(default)
+}
+
+impl SprayRules {
+  pub(crate) fn from_env() -> SprayRules {
+    SprayRules {
+      min_wind_speed: env("SPRAY_MIN_WIND_SPEED", 0.8),
+      max_wind_speed: env("SPRAY_MAX_WIND_SPEED", 2.8),
+      rain_free_hours_before: env("SPRAY_RAIN_FREE_HOURS_BEFORE", 2).clamp(0, MAX_RAIN_FREE_HOURS),
+      rain_free_hours_after: env("SPRAY_RAIN_FREE_HOURS_AFTER", 4).clamp(0, MAX_RAIN_FREE_HOURS),
+      rain_threshold: env("SPRAY_RAIN_THRESHOLD", 0.2),
+      max_rain_probability: env("SPRAY_MAX_RAIN_PROBABILITY", 50.0),
+      min_temperature: env("SPRAY_MIN_TEMPERATURE", 10.0),
+      max_temperature: env("SPRAY_MAX_TEMPERATURE", 30.0),
+      min_humidity: env("SPRAY_MIN_HUMIDITY", 55.0),
+    }
+  }
+
+  fn is_rain(&self, forecast: &StationForecast) -> bool {
+    forecast
+      .precipitation
+      .is_some_and(|precipitation| precipitation > self.rain_threshold)
+      || forecast
+        .precipitation_probability
+        .is_some_and(|probability| probability >= self.max_rain_probability)
+  }
+
+  fn rain_between(
+    &self,
+    forecasts: &[StationForecast],
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+  ) -> bool {
+    forecasts
+      .iter()
+      .filter(|forecast| forecast.date >= from && forecast.date <= to)
+      .any(|forecast| self.is_rain(forecast))
+  }
+
+  /// Checks every observed or forecasted hour between `from` and `to`.
+  /// `forecasts` should also hold the hours around them, for the rain before
+  /// and after.
+  pub(crate) fn evaluate(
+    &self,
+    forecasts: &[StationForecast],
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+  ) -> Vec<SprayHour> {
+    forecasts
+      .iter()
+      .filter(|forecast| forecast.date >= from && forecast.date <= to)
+      .map(|forecast| {
+        let mut reasons = vec![];
+
+        match forecast.wind_speed {
+          Some(speed) if speed < self.min_wind_speed => reasons.push(Reason::WindTooLow),
+          Some(speed) if speed > self.max_wind_speed => reasons.push(Reason::WindTooHigh),
+          Some(_) => {}
+          None => reasons.push(Reason::MissingData),
+        }
+
+        match forecast.temperature {
+          Some(temperature) if temperature < self.min_temperature => {
+            reasons.push(Reason::TemperatureTooLow)
+          }
+          Some(temperature) if temperature > self.max_temperature => {
+            reasons.push(Reason::TemperatureTooHigh)
+          }
+          Some(_) => {}
+          None if !reasons.contains(&Reason::MissingData) => reasons.push(Reason::MissingData),
+          None => {}
+        }
+
+        match forecast.humidity {
+          Some(humidity) if humidity < self.min_humidity => reasons.push(Reason::HumidityTooLow),
+          Some(_) => {}
+          None if !reasons.contains(&Reason::MissingData) => reasons.push(Reason::MissingData),
+          None => {}
+        }
+
+        if self.is_rain(forecast) {
+          reasons.push(Reason::Rain);
+        }
+
+        let before = forecast.date - Duration::hours(self.rain_free_hours_before);
+        if self.rain_between(forecasts, before, forecast.date - Duration::hours(1)) {
+          reasons.push(Reason::RainBefore);
+        }
+
+        let after = forecast.date + Duration::hours(self.rain_free_hours_after);
+        if self.rain_between(forecasts, forecast.date + Duration::hours(1), after) {
+          reasons.push(Reason::RainAfter);
+        }
+
+        SprayHour {
+          date: forecast.date,
+          suitable: reasons.is_empty(),
+          reasons,
+          temperature: forecast.temperature,
+          humidity: forecast.humidity,
+          wind_speed: forecast.wind_speed,
+          precipitation: forecast.precipitation,
+          precipitation_probability: forecast.precipitation_probability,
+          observed: forecast.observed,
+        }
+      })
+      .collect()
+  }
+}
+
+/// Joins the suitable hours that follow each other.
+pub(crate) fn windows(hours: &[SprayHour]) -> Vec<SprayWindow> {
+  let mut windows: Vec<SprayWindow> = vec![];
+
+  for hour in hours.iter().filter(|hour| hour.suitable) {
+    match windows.last_mut() {
+      Some(window) if hour.date - window.end == Duration::hours(1) => {
+        window.end = hour.date;
+        window.hours += 1;
+      }
+      _ => windows.push(SprayWindow {
+        start: hour.date,
+        end: hour.date,
+        hours: 1,
+      }),
+    }
+  }
+
+  windows
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::NaiveDate;
+
+  const RULES: SprayRules = SprayRules {
+    min_wind_speed: 0.8,
+    max_wind_speed: 2.8,
+    rain_free_hours_before: 2,
+    rain_free_hours_after: 4,
+    rain_threshold: 0.2,
+    max_rain_probability: 50.0,
+    min_temperature: 10.0,
+    max_temperature: 30.0,
+    min_humidity: 55.0,
+  };
+
+  fn at(hour: i64) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 19)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap()
+      + Duration::hours(hour)
+  }
+
+  /// A dry and calm hour, suitable on every rule
+  fn calm(hour: i64) -> StationForecast {
+    StationForecast {
+      date: at(hour),
+      temperature: Some(20.0),
+      humidity: Some(70.0),
+      precipitation: Some(0.0),
+      precipitation_probability: Some(10.0),
+      wind_speed: Some(1.5),
+      observed: false,
+    }
+  }
+
+  fn rainy(hour: i64) -> StationForecast {
+    StationForecast {
+      precipitation: Some(1.0),
+      ..calm(hour)
+    }
+  }
+
+  fn day(change: impl Fn(i64) -> StationForecast) -> Vec<StationForecast> {
+    (0..24).map(change).collect()
+  }
+
+  /// Reasons of the hour at noon
+  fn reasons(forecasts: &[StationForecast]) -> Vec<Reason> {
+    RULES.evaluate(forecasts, at(12), at(12))[0].reasons.clone()
+  }
+
+  #[test]
+  fn a_dry_calm_hour_is_suitable() {
+    let hours = RULES.evaluate(&day(calm), at(12), at(12));
+
+    assert!(hours[0].suitable);
+    assert!(hours[0].reasons.is_empty());
+  }
+
+  #[test]
+  fn the_wind_must_be_inside_the_band() {
+    let low = day(|hour| StationForecast {
+      wind_speed: Some(0.5),
+      ..calm(hour)
+    });
+    let high = day(|hour| StationForecast {
+      wind_speed: Some(3.5),
+      ..calm(hour)
+    });
+    let on_the_limits = day(|hour| StationForecast {
+      wind_speed: Some(if hour % 2 == 0 { 0.8 } else { 2.8 }),
+      ..calm(hour)
+    });
+
+    assert_eq!(reasons(&low), vec![Reason::WindTooLow]);
+    assert_eq!(reasons(&high), vec![Reason::WindTooHigh]);
+    assert!(RULES
+      .evaluate(&on_the_limits, at(12), at(13))
+      .iter()
+      .all(|hour| hour.suitable));
+  }
+
+  #[test]
+  fn temperature_and_humidity_have_limits() {
+    let cold = day(|hour| StationForecast {
+      temperature: Some(5.0),
+      ..calm(hour)
+    });
+    let hot = day(|hour| StationForecast {
+      temperature: Some(35.0),
+      humidity: Some(40.0),
+      ..calm(hour)
+    });
+
+    assert_eq!(reasons(&cold), vec![Reason::TemperatureTooLow]);
+    assert_eq!(
+      reasons(&hot),
+      vec![Reason::TemperatureTooHigh, Reason::HumidityTooLow]
+    );
+  }
+
+  #[test]
+  fn missing_readings_are_reported_once() {
+    let missing = day(|hour| StationForecast {
+      wind_speed: None,
+      temperature: None,
+      humidity: None,
+      ..calm(hour)
+    });
+
+    assert_eq!(reasons(&missing), vec![Reason::MissingData]);
+  }
+
+  #[test]
+  fn a_likely_rain_counts_as_rain() {
+    let forecasts = day(|hour| match hour {
+      12 => StationForecast {
+        precipitation_probability: Some(50.0),
+        ..calm(hour)
+      },
+      _ => calm(hour),
+    });
+
+    assert_eq!(reasons(&forecasts), vec![Reason::Rain]);
+  }
+
+  #[test]
+  fn rain_in_the_hours_before_or_after_isnt_suitable() {
+    let before = day(|hour| if hour == 10 { rainy(hour) } else { calm(hour) });
+    let after = day(|hour| if hour == 16 { rainy(hour) } else { calm(hour) });
+
+    assert_eq!(reasons(&before), vec![Reason::RainBefore]);
+    assert_eq!(reasons(&after), vec![Reason::RainAfter]);
+  }
+
+  #[test]
+  fn rain_beyond_the_rain_free_hours_is_ignored() {
+    let forecasts = day(|hour| match hour {
+      9 | 17 => rainy(hour),
+      _ => calm(hour),
+    });
+
+    assert!(reasons(&forecasts).is_empty());
+  }
+
+  #[test]
+  fn only_the_hours_asked_are_evaluated() {
+    let hours = RULES.evaluate(&day(calm), at(6), at(8));
+
+    assert_eq!(
+      hours.iter().map(|hour| hour.date).collect::<Vec<_>>(),
+      vec![at(6), at(7), at(8)]
+    );
+  }
+
+  #[test]
+  fn consecutive_suitable_hours_are_one_window() {
+    let forecasts = day(|hour| if hour == 12 { rainy(hour) } else { calm(hour) });
+
+    // 12 has rain, 8 to 11 have it after and 13 to 14 before
+    let windows = windows(&RULES.evaluate(&forecasts, at(0), at(23)));
+
+    assert_eq!(windows.len(), 2);
+    assert_eq!((windows[0].start, windows[0].end, windows[0].hours), (at(0), at(7), 8));
+    assert_eq!((windows[1].start, windows[1].end, windows[1].hours), (at(15), at(23), 9));
+  }
+
+  #[test]
+  fn a_missing_hour_splits_the_window() {
+    let forecasts = day(calm)
+      .into_iter()
+      .filter(|forecast| forecast.date != at(5))
+      .collect::<Vec<_>>();
+
+    let windows = windows(&RULES.evaluate(&forecasts, at(0), at(10)));
+
+    assert_eq!(windows.len(), 2);
+    assert_eq!(windows[0].hours, 5);
+    assert_eq!(windows[1].start, at(6));
+  }
+}
@@ -0,0 +1,71 @@
+use crate::utils::database::DataBase;
+use chrono::NaiveDateTime;
+use sqlx::Result;
+
+/// Hourly weather of a station, forecasted by the crawler `forecast-risk`
+/// script or observed by INMET. Dates are in UTC.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct StationForecast {
+  pub date: NaiveDateTime,
+  pub temperature: Option<f64>,
+  pub humidity: Option<f64>,
+  pub precipitation: Option<f64>,
+  pub precipitation_probability: Option<f64>,
+  pub wind_speed: Option<f64>,
+  /// Read by the station rather than forecasted
+  pub observed: bool,
+}
+
+impl StationForecast {
+  /// Hours between `from` and `to`. The readings of the station win over the
+  /// forecasts of the same hour, and among the forecasts the latest fetch.
+  pub(crate) async fn between(
+    db: &DataBase,
+    station_id: i64,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+  ) -> Result<Vec<StationForecast>> {
+    sqlx::query_as!(
+      StationForecast,
+      r#"
+      SELECT DISTINCT ON (date) date AS "date!",
+            temperature,
+            humidity,
+            precipitation,
+            precipitation_probability,
+            wind_speed,
+            observed AS "observed!"
+      FROM (SELECT date,
+                   temperature,
+                   humidity,
+                   precipitation,
+                   NULL::float8 AS precipitation_probability,
+                   wind_speed,
+                   TRUE         AS observed,
+                   fetch_date
+            FROM station_observations
+            WHERE station_id = $1
+              AND date BETWEEN $2 AND $3
+            UNION ALL
+            SELECT date,
+                   temperature,
+                   humidity,
+                   precipitation,
+                   precipitation_probability,
+                   wind_speed,
+                   FALSE AS observed,
+                   fetch_date
+            FROM station_forecasts
+            WHERE station_id = $1
+              AND date BETWEEN $2 AND $3) hours
+      ORDER BY date, observed DESC, fetch_date DESC
+      "#,
+      station_id,
+      from,
+      to
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(DataBase::database_error)
+  }
+}
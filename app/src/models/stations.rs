@@ -1,8 +1,9 @@
 use crate::utils::database::DataBase;
 use sqlx::Result;
+use std::collections::HashMap;
 
 #[derive(Debug, serde::Serialize, Clone)]
-pub(crate) struct Station {
+pub struct Station {
   pub id: i64,
   pub city: String,
   pub uf: String,
@@ -14,8 +15,20 @@ pub(crate) struct Station {
   pub update_date: Option<chrono::NaiveDateTime>,
 }
 
+/// Station used by plantations that stopped returning observations.
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct StaleStation {
+  pub id: i64,
+  pub city: String,
+  pub uf: String,
+  pub inmet_code: Option<String>,
+  pub last_fetch_date: Option<chrono::NaiveDateTime>,
+  pub last_data_date: Option<chrono::NaiveDateTime>,
+  pub plantations: i64,
+}
+
 impl Station {
-  pub(crate) async fn find_by_id(db: &DataBase, id: i64) -> Station {
+  pub async fn find_by_id(db: &DataBase, id: i64) -> Station {
     sqlx::query_as!(
       Station,
       "
@@ -38,6 +51,36 @@ impl Station {
     .unwrap()
   }
 
+  /// The stations of `ids` by id, in a single query
+  pub async fn find_by_ids(db: &DataBase, ids: &[i64]) -> Result<HashMap<i64, Station>> {
+    let stations = sqlx::query_as!(
+      Station,
+      "
+      SELECT id,
+            city,
+            uf,
+            st_x(stations.location::geometry) AS latitude,
+            st_y(stations.location::geometry) AS longitude,
+            status,
+            inmet_code,
+            create_date,
+            update_date
+      FROM stations
+      WHERE id = ANY($1);
+        ",
+      ids
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    Ok(
+      stations
+        .into_iter()
+        .map(|station| (station.id, station))
+        .collect(),
+    )
+  }
+
   pub(crate) async fn find_closest_by_latitude_longitude(
     db: &DataBase,
     latitude: f64,
@@ -65,4 +108,32 @@ impl Station {
     .fetch_one(&db.pool)
     .await
   }
+
+  /// Active stations with plantations whose newest observation is older than
+  /// `hours`, or that never returned any.
+  pub(crate) async fn stale(db: &DataBase, hours: i32) -> Result<Vec<StaleStation>> {
+    sqlx::query_as!(
+      StaleStation,
+      r#"
+      SELECT s.id,
+            s.city,
+            s.uf,
+            s.inmet_code,
+            s.last_fetch_date,
+            s.last_data_date,
+            COUNT(p.id) AS "plantations!"
+      FROM stations s
+              JOIN plantations p ON p.station_id = s.id
+      WHERE s.status = TRUE
+        AND s.inmet_code IS NOT NULL
+        AND p.delete_at IS NULL
+        AND (s.last_data_date IS NULL OR s.last_data_date < NOW() - make_interval(hours => $1))
+      GROUP BY s.id
+      ORDER BY s.last_data_date NULLS FIRST
+        "#,
+      hours
+    )
+    .fetch_all(&db.pool)
+    .await
+  }
 }
@@ -0,0 +1,208 @@
+use crate::{
+  models::{plantation_pathogenic_occurrences::RegionalFilter, public_occurrence},
+  utils::database::DataBase,
+};
+use sqlx::Result;
+use uuid::Uuid;
+
+/// Deepest zoom served, plantations are already a few pixels wide
+const MAX_ZOOM: i32 = 20;
+
+/// A map tile in the XYZ scheme.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tile {
+  pub z: i32,
+  pub x: i32,
+  pub y: i32,
+}
+
+impl Tile {
+  pub(crate) fn new(z: i32, x: i32, y: i32) -> Option<Tile> {
+    if !(0..=MAX_ZOOM).contains(&z) {
+      return None;
+    }
+
+    let size = 1 << z;
+
+    ((0..size).contains(&x) && (0..size).contains(&y)).then_some(Tile { z, x, y })
+  }
+
+  /// Occurrences on the plantations the user can read, with their id, and the
+  /// regional ones of the user's plantations at the center of their grid cell.
+  pub(crate) async fn occurrences(
+    &self,
+    db: &DataBase,
+    user_id: Uuid,
+    filter: &RegionalFilter,
+  ) -> Result<Vec<u8>> {
+    let tile = sqlx::query_scalar!(
+      r#"
+      WITH bounds AS (SELECT ST_TileEnvelope($1, $2, $3) AS geom),
+           -- The tile in degrees, one grid cell wider for the snapped points
+           -- of the plantations just outside it
+           area AS (SELECT box(point(ST_YMin(envelope) - $10, ST_XMin(envelope) - $10),
+                               point(ST_YMax(envelope) + $10, ST_XMax(envelope) + $10)) AS box
+                    FROM (SELECT ST_Transform(geom, 4326) AS envelope FROM bounds) tile),
+           own AS (SELECT p.id, p.location
+                   FROM plantations p
+                   WHERE p.farm_id IN (SELECT farm_id FROM farm_members WHERE user_id = $4)
+                     AND p.delete_at IS NULL),
+           readable AS (SELECT id
+                        FROM own
+                        UNION
+                        SELECT p.id
+                        FROM plantations p
+                                JOIN farm_members fm ON fm.farm_id = p.farm_id
+                                JOIN organization_members grower ON grower.user_id = fm.user_id
+                                JOIN organization_members agronomist
+                                     ON agronomist.organization_id = grower.organization_id
+                                JOIN organizations org ON org.id = grower.organization_id
+                        WHERE fm.role = 'owner'
+                          AND agronomist.user_id = $4
+                          AND agronomist.role = 'agronomist'
+                          AND org.delete_at IS NULL),
+           located AS (SELECT o.id,
+                              o.plantation_id IN (SELECT id FROM readable) AS readable,
+                              o.pathogenic_id,
+                              pa.name                                      AS pathogenic_name,
+                              o.occurrence_date::date::text                AS occurrence_date,
+                              o.status,
+                              o.occurrence_date                            AS date,
+                              p.location
+                       FROM plantations p
+                               JOIN area ON p.location <@ area.box
+                               JOIN plantation_pathogenic_occurrences o ON o.plantation_id = p.id
+                               JOIN pathogenics pa ON pa.id = o.pathogenic_id
+                       WHERE p.delete_at IS NULL),
+           -- Like the regional feed, the plantations the user can't read are
+           -- only placed at the center of their grid cell
+           points AS (SELECT located.*,
+                             ST_Transform(ST_SetSRID(ST_MakePoint(
+                                                         CASE
+                                                             WHEN located.readable
+                                                                 THEN st_y(located.location::geometry)
+                                                             ELSE (FLOOR(st_y(located.location::geometry) / $10) + 0.5) * $10
+                                                             END,
+                                                         CASE
+                                                             WHEN located.readable
+                                                                 THEN st_x(located.location::geometry)
+                                                             ELSE (FLOOR(st_x(located.location::geometry) / $10) + 0.5) * $10
+                                                             END), 4326),
+                                          3857) AS geom
+                      FROM located),
+           mvt AS (SELECT CASE WHEN points.readable THEN points.id END AS id,
+                          points.pathogenic_id,
+                          points.pathogenic_name,
+                          points.occurrence_date,
+                          points.status,
+                          ST_AsMVTGeom(points.geom, bounds.geom)          AS geom
+                   FROM points,
+                        bounds
+                   WHERE ST_Intersects(points.geom, bounds.geom)
+                     AND (points.readable
+                       OR (points.status = ANY ($5)
+                           AND ($7::bigint IS NULL OR points.pathogenic_id = $7)
+                           AND ($8::timestamp IS NULL OR points.date >= $8)
+                           AND ($9::timestamp IS NULL OR points.date <= $9)
+                           AND EXISTS(SELECT 1
+                                      FROM own
+                                      WHERE st_distancesphere(own.location::geometry,
+                                                              points.location::geometry) < $6::float8 * 1000))))
+      SELECT ST_AsMVT(mvt.*, 'occurrences') AS tile
+      FROM mvt
+      "#,
+      self.z,
+      self.x,
+      self.y,
+      user_id,
+      filter.statuses(),
+      filter.radius_km,
+      filter.pathogenic_id,
+      filter.from,
+      filter.to,
+      public_occurrence::DEFAULT_CELL
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(tile.unwrap_or_default())
+  }
+
+  /// Plantations of the farms the user is a member of
+  pub(crate) async fn plantations(&self, db: &DataBase, user_id: Uuid) -> Result<Vec<u8>> {
+    let tile = sqlx::query_scalar!(
+      r#"
+      WITH bounds AS (SELECT ST_TileEnvelope($1, $2, $3) AS geom),
+           points AS (SELECT p.id,
+                             p.alias,
+                             p.culture_id,
+                             c.name                                                   AS culture_name,
+                             p.area,
+                             ST_Transform(ST_SetSRID(ST_MakePoint(st_y(p.location::geometry),
+                                                                  st_x(p.location::geometry)), 4326),
+                                          3857)                                       AS geom
+                      FROM plantations p
+                              JOIN cultures c ON c.id = p.culture_id
+                      WHERE p.farm_id IN (SELECT farm_id FROM farm_members WHERE user_id = $4)
+                        AND p.delete_at IS NULL),
+           mvt AS (SELECT points.id,
+                          points.alias,
+                          points.culture_id,
+                          points.culture_name,
+                          points.area,
+                          ST_AsMVTGeom(points.geom, bounds.geom) AS geom
+                   FROM points,
+                        bounds
+                   WHERE ST_Intersects(points.geom, bounds.geom))
+      SELECT ST_AsMVT(mvt.*, 'plantations') AS tile
+      FROM mvt
+      "#,
+      self.z,
+      self.x,
+      self.y,
+      user_id
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(tile.unwrap_or_default())
+  }
+
+  /// Active stations
+  pub(crate) async fn stations(&self, db: &DataBase) -> Result<Vec<u8>> {
+    let tile = sqlx::query_scalar!(
+      r#"
+      WITH bounds AS (SELECT ST_TileEnvelope($1, $2, $3) AS geom),
+           points AS (SELECT s.id,
+                             s.city,
+                             s.uf,
+                             s.inmet_code,
+                             ST_Transform(ST_SetSRID(ST_MakePoint(st_y(s.location::geometry),
+                                                                  st_x(s.location::geometry)), 4326),
+                                          3857)                                       AS geom
+                      FROM stations s
+                      WHERE s.status = TRUE),
+           mvt AS (SELECT points.id,
+                          points.city,
+                          points.uf,
+                          points.inmet_code,
+                          ST_AsMVTGeom(points.geom, bounds.geom) AS geom
+                   FROM points,
+                        bounds
+                   WHERE ST_Intersects(points.geom, bounds.geom))
+      SELECT ST_AsMVT(mvt.*, 'stations') AS tile
+      FROM mvt
+      "#,
+      self.z,
+      self.x,
+      self.y
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(tile.unwrap_or_default())
+  }
+}
@@ -13,6 +13,9 @@ pub(crate) struct User {
   #[serde(with = "ts_seconds")]
   pub create_date: NaiveDateTime,
   pub notification_token: Option<String>,
+  pub role: String,
+  /// Radius of the regional occurrences, the default one when empty
+  pub occurrence_radius_km: Option<f64>,
 }
 
 #[derive(Debug, serde::Serialize, Clone)]
@@ -100,6 +103,23 @@ impl User {
     Ok(())
   }
 
+  pub(crate) async fn update_occurrence_radius(
+    database: &DataBase,
+    uid: Uuid,
+    radius_km: Option<f64>,
+  ) -> Result<()> {
+    sqlx::query!(
+      "UPDATE users SET occurrence_radius_km = $1 WHERE id = $2",
+      radius_km,
+      uid
+    )
+    .execute(&database.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
   pub(crate) async fn get_notifications(
     database: DataBase,
     uid: Uuid,
@@ -114,6 +134,19 @@ impl User {
     .map_err(DataBase::database_error)?)
   }
 
+  pub(crate) async fn notify(database: &DataBase, uid: Uuid, message: &str) -> Result<()> {
+    sqlx::query!(
+      "INSERT INTO user_notifications (user_id, message) VALUES ($1, $2)",
+      uid,
+      message
+    )
+    .execute(&database.pool)
+    .await
+    .map_err(DataBase::database_error)?;
+
+    Ok(())
+  }
+
   pub(crate) async fn update_viewed_notifications(database: DataBase, uid: Uuid) -> Result<()> {
     sqlx::query!(
       "UPDATE user_notifications SET viewed = true WHERE user_id = $1",
@@ -0,0 +1,150 @@
+use super::Storage;
+use crate::utils::jwt;
+use async_trait::async_trait;
+use std::{
+  io::{self, ErrorKind},
+  path::PathBuf,
+  time::Duration,
+};
+
+/// Keeps the files on disk, under `STORAGE_LOCAL_PATH` (`app/images` by
+/// default). Downloads go through the `/images` route, which only accepts
+/// URLs signed by [`LocalStorage::signed_url`].
+pub(crate) struct LocalStorage {
+  root: PathBuf,
+}
+
+impl LocalStorage {
+  pub(crate) fn from_env() -> Self {
+    Self {
+      root: PathBuf::from(std::env::var("STORAGE_LOCAL_PATH").unwrap_or("app/images".to_string())),
+    }
+  }
+
+  fn path(&self, key: &str) -> io::Result<PathBuf> {
+    if key.is_empty() || key.starts_with('/') || key.split('/').any(|part| part == "..") {
+      return Err(io::Error::new(
+        ErrorKind::InvalidInput,
+        "invalid storage key",
+      ));
+    }
+
+    Ok(self.root.join(key))
+  }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+  async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> io::Result<()> {
+    let path = self.path(key)?;
+
+    if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+
+    tokio::fs::write(path, bytes).await
+  }
+
+  async fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+    tokio::fs::read(self.path(key)?).await
+  }
+
+  async fn delete(&self, key: &str) -> io::Result<()> {
+    match tokio::fs::remove_file(self.path(key)?).await {
+      Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+      result => result,
+    }
+  }
+
+  fn signed_url(&self, key: &str, ttl: Duration) -> String {
+    format!(
+      "/images/{}?token={}",
+      key,
+      jwt::encode_image(
+        key.to_string(),
+        chrono::Duration::seconds(ttl.as_secs() as i64)
+      )
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn storage() -> LocalStorage {
+    LocalStorage {
+      root: std::env::temp_dir().join(format!("cropi-storage-{}", uuid::Uuid::new_v4())),
+    }
+  }
+
+  #[test]
+  fn keys_stay_under_the_root() {
+    let storage = storage();
+
+    assert_eq!(
+      storage.path("ocurrences/image.png").unwrap(),
+      storage.root.join("ocurrences/image.png")
+    );
+  }
+
+  #[test]
+  fn path_traversal_is_rejected() {
+    let storage = storage();
+
+    for key in [
+      "",
+      "..",
+      "../.env",
+      "ocurrences/../../.env",
+      "ocurrences/..",
+      "/etc/passwd",
+    ] {
+      assert_eq!(
+        storage.path(key).unwrap_err().kind(),
+        ErrorKind::InvalidInput,
+        "{}",
+        key
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn put_get_and_delete_a_file() {
+    let storage = storage();
+
+    storage
+      .put("ocurrences/image.png", b"image".to_vec(), "image/png")
+      .await
+      .unwrap();
+    assert_eq!(storage.get("ocurrences/image.png").await.unwrap(), b"image");
+
+    storage.delete("ocurrences/image.png").await.unwrap();
+    assert_eq!(
+      storage
+        .get("ocurrences/image.png")
+        .await
+        .unwrap_err()
+        .kind(),
+      ErrorKind::NotFound
+    );
+
+    // Deleting what isn't there isn't an error
+    storage.delete("ocurrences/image.png").await.unwrap();
+
+    let _ = std::fs::remove_dir_all(&storage.root);
+  }
+
+  #[tokio::test]
+  async fn traversal_never_reaches_the_disk() {
+    let storage = storage();
+
+    assert!(storage
+      .put("../escaped.png", b"image".to_vec(), "image/png")
+      .await
+      .is_err());
+    assert!(storage.get("../../etc/passwd").await.is_err());
+    assert!(storage.delete("../escaped.png").await.is_err());
+    assert!(!storage.root.parent().unwrap().join("escaped.png").exists());
+  }
+}
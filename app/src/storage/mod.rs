@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use std::{io, sync::Arc, time::Duration};
+
+pub mod local;
+pub mod s3;
+
+/// Backend where uploaded files (ocurrence images) are kept.
+///
+/// Keys are relative paths like `ocurrences/<uuid>.png`, they are what gets
+/// persisted on the database, never the URL handed to the client.
+#[async_trait]
+pub(crate) trait Storage: Send + Sync {
+  async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> io::Result<()>;
+
+  async fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+
+  async fn delete(&self, key: &str) -> io::Result<()>;
+
+  /// Time limited URL the client can use to download the file without the
+  /// Authorization header.
+  fn signed_url(&self, key: &str, ttl: Duration) -> String;
+}
+
+pub(crate) type SharedStorage = Arc<dyn Storage>;
+
+/// Builds the backend configured by `STORAGE_DRIVER` (`local` or `s3`).
+pub(crate) fn from_env() -> SharedStorage {
+  match std::env::var("STORAGE_DRIVER")
+    .unwrap_or("local".to_string())
+    .as_str()
+  {
+    "s3" => Arc::new(s3::S3Storage::from_env()),
+    "local" => Arc::new(local::LocalStorage::from_env()),
+    driver => panic!("storage driver not supported: {}", driver),
+  }
+}
+
+/// How long the signed image URLs stay valid, `STORAGE_URL_TTL` in seconds.
+pub(crate) fn url_ttl() -> Duration {
+  Duration::from_secs(
+    std::env::var("STORAGE_URL_TTL")
+      .ok()
+      .and_then(|ttl| ttl.parse::<u64>().ok())
+      .unwrap_or(900),
+  )
+}
+
+/// Images used to be saved as `/images/ocurrences/<uuid>.png`, strip the old
+/// public prefix so both formats resolve to the same key.
+pub(crate) fn key_from_image(image: &str) -> String {
+  image.trim_start_matches("/images/").to_string()
+}
+
+/// Signed URL of a saved image, for the responses.
+pub(crate) fn image_url(storage: &SharedStorage, image: &Option<String>) -> Option<String> {
+  image
+    .as_ref()
+    .map(|image| storage.signed_url(&key_from_image(image), url_ttl()))
+}
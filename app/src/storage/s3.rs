@@ -0,0 +1,158 @@
+use super::Storage;
+use async_trait::async_trait;
+use reqwest::Client;
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+use std::{
+  io::{self, ErrorKind},
+  time::Duration,
+};
+
+/// Any S3 compatible service (AWS, MinIO, R2...). Requests are presigned, the
+/// client downloads straight from the bucket.
+pub(crate) struct S3Storage {
+  bucket: Bucket,
+  credentials: Credentials,
+  client: Client,
+}
+
+impl S3Storage {
+  pub(crate) fn from_env() -> Self {
+    let endpoint = std::env::var("S3_ENDPOINT").expect("S3_ENDPOINT");
+
+    let bucket = Bucket::new(
+      endpoint.parse().expect("S3_ENDPOINT must be a valid url"),
+      UrlStyle::Path,
+      std::env::var("S3_BUCKET").expect("S3_BUCKET"),
+      std::env::var("S3_REGION").unwrap_or("us-east-1".to_string()),
+    )
+    .expect("invalid S3 bucket configuration");
+
+    Self {
+      bucket,
+      credentials: Credentials::new(
+        std::env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY"),
+        std::env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY"),
+      ),
+      client: Client::new(),
+    }
+  }
+}
+
+fn request_error(err: reqwest::Error) -> io::Error {
+  tracing::error!("Failed to execute storage request: {:?}", err);
+  io::Error::other(err)
+}
+
+fn status_error(status: reqwest::StatusCode) -> io::Error {
+  if status == reqwest::StatusCode::NOT_FOUND {
+    return io::Error::new(ErrorKind::NotFound, "object not found");
+  }
+
+  io::Error::other(format!("storage responded {}", status))
+}
+
+const REQUEST_TTL: Duration = Duration::from_secs(60);
+
+#[async_trait]
+impl Storage for S3Storage {
+  async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> io::Result<()> {
+    let url = self
+      .bucket
+      .put_object(Some(&self.credentials), key)
+      .sign(REQUEST_TTL);
+
+    let response = self
+      .client
+      .put(url)
+      .header("Content-Type", content_type)
+      .body(bytes)
+      .send()
+      .await
+      .map_err(request_error)?;
+
+    if !response.status().is_success() {
+      return Err(status_error(response.status()));
+    }
+
+    Ok(())
+  }
+
+  async fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+    let url = self
+      .bucket
+      .get_object(Some(&self.credentials), key)
+      .sign(REQUEST_TTL);
+
+    let response = self.client.get(url).send().await.map_err(request_error)?;
+
+    if !response.status().is_success() {
+      return Err(status_error(response.status()));
+    }
+
+    Ok(response.bytes().await.map_err(request_error)?.to_vec())
+  }
+
+  async fn delete(&self, key: &str) -> io::Result<()> {
+    let url = self
+      .bucket
+      .delete_object(Some(&self.credentials), key)
+      .sign(REQUEST_TTL);
+
+    let response = self
+      .client
+      .delete(url)
+      .send()
+      .await
+      .map_err(request_error)?;
+
+    if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+      return Err(status_error(response.status()));
+    }
+
+    Ok(())
+  }
+
+  fn signed_url(&self, key: &str, ttl: Duration) -> String {
+    self
+      .bucket
+      .get_object(Some(&self.credentials), key)
+      .sign(ttl)
+      .to_string()
+  }
+}
+
+/// Runs against the bucket on the `S3_*` variables, a local MinIO:
+/// `cargo test -p app storage::s3 -- --ignored`
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+  #[tokio::test]
+  #[ignore = "needs an S3 compatible service on S3_ENDPOINT"]
+  async fn put_get_and_delete_an_object() {
+    dotenv::dotenv().ok();
+
+    let storage = S3Storage::from_env();
+    let key = format!("tests/{}.png", uuid::Uuid::new_v4());
+
+    storage.put(&key, PNG.to_vec(), "image/png").await.unwrap();
+    assert_eq!(storage.get(&key).await.unwrap(), PNG);
+
+    let signed = reqwest::get(storage.signed_url(&key, Duration::from_secs(60)))
+      .await
+      .unwrap();
+    assert!(signed.status().is_success());
+    assert_eq!(signed.bytes().await.unwrap(), PNG);
+
+    storage.delete(&key).await.unwrap();
+    assert_eq!(
+      storage.get(&key).await.unwrap_err().kind(),
+      ErrorKind::NotFound
+    );
+
+    // Deleting what isn't there isn't an error
+    storage.delete(&key).await.unwrap();
+  }
+}
@@ -1,21 +1,37 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 
+/// Audience of the tokens signing the image URLs, never accepted as a login
+const IMAGE_AUDIENCE: &str = "image";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Claims {
   pub sub: String,
   pub company: String,
   pub exp: usize,
+  /// Empty on the login tokens
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub aud: Option<String>,
 }
 
 pub(crate) fn encode(sub: String) -> String {
+  encode_claims(sub, Duration::days(182), None)
+}
+
+/// Token of an image URL, `sub` being the storage key
+pub(crate) fn encode_image(key: String, ttl: Duration) -> String {
+  encode_claims(key, ttl, Some(IMAGE_AUDIENCE.to_string()))
+}
+
+fn encode_claims(sub: String, ttl: Duration, aud: Option<String>) -> String {
   let secret = std::env::var("APP_SECRET").unwrap();
 
   let claims = Claims {
     sub,
     company: "Cropi".to_string(),
-    exp: (Utc::now() + Duration::days(182)).timestamp() as usize,
+    exp: (Utc::now() + ttl).timestamp() as usize,
+    aud,
   };
 
   let token = jsonwebtoken::encode(
@@ -31,14 +47,102 @@ pub(crate) fn encode(sub: String) -> String {
   token.unwrap()
 }
 
+/// Decodes a login token, rejecting the image ones
 pub(crate) fn decode(
   jwt: String,
+) -> Result<jsonwebtoken::TokenData<Claims>, jsonwebtoken::errors::Error> {
+  let token = decode_with(jwt, Validation::default())?;
+
+  if token.claims.aud.is_some() {
+    return Err(ErrorKind::InvalidAudience.into());
+  }
+
+  Ok(token)
+}
+
+/// Decodes the token of an image URL
+pub(crate) fn decode_image(
+  jwt: String,
+) -> Result<jsonwebtoken::TokenData<Claims>, jsonwebtoken::errors::Error> {
+  let mut validation = Validation::default();
+  validation.set_audience(&[IMAGE_AUDIENCE]);
+  validation.set_required_spec_claims(&["exp", "aud"]);
+
+  decode_with(jwt, validation)
+}
+
+fn decode_with(
+  jwt: String,
+  validation: Validation,
 ) -> Result<jsonwebtoken::TokenData<Claims>, jsonwebtoken::errors::Error> {
   let secret = std::env::var("APP_SECRET").unwrap();
 
   jsonwebtoken::decode::<Claims>(
     &jwt,
     &DecodingKey::from_secret(secret.as_ref()),
-    &Validation::default(),
+    &validation,
   )
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn with_secret() {
+    std::env::set_var("APP_SECRET", "test");
+  }
+
+  #[test]
+  fn login_tokens_decode() {
+    with_secret();
+
+    let token = encode("user".to_string());
+
+    assert_eq!(decode(token).unwrap().claims.sub, "user");
+  }
+
+  #[test]
+  fn image_tokens_decode_as_images() {
+    with_secret();
+
+    let token = encode_image("ocurrences/image.png".to_string(), Duration::minutes(5));
+
+    assert_eq!(
+      decode_image(token).unwrap().claims.sub,
+      "ocurrences/image.png"
+    );
+  }
+
+  #[test]
+  fn image_tokens_arent_logins() {
+    with_secret();
+
+    let token = encode_image("ocurrences/image.png".to_string(), Duration::minutes(5));
+
+    assert_eq!(
+      decode(token).unwrap_err().kind(),
+      &ErrorKind::InvalidAudience
+    );
+  }
+
+  #[test]
+  fn login_tokens_arent_images() {
+    with_secret();
+
+    let token = encode("user".to_string());
+
+    assert!(decode_image(token).is_err());
+  }
+
+  #[test]
+  fn expired_image_tokens_are_rejected() {
+    with_secret();
+
+    let token = encode_image("ocurrences/image.png".to_string(), Duration::minutes(-5));
+
+    assert_eq!(
+      decode_image(token).unwrap_err().kind(),
+      &ErrorKind::ExpiredSignature
+    );
+  }
+}
@@ -1,5 +1,6 @@
-pub(crate) mod database;
+pub mod database;
 pub(crate) mod google_jwt;
 pub(crate) mod jwt;
+pub mod pagination;
 pub(crate) mod request_error;
 pub(crate) mod response;
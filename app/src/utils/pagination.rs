@@ -0,0 +1,242 @@
+use crate::utils::response::JsonError;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(crate) const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 200;
+
+/// Cursor pagination, sorting and field selection of a listing
+#[derive(Debug, Deserialize)]
+pub(crate) struct PageQuery {
+  pub limit: Option<i64>,
+  /// Id of the last item of the previous page
+  pub cursor: Option<Uuid>,
+  /// Field to sort by, descending with a leading `-`
+  pub sort: Option<String>,
+  /// Comma separated fields of each item, all of them when empty
+  pub fields: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+  pub limit: i64,
+  pub cursor: Option<Uuid>,
+  pub sort: String,
+  pub descending: bool,
+  pub fields: Option<Vec<String>>,
+}
+
+impl PageQuery {
+  /// The page of a listing sorted by one of `sorts`, `default_sort` when the
+  /// request doesn't say, and with items made of `fields`
+  pub(crate) fn page(
+    self,
+    sorts: &[&str],
+    default_sort: &str,
+    fields: &[&str],
+  ) -> Result<Page, Vec<JsonError>> {
+    let mut errors = vec![];
+
+    let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+      errors.push(JsonError::new(
+        "limit".to_string(),
+        format!("must be between 1 and {}", MAX_LIMIT),
+      ));
+    }
+
+    let sort = self.sort.unwrap_or(default_sort.to_string());
+    let (descending, field) = match sort.strip_prefix('-') {
+      Some(field) => (true, field),
+      None => (false, sort.as_str()),
+    };
+    if !sorts.contains(&field) {
+      errors.push(JsonError::new(
+        "sort".to_string(),
+        format!("must be one of {}, - first to descend", sorts.join(", ")),
+      ));
+    }
+
+    let selected = self.fields.map(|selected| {
+      selected
+        .split(',')
+        .map(|field| field.trim().to_string())
+        .filter(|field| !field.is_empty())
+        .collect::<Vec<_>>()
+    });
+    if let Some(unknown) = selected
+      .iter()
+      .flatten()
+      .find(|field| !fields.contains(&field.as_str()))
+    {
+      errors.push(JsonError::new(
+        "fields".to_string(),
+        format!("{} is not one of {}", unknown, fields.join(", ")),
+      ));
+    }
+
+    if !errors.is_empty() {
+      return Err(errors);
+    }
+
+    Ok(Page {
+      limit,
+      cursor: self.cursor,
+      sort: field.to_string(),
+      descending,
+      fields: selected,
+    })
+  }
+}
+
+impl Page {
+  /// Rows to fetch, one past the limit tells whether there's a next page
+  pub fn fetch_limit(&self) -> i64 {
+    self.limit + 1
+  }
+
+  /// `items` under `key`, with only the selected fields, and where the next
+  /// page starts
+  pub(crate) fn envelope<T: Serialize>(
+    &self,
+    key: &str,
+    mut items: Vec<T>,
+    id: impl Fn(&T) -> Uuid,
+  ) -> serde_json::Value {
+    let has_more = items.len() as i64 > self.limit;
+    items.truncate(self.limit as usize);
+
+    let next_cursor = items.last().filter(|_| has_more).map(id);
+
+    let items = items
+      .iter()
+      .map(|item| {
+        let mut value = serde_json::to_value(item).unwrap();
+
+        if let (Some(fields), Some(object)) = (&self.fields, value.as_object_mut()) {
+          object.retain(|field, _| fields.contains(field));
+        }
+
+        value
+      })
+      .collect::<Vec<_>>();
+
+    serde_json::json!({
+      key: items,
+      "pagination": {
+        "limit": self.limit,
+        "sort": if self.descending { format!("-{}", self.sort) } else { self.sort.clone() },
+        "has_more": has_more,
+        "next_cursor": next_cursor
+      }
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SORTS: [&str; 2] = ["create_date", "area"];
+  const FIELDS: [&str; 2] = ["id", "alias"];
+
+  fn query(value: serde_json::Value) -> serde_json::Result<PageQuery> {
+    serde_json::from_value(value)
+  }
+
+  fn page(value: serde_json::Value) -> Result<Page, Vec<JsonError>> {
+    query(value).unwrap().page(&SORTS, "-create_date", &FIELDS)
+  }
+
+  fn items(count: usize) -> Vec<serde_json::Value> {
+    (0..count)
+      .map(|_| serde_json::json!({ "id": Uuid::new_v4(), "alias": "Talhão" }))
+      .collect()
+  }
+
+  fn id(item: &serde_json::Value) -> Uuid {
+    Uuid::parse_str(item["id"].as_str().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn defaults_to_the_default_sort_and_limit() {
+    let page = page(serde_json::json!({})).unwrap();
+
+    assert_eq!(page.limit, DEFAULT_LIMIT);
+    assert_eq!(page.fetch_limit(), DEFAULT_LIMIT + 1);
+    assert_eq!(page.sort, "create_date");
+    assert!(page.descending);
+    assert!(page.cursor.is_none());
+  }
+
+  #[test]
+  fn the_next_cursor_starts_the_next_page() {
+    let first = page(serde_json::json!({ "limit": 2 })).unwrap();
+    let listed = items(3);
+
+    let envelope = first.envelope("plantations", listed.clone(), id);
+
+    assert_eq!(envelope["pagination"]["has_more"], true);
+    assert_eq!(envelope["plantations"].as_array().unwrap().len(), 2);
+
+    let next = page(serde_json::json!({
+      "limit": 2,
+      "cursor": envelope["pagination"]["next_cursor"]
+    }))
+    .unwrap();
+
+    assert_eq!(next.cursor, Some(id(&listed[1])));
+  }
+
+  #[test]
+  fn the_last_page_has_no_cursor() {
+    let page = page(serde_json::json!({ "limit": 2 })).unwrap();
+
+    let envelope = page.envelope("plantations", items(2), id);
+
+    assert_eq!(envelope["pagination"]["has_more"], false);
+    assert!(envelope["pagination"]["next_cursor"].is_null());
+  }
+
+  #[test]
+  fn a_garbage_cursor_is_rejected() {
+    assert!(query(serde_json::json!({ "cursor": "not-a-cursor" })).is_err());
+    assert!(query(serde_json::json!({ "cursor": "' OR 1=1 --" })).is_err());
+  }
+
+  #[test]
+  fn the_limit_must_be_between_one_and_the_maximum() {
+    assert!(page(serde_json::json!({ "limit": 0 })).is_err());
+    assert!(page(serde_json::json!({ "limit": -1 })).is_err());
+    assert!(page(serde_json::json!({ "limit": MAX_LIMIT + 1 })).is_err());
+    assert_eq!(page(serde_json::json!({ "limit": 1 })).unwrap().limit, 1);
+    assert_eq!(
+      page(serde_json::json!({ "limit": MAX_LIMIT })).unwrap().limit,
+      MAX_LIMIT
+    );
+  }
+
+  #[test]
+  fn unknown_sorts_and_fields_are_rejected() {
+    assert!(page(serde_json::json!({ "sort": "name" })).is_err());
+    assert!(page(serde_json::json!({ "fields": "id,secret" })).is_err());
+
+    let page = page(serde_json::json!({ "sort": "area", "fields": "alias" })).unwrap();
+
+    assert_eq!(page.sort, "area");
+    assert!(!page.descending);
+    assert_eq!(page.fields, Some(vec!["alias".to_string()]));
+  }
+
+  #[test]
+  fn only_the_selected_fields_are_kept() {
+    let page = page(serde_json::json!({ "fields": "alias" })).unwrap();
+
+    let envelope = page.envelope("plantations", items(1), id);
+
+    assert_eq!(
+      envelope["plantations"][0],
+      serde_json::json!({ "alias": "Talhão" })
+    );
+  }
+}
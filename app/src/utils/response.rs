@@ -1,7 +1,7 @@
 use poem::{http::StatusCode, web::Json, Response};
 use serde::Serialize;
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 pub(crate) struct JsonError {
   field: String,
   message: String,
@@ -0,0 +1,265 @@
+use crate::{client, error::CrawlerError, handlers, utils::database::DataBase};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use sqlx::{Connection, PgConnection};
+use std::{str::FromStr, time::Duration};
+use tokio::{
+  signal::unix::{signal, SignalKind},
+  task::JoinSet,
+};
+
+#[derive(Deserialize, Debug)]
+struct Config {
+  schedules: Vec<ScheduleConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ScheduleConfig {
+  name: String,
+  /// Cron expression with seconds, e.g. `0 0 6 * * *`
+  cron: String,
+  script: String,
+  ocurrence_id: Option<String>,
+  pathogenic_id: Option<String>,
+  /// Runs the script once for each pathogenic, passing its id as `--pathogenic-id`
+  #[serde(default)]
+  all_pathogens: bool,
+}
+
+struct Schedule {
+  config: ScheduleConfig,
+  cron: cron::Schedule,
+  next_run: Option<DateTime<Local>>,
+}
+
+/// How long SIGTERM waits for the running scripts before aborting them, a stuck
+/// WebDriver session would otherwise hold the shutdown forever.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn load_schedules(path: &str) -> Result<Vec<Schedule>, CrawlerError> {
+  let content = std::fs::read_to_string(path)
+    .map_err(|e| CrawlerError::Config(format!("error reading {}: {}", path, e)))?;
+
+  parse_schedules(&content).map_err(|e| CrawlerError::Config(format!("{}: {}", path, e)))
+}
+
+fn parse_schedules(content: &str) -> Result<Vec<Schedule>, String> {
+  let config: Config = toml::from_str(content).map_err(|e| format!("error parsing: {}", e))?;
+
+  config
+    .schedules
+    .into_iter()
+    .map(|config| {
+      if !handlers::SCRIPTS.contains(&config.script.as_str()) {
+        return Err(format!(
+          "schedule {}: script {} not found",
+          config.name, config.script
+        ));
+      }
+
+      let cron = cron::Schedule::from_str(&config.cron).map_err(|e| {
+        format!(
+          "schedule {}: invalid cron {}: {}",
+          config.name, config.cron, e
+        )
+      })?;
+
+      Ok(Schedule {
+        next_run: cron.upcoming(Local).next(),
+        config,
+        cron,
+      })
+    })
+    .collect()
+}
+
+/// Runs the schedules until SIGTERM/SIGINT, then waits for the running scripts
+/// to finish.
+pub(crate) async fn run(config_path: &str) -> Result<(), CrawlerError> {
+  let mut schedules = load_schedules(config_path)?;
+
+  for schedule in &schedules {
+    println!(
+      "Schedule {} ({}) next run: {:?}",
+      schedule.config.name, schedule.config.script, schedule.next_run
+    );
+  }
+
+  let mut sigterm = signal(SignalKind::terminate()).expect("error listening to SIGTERM");
+  let mut sigint = signal(SignalKind::interrupt()).expect("error listening to SIGINT");
+  let mut running: JoinSet<()> = JoinSet::new();
+
+  loop {
+    let now = Local::now();
+
+    for schedule in schedules.iter_mut() {
+      if schedule.next_run.is_some_and(|next_run| next_run <= now) {
+        running.spawn(run_schedule(schedule.config.clone()));
+        schedule.next_run = schedule.cron.after(&now).next();
+      }
+    }
+
+    let sleep = schedules
+      .iter()
+      .filter_map(|schedule| schedule.next_run)
+      .min()
+      .map(|next_run| (next_run - now).to_std().unwrap_or(Duration::ZERO))
+      .unwrap_or(Duration::from_secs(60));
+
+    tokio::select! {
+      _ = tokio::time::sleep(sleep) => {}
+      Some(_) = running.join_next(), if !running.is_empty() => {}
+      _ = sigterm.recv() => break,
+      _ = sigint.recv() => break,
+    }
+  }
+
+  println!(
+    "Shutting down, waiting for {} running scripts",
+    running.len()
+  );
+
+  let drain = async { while running.join_next().await.is_some() {} };
+
+  if tokio::time::timeout(SHUTDOWN_TIMEOUT, drain).await.is_err() {
+    println!(
+      "{} scripts still running after {:?}, aborting them",
+      running.len(),
+      SHUTDOWN_TIMEOUT
+    );
+
+    // Aborting drops the lock connections, so Postgres releases their locks
+    running.shutdown().await;
+  }
+
+  Ok(())
+}
+
+async fn run_schedule(schedule: ScheduleConfig) {
+  // The lock lives on its own connection: if the run panics the connection is
+  // dropped and Postgres releases the lock, which wouldn't happen on a pooled one.
+  let mut lock = PgConnection::connect(std::env::var("DATABASE_URL").unwrap().as_str())
+    .await
+    .expect("Error connecting to database");
+
+  let locked = sqlx::query_scalar!("SELECT pg_try_advisory_lock(hashtext($1))", schedule.name)
+    .fetch_one(&mut lock)
+    .await
+    .map_err(DataBase::database_error)
+    .ok()
+    .flatten()
+    .unwrap_or(false);
+
+  if !locked {
+    println!("Schedule {} is still running, skipping", schedule.name);
+    return;
+  }
+
+  let pathogenic_ids = if schedule.all_pathogens {
+    let db = DataBase::new().await;
+
+    sqlx::query_scalar!("SELECT id FROM pathogenics ORDER BY id")
+      .fetch_all(&db.pool)
+      .await
+      .map_err(DataBase::database_error)
+      .unwrap_or_default()
+      .into_iter()
+      .map(|id| Some(id.to_string()))
+      .collect()
+  } else {
+    vec![schedule.pathogenic_id.clone()]
+  };
+
+  match client::make().await {
+    Ok(client) => {
+      for pathogenic_id in pathogenic_ids {
+        println!(
+          "Schedule {} running {} (pathogenic: {:?})",
+          schedule.name, schedule.script, pathogenic_id
+        );
+
+        // Spawned so a panic inside the script doesn't skip closing the browser session
+        let script_client = client.clone();
+        let script = schedule.script.clone();
+        let ocurrence_id = schedule.ocurrence_id.clone();
+
+        let result = tokio::spawn(async move {
+          handlers::run(&script_client, &script, ocurrence_id, pathogenic_id).await
+        })
+        .await;
+
+        match result {
+          Ok(Ok(stats)) => println!(
+            "Schedule {} finished: {} ({} succeeded, {} failed)",
+            schedule.name,
+            stats.status(),
+            stats.succeeded,
+            stats.failed
+          ),
+          Ok(Err(e)) => println!("Schedule {} failed: {}", schedule.name, e),
+          Err(e) => println!("Schedule {} panicked: {}", schedule.name, e),
+        }
+      }
+
+      let _ = client.close().await;
+    }
+    Err(e) => println!("Schedule {}: error on client build: {}", schedule.name, e),
+  }
+
+  let _ = sqlx::query_scalar!("SELECT pg_advisory_unlock(hashtext($1))", schedule.name)
+    .fetch_one(&mut lock)
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schedule(cron: &str, script: &str) -> String {
+    format!(
+      "[[schedules]]\nname = \"test\"\ncron = \"{}\"\nscript = \"{}\"\n",
+      cron, script
+    )
+  }
+
+  #[test]
+  fn the_example_config_parses() {
+    let schedules =
+      load_schedules(concat!(env!("CARGO_MANIFEST_DIR"), "/crawler.example.toml")).unwrap();
+
+    assert_eq!(schedules.len(), 5);
+    assert!(schedules.iter().all(|schedule| schedule.next_run.is_some()));
+  }
+
+  #[test]
+  fn a_missing_config_is_a_config_error() {
+    assert!(matches!(
+      load_schedules("does-not-exist.toml"),
+      Err(CrawlerError::Config(_))
+    ));
+  }
+
+  #[test]
+  fn invalid_toml_is_rejected() {
+    assert!(parse_schedules("[[schedules]\nname =").is_err());
+    assert!(parse_schedules("[[schedules]]\nname = \"test\"").is_err());
+  }
+
+  #[test]
+  fn invalid_crons_are_rejected() {
+    let error = parse_schedules(&schedule("every morning", "hotspots"))
+      .err()
+      .unwrap();
+
+    assert!(error.contains("invalid cron"), "{}", error);
+  }
+
+  #[test]
+  fn unknown_scripts_are_rejected() {
+    let error = parse_schedules(&schedule("0 0 6 * * *", "nope"))
+      .err()
+      .unwrap();
+
+    assert!(error.contains("script nope not found"), "{}", error);
+  }
+}
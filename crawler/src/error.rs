@@ -0,0 +1,54 @@
+use crawler::{forecast::ForecastError, parsers::table::ParseError};
+use fantoccini::error::CmdError;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CrawlerError {
+  /// The INMET pages didn't load or an element wasn't found
+  #[error("navigation error: {0}")]
+  Navigation(#[from] Box<CmdError>),
+  #[error("parse error: {0}")]
+  Parse(String),
+  #[error("database error: {0}")]
+  Database(#[from] sqlx::Error),
+  #[error("notification error: {0}")]
+  Notification(String),
+  #[error("forecast error: {0}")]
+  Forecast(String),
+  /// The daemon schedules couldn't be read
+  #[error("config error: {0}")]
+  Config(String),
+  /// The task was aborted before finishing
+  #[error("cancelled: {0}")]
+  Cancelled(String),
+}
+
+impl From<CmdError> for CrawlerError {
+  fn from(err: CmdError) -> Self {
+    CrawlerError::Navigation(Box::new(err))
+  }
+}
+
+impl From<ParseError> for CrawlerError {
+  fn from(err: ParseError) -> Self {
+    CrawlerError::Parse(err.to_string())
+  }
+}
+
+impl From<ForecastError> for CrawlerError {
+  fn from(err: ForecastError) -> Self {
+    match err {
+      ForecastError::Request(e) => CrawlerError::Forecast(e),
+      ForecastError::Parse(e) => CrawlerError::Parse(e),
+    }
+  }
+}
+
+impl CrawlerError {
+  /// Network failures are worth trying again, bad data or queries are not.
+  pub(crate) fn is_retryable(&self) -> bool {
+    matches!(
+      self,
+      CrawlerError::Navigation(_) | CrawlerError::Notification(_) | CrawlerError::Forecast(_)
+    )
+  }
+}
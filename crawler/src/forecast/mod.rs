@@ -0,0 +1,59 @@
+pub mod open_meteo;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ForecastError {
+  /// The provider didn't answer or answered with an error status
+  #[error("{0}")]
+  Request(String),
+  #[error("{0}")]
+  Parse(String),
+}
+
+/// Forecast of an hour, in UTC like the INMET tables.
+#[derive(Debug, Clone)]
+pub struct ForecastHour {
+  pub date: NaiveDateTime,
+  pub temperature: Option<f64>,
+  pub humidity: Option<f64>,
+  pub precipitation: Option<f64>,
+  pub precipitation_probability: Option<f64>,
+  /// m/s
+  pub wind_speed: Option<f64>,
+}
+
+#[async_trait]
+pub trait ForecastProvider: Send + Sync {
+  /// Saved with the forecasts, so providers can be switched without mixing them
+  fn name(&self) -> &'static str;
+
+  /// Hourly forecast from the start of today until `days` days after it.
+  async fn hourly(
+    &self,
+    latitude: f64,
+    longitude: f64,
+    days: u32,
+  ) -> Result<Vec<ForecastHour>, ForecastError>;
+}
+
+/// Provider set by `FORECAST_PROVIDER`, only `open-meteo` for now.
+pub fn from_env() -> Box<dyn ForecastProvider> {
+  match std::env::var("FORECAST_PROVIDER")
+    .unwrap_or("open-meteo".to_string())
+    .as_str()
+  {
+    "open-meteo" => Box::new(open_meteo::OpenMeteo::from_env()),
+    provider => panic!("FORECAST_PROVIDER {} not supported", provider),
+  }
+}
+
+/// Days ahead the predicted risk is calculated for
+pub fn days() -> u32 {
+  std::env::var("FORECAST_DAYS")
+    .ok()
+    .and_then(|days| days.parse::<u32>().ok())
+    .unwrap_or(5)
+    .clamp(1, 15)
+}
@@ -0,0 +1,104 @@
+use super::{ForecastError, ForecastHour, ForecastProvider};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+/// Open-Meteo forecast API (https://open-meteo.com/en/docs), or anything
+/// answering the same `/v1/forecast` request, like a local stub.
+pub struct OpenMeteo {
+  base_url: String,
+  client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+  hourly: Hourly,
+}
+
+#[derive(Deserialize)]
+struct Hourly {
+  time: Vec<String>,
+  temperature_2m: Vec<Option<f64>>,
+  relative_humidity_2m: Vec<Option<f64>>,
+  #[serde(default)]
+  precipitation: Vec<Option<f64>>,
+  #[serde(default)]
+  precipitation_probability: Vec<Option<f64>>,
+  #[serde(default)]
+  wind_speed_10m: Vec<Option<f64>>,
+}
+
+impl OpenMeteo {
+  pub fn new(base_url: String) -> OpenMeteo {
+    OpenMeteo {
+      base_url: base_url.trim_end_matches('/').to_string(),
+      client: reqwest::Client::new(),
+    }
+  }
+
+  pub fn from_env() -> OpenMeteo {
+    OpenMeteo::new(
+      std::env::var("FORECAST_BASE_URL").unwrap_or("https://api.open-meteo.com".to_string()),
+    )
+  }
+}
+
+#[async_trait]
+impl ForecastProvider for OpenMeteo {
+  fn name(&self) -> &'static str {
+    "open-meteo"
+  }
+
+  async fn hourly(
+    &self,
+    latitude: f64,
+    longitude: f64,
+    days: u32,
+  ) -> Result<Vec<ForecastHour>, ForecastError> {
+    let response = self
+      .client
+      .get(format!("{}/v1/forecast", self.base_url))
+      .query(&[
+        ("latitude", latitude.to_string()),
+        ("longitude", longitude.to_string()),
+        (
+          "hourly",
+          "temperature_2m,relative_humidity_2m,precipitation,precipitation_probability,wind_speed_10m"
+            .to_string(),
+        ),
+        ("wind_speed_unit", "ms".to_string()),
+        ("timezone", "GMT".to_string()),
+        // Today is counted as a day
+        ("forecast_days", (days + 1).to_string()),
+      ])
+      .send()
+      .await
+      .and_then(|response| response.error_for_status())
+      .map_err(|e| ForecastError::Request(e.to_string()))?
+      .json::<ForecastResponse>()
+      .await
+      .map_err(|e| ForecastError::Parse(format!("open-meteo response: {}", e)))?;
+
+    let hourly = response.hourly;
+    let at = |values: &[Option<f64>], index: usize| values.get(index).copied().flatten();
+
+    hourly
+      .time
+      .iter()
+      .enumerate()
+      .map(|(index, time)| {
+        let date = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
+          .map_err(|e| ForecastError::Parse(format!("open-meteo time {}: {}", time, e)))?;
+
+        Ok(ForecastHour {
+          date,
+          temperature: at(&hourly.temperature_2m, index),
+          humidity: at(&hourly.relative_humidity_2m, index),
+          precipitation: at(&hourly.precipitation, index),
+          precipitation_probability: at(&hourly.precipitation_probability, index),
+          wind_speed: at(&hourly.wind_speed_10m, index),
+        })
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,207 @@
+use crate::{
+  error::CrawlerError,
+  utils::{
+    crawl_runs::RunStats,
+    database::DataBase,
+    plantation_risks::{self, PathogenicCulture, Plantation, Protection, Risk, RiskKind},
+    retry,
+  },
+};
+use chrono::Utc;
+use crawler::{
+  forecast::{self, ForecastHour, ForecastProvider},
+  quality::{Climate, MAX_INTERPOLATED_HOURS, MIN_CONFIDENCE},
+};
+use std::collections::BTreeMap;
+
+const FETCH_ATTEMPTS: u32 = 3;
+
+/// Predicted risk of the next days, from the forecast of each station's
+/// location and the same rules of the observed risk. Saved apart from the
+/// observed risk, no alerts are sent.
+pub(crate) async fn handler(pathogenic_id: Option<String>) -> Result<RunStats, CrawlerError> {
+  let db: DataBase = DataBase::new().await;
+
+  let pathogenic_id = pathogenic_id
+    .map(|id| {
+      id.parse::<i64>()
+        .map_err(|e| CrawlerError::Parse(format!("pathogenic id {}: {}", id, e)))
+    })
+    .transpose()?;
+
+  let pathogenics = plantation_risks::pathogenics(&db, pathogenic_id).await?;
+  let plantations = plantation_risks::plantations(&db, &pathogenics).await?;
+  let protections = plantation_risks::protections(
+    &db,
+    &plantations
+      .iter()
+      .map(|plantation| plantation.id)
+      .collect::<Vec<_>>(),
+    Utc::now().date_naive(),
+  )
+  .await?;
+
+  let mut stations: BTreeMap<i64, Vec<&Plantation>> = BTreeMap::new();
+
+  for plantation in &plantations {
+    stations
+      .entry(plantation.station_id)
+      .or_default()
+      .push(plantation);
+  }
+
+  let provider = forecast::from_env();
+  let days = forecast::days();
+  let mut stats = RunStats::default();
+
+  for (station_id, station_plantations) in stations {
+    match process_station(
+      &db,
+      provider.as_ref(),
+      days,
+      &station_plantations,
+      &pathogenics,
+      &protections,
+      &mut stats,
+    )
+    .await
+    {
+      Ok(()) => stats.success(),
+      Err(e) => stats.failure(&format!("station {}", station_id), &e),
+    }
+  }
+
+  Ok(stats)
+}
+
+async fn process_station(
+  db: &DataBase,
+  provider: &dyn ForecastProvider,
+  days: u32,
+  plantations: &[&Plantation],
+  pathogenics: &[PathogenicCulture],
+  protections: &[Protection],
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  let station = plantations[0];
+
+  let (Some(latitude), Some(longitude)) = (station.station_latitude, station.station_longitude)
+  else {
+    return Err(CrawlerError::Parse("station without location".to_string()));
+  };
+
+  let hours = retry::with_backoff(
+    &format!("forecast of station {}", station.inmet_code),
+    FETCH_ATTEMPTS,
+    || async {
+      provider
+        .hourly(latitude, longitude, days)
+        .await
+        .map_err(CrawlerError::from)
+    },
+  )
+  .await?;
+
+  save_forecast(db, provider.name(), station.station_id, &hours, stats).await?;
+
+  // Today is part observed already, the predicted days start tomorrow
+  let today = Utc::now().date_naive();
+  let mut by_day: BTreeMap<_, Vec<&ForecastHour>> = BTreeMap::new();
+
+  for hour in hours.iter().filter(|hour| hour.date.date() > today) {
+    by_day.entry(hour.date.date()).or_default().push(hour);
+  }
+
+  for day_hours in by_day.values() {
+    let climate = Climate::from_forecast(
+      day_hours
+        .iter()
+        .map(|hour| (hour.date, hour.temperature, hour.humidity)),
+      MAX_INTERPOLATED_HOURS,
+    );
+
+    if climate.confidence() < MIN_CONFIDENCE {
+      continue;
+    }
+
+    let period = (day_hours[0].date, day_hours[day_hours.len() - 1].date);
+
+    for pathogenic in pathogenics {
+      let risk = Risk::new(&climate, pathogenic);
+
+      for plantation in plantations
+        .iter()
+        .filter(|plantation| plantation.culture_id == pathogenic.culture_id)
+      {
+        let risk = risk.protected_by(plantation_risks::protection(
+          protections,
+          plantation,
+          pathogenic,
+          period.1.date(),
+        ));
+
+        let inserted = plantation_risks::save(
+          db,
+          plantation,
+          pathogenic,
+          RiskKind::Predicted,
+          &risk,
+          climate.confidence(),
+          period,
+        )
+        .await?;
+
+        if inserted {
+          stats.rows_inserted += 1;
+        } else {
+          stats.rows_updated += 1;
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Keeps the latest forecast of each hour
+async fn save_forecast(
+  db: &DataBase,
+  provider: &str,
+  station_id: i64,
+  hours: &[ForecastHour],
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  for hour in hours {
+    let inserted = sqlx::query_scalar!(
+      r#"INSERT INTO station_forecasts (station_id, provider, date, temperature, humidity,
+                               precipitation, precipitation_probability, wind_speed)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+      ON CONFLICT (station_id, provider, date) DO UPDATE
+          SET temperature               = $4,
+              humidity                  = $5,
+              precipitation             = $6,
+              precipitation_probability = $7,
+              wind_speed                = $8,
+              fetch_date                = NOW()
+      RETURNING (xmax = 0) AS "inserted!""#,
+      station_id,
+      provider,
+      hour.date,
+      hour.temperature,
+      hour.humidity,
+      hour.precipitation,
+      hour.precipitation_probability,
+      hour.wind_speed
+    )
+    .fetch_one(&db.pool)
+    .await?;
+
+    if inserted {
+      stats.rows_inserted += 1;
+    } else {
+      stats.rows_updated += 1;
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,452 @@
+use crate::{
+  error::CrawlerError,
+  utils::{crawl_runs::RunStats, database::DataBase, notification},
+};
+use crawler::hotspots::{self, Extent, Point};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Knobs of the clustering, from the environment
+struct Settings {
+  /// Days of confirmed occurrences clustered
+  days: i32,
+  /// Distance between neighbour occurrences
+  eps_km: f64,
+  /// Plantations with occurrences around a plantation to start a hotspot
+  min_plantations: usize,
+  /// How far beyond its radius a hotspot alerts the plantations
+  alert_km: f64,
+}
+
+impl Settings {
+  fn from_env() -> Settings {
+    fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+      std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<T>().ok())
+        .unwrap_or(default)
+    }
+
+    Settings {
+      days: var("HOTSPOT_DAYS", 21).clamp(1, 365),
+      eps_km: var("HOTSPOT_EPS_KM", 10.0_f64).clamp(0.5, 200.0),
+      min_plantations: var("HOTSPOT_MIN_PLANTATIONS", 3).max(2),
+      alert_km: var("HOTSPOT_ALERT_KM", 20.0_f64).clamp(0.0, 500.0),
+    }
+  }
+}
+
+struct Occurrence {
+  id: Uuid,
+  pathogenic_id: i64,
+  pathogenic_name: String,
+  plantation_id: Uuid,
+  latitude: f64,
+  longitude: f64,
+}
+
+/// Hotspot still active since the last run
+struct ActiveHotspot {
+  id: i64,
+  pathogenic_id: i64,
+  latitude: f64,
+  longitude: f64,
+  size: i32,
+  occurrence_ids: Vec<Uuid>,
+}
+
+/// Plantation of a culture the pathogen attacks
+struct Susceptible {
+  id: Uuid,
+  /// First owner of the farm holding the plantation, or its creator when it
+  /// has no farm, as the risk alerts
+  owner_id: Uuid,
+  alias: Option<String>,
+  culture_name: String,
+  latitude: f64,
+  longitude: f64,
+}
+
+/// A plantation in the path of an emerging hotspot
+struct Alert {
+  plantation: String,
+  pathogenic: String,
+  distance_km: f64,
+}
+
+/// Clusters the recent confirmed occurrences of every pathogen, or only of
+/// `pathogenic_id`, into hotspots. A cluster sharing occurrences with an
+/// active hotspot updates it, the others start new hotspots and the active
+/// hotspots left without a cluster are closed. Emerging hotspots alert the
+/// growers of the susceptible plantations in their path.
+pub(crate) async fn handler(pathogenic_id: Option<String>) -> Result<RunStats, CrawlerError> {
+  let db: DataBase = DataBase::new().await;
+
+  let pathogenic_id = pathogenic_id
+    .map(|id| {
+      id.parse::<i64>()
+        .map_err(|e| CrawlerError::Parse(format!("pathogenic id {}: {}", id, e)))
+    })
+    .transpose()?;
+
+  let settings = Settings::from_env();
+
+  let occurrences = sqlx::query_as!(
+    Occurrence,
+    r#"SELECT o.id,
+            o.pathogenic_id,
+            pa.name                    AS pathogenic_name,
+            o.plantation_id,
+            st_x(p.location::geometry) AS "latitude!",
+            st_y(p.location::geometry) AS "longitude!"
+      FROM plantation_pathogenic_occurrences o
+              JOIN plantations p ON p.id = o.plantation_id
+              JOIN pathogenics pa ON pa.id = o.pathogenic_id
+      WHERE o.status = 'confirmed'
+        AND p.delete_at IS NULL
+        AND o.occurrence_date >= NOW() - MAKE_INTERVAL(days => $1)
+        AND ($2::bigint IS NULL OR o.pathogenic_id = $2)
+      ORDER BY o.pathogenic_id, o.occurrence_date"#,
+    settings.days,
+    pathogenic_id
+  )
+  .fetch_all(&db.pool)
+  .await?;
+
+  let active = sqlx::query_as!(
+    ActiveHotspot,
+    r#"SELECT id,
+            pathogenic_id,
+            st_x(location::geometry) AS "latitude!",
+            st_y(location::geometry) AS "longitude!",
+            size,
+            occurrence_ids
+      FROM hotspots
+      WHERE status = 'active'
+        AND ($1::bigint IS NULL OR pathogenic_id = $1)"#,
+    pathogenic_id
+  )
+  .fetch_all(&db.pool)
+  .await?;
+
+  let mut pathogenics: BTreeMap<i64, (Vec<&Occurrence>, Vec<&ActiveHotspot>)> = BTreeMap::new();
+
+  for occurrence in &occurrences {
+    pathogenics
+      .entry(occurrence.pathogenic_id)
+      .or_default()
+      .0
+      .push(occurrence);
+  }
+  for hotspot in &active {
+    pathogenics
+      .entry(hotspot.pathogenic_id)
+      .or_default()
+      .1
+      .push(hotspot);
+  }
+
+  let mut alerts: HashMap<Uuid, Vec<Alert>> = HashMap::new();
+  let mut stats = RunStats::default();
+
+  for (pathogenic_id, (occurrences, active)) in pathogenics {
+    match process_pathogenic(
+      &db,
+      &settings,
+      pathogenic_id,
+      &occurrences,
+      &active,
+      &mut alerts,
+      &mut stats,
+    )
+    .await
+    {
+      Ok(()) => stats.success(),
+      Err(e) => stats.failure(&format!("pathogenic {}", pathogenic_id), &e),
+    }
+  }
+
+  let messages = alerts
+    .into_iter()
+    .map(|(user_id, alerts)| (user_id, alert_message(&alerts)))
+    .collect();
+
+  notification::notify_users(&db, "ALERTA: Foco emergente", messages, &mut stats).await?;
+
+  Ok(stats)
+}
+
+async fn process_pathogenic(
+  db: &DataBase,
+  settings: &Settings,
+  pathogenic_id: i64,
+  occurrences: &[&Occurrence],
+  active: &[&ActiveHotspot],
+  alerts: &mut HashMap<Uuid, Vec<Alert>>,
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  // Clustered by plantation, many reports on a single plantation aren't a
+  // hotspot and its centroid would be the plantation
+  let mut plantations: Vec<(Point, Vec<&Occurrence>)> = vec![];
+  let mut plantation_index = HashMap::new();
+
+  for &occurrence in occurrences {
+    let index = *plantation_index
+      .entry(occurrence.plantation_id)
+      .or_insert_with(|| {
+        plantations.push((
+          Point {
+            latitude: occurrence.latitude,
+            longitude: occurrence.longitude,
+          },
+          vec![],
+        ));
+        plantations.len() - 1
+      });
+
+    plantations[index].1.push(occurrence);
+  }
+
+  let points = plantations
+    .iter()
+    .map(|(point, _)| *point)
+    .collect::<Vec<_>>();
+
+  let mut clusters = hotspots::dbscan(&points, settings.eps_km, settings.min_plantations);
+  clusters.sort_by_key(|members| {
+    std::cmp::Reverse(
+      members
+        .iter()
+        .map(|&i| plantations[i].1.len())
+        .sum::<usize>(),
+    )
+  });
+
+  // Hotspots already taken by a cluster are left out of the next matches, so
+  // a split hotspot goes on as its largest part and the others start anew
+  let mut previous = active
+    .iter()
+    .map(|hotspot| hotspot.occurrence_ids.clone())
+    .collect::<Vec<_>>();
+  let mut matched = HashSet::new();
+
+  let mut susceptible: Option<Vec<Susceptible>> = None;
+
+  for members in clusters {
+    let Some(extent) = Extent::new(&points, &members) else {
+      continue;
+    };
+
+    let members = members
+      .iter()
+      .flat_map(|&i| plantations[i].1.iter().copied())
+      .collect::<Vec<_>>();
+
+    let occurrence_ids = members
+      .iter()
+      .map(|occurrence| occurrence.id)
+      .collect::<Vec<_>>();
+    let size = members.len() as i32;
+
+    let last = hotspots::matching(&occurrence_ids, &previous).map(|index| {
+      previous[index].clear();
+      matched.insert(active[index].id);
+      active[index]
+    });
+
+    let emerging = hotspots::emerging(last.map(|hotspot| hotspot.size as usize), members.len());
+
+    let hotspot_id = match last {
+      Some(hotspot) => {
+        sqlx::query!(
+          "UPDATE hotspots
+          SET location       = point($2, $3),
+              radius_km      = $4,
+              size           = $5,
+              growth         = $5 - size,
+              emerging       = $6,
+              occurrence_ids = $7,
+              update_date    = NOW()
+          WHERE id = $1",
+          hotspot.id,
+          extent.centroid.latitude,
+          extent.centroid.longitude,
+          extent.radius_km,
+          size,
+          emerging,
+          &occurrence_ids[..]
+        )
+        .execute(&db.pool)
+        .await?;
+
+        stats.rows_updated += 1;
+        hotspot.id
+      }
+      None => {
+        let id = sqlx::query_scalar!(
+          "INSERT INTO hotspots (pathogenic_id, location, radius_km, size, growth, emerging,
+                                occurrence_ids)
+          VALUES ($1, point($2, $3), $4, $5, $5, $6, $7)
+          RETURNING id",
+          pathogenic_id,
+          extent.centroid.latitude,
+          extent.centroid.longitude,
+          extent.radius_km,
+          size,
+          emerging,
+          &occurrence_ids[..]
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        stats.rows_inserted += 1;
+        id
+      }
+    };
+
+    sqlx::query!(
+      "INSERT INTO hotspot_snapshots (hotspot_id, location, radius_km, size)
+      VALUES ($1, point($2, $3), $4, $5)",
+      hotspot_id,
+      extent.centroid.latitude,
+      extent.centroid.longitude,
+      extent.radius_km,
+      size
+    )
+    .execute(&db.pool)
+    .await?;
+
+    stats.rows_inserted += 1;
+
+    if !emerging {
+      continue;
+    }
+
+    if susceptible.is_none() {
+      susceptible = Some(susceptible_plantations(db, pathogenic_id).await?);
+    }
+
+    let last_centroid = last.map(|hotspot| Point {
+      latitude: hotspot.latitude,
+      longitude: hotspot.longitude,
+    });
+    let reported = members
+      .iter()
+      .map(|occurrence| occurrence.plantation_id)
+      .collect::<HashSet<_>>();
+
+    for plantation in susceptible.iter().flatten() {
+      let location = Point {
+        latitude: plantation.latitude,
+        longitude: plantation.longitude,
+      };
+
+      // The growers that reported it already know about the hotspot
+      if reported.contains(&plantation.id)
+        || !extent.in_path(last_centroid, location, settings.alert_km)
+      {
+        continue;
+      }
+
+      // Once per plantation and hotspot, however many runs it keeps emerging
+      let inserted = sqlx::query_scalar!(
+        r#"INSERT INTO hotspot_alerts (hotspot_id, plantation_id, user_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (hotspot_id, plantation_id) DO NOTHING
+        RETURNING id"#,
+        hotspot_id,
+        plantation.id,
+        plantation.owner_id
+      )
+      .fetch_optional(&db.pool)
+      .await?
+      .is_some();
+
+      if !inserted {
+        continue;
+      }
+
+      stats.rows_inserted += 1;
+
+      alerts.entry(plantation.owner_id).or_default().push(Alert {
+        plantation: plantation
+          .alias
+          .clone()
+          .unwrap_or_else(|| format!("sua plantação de {}", plantation.culture_name)),
+        pathogenic: members[0].pathogenic_name.clone(),
+        distance_km: hotspots::distance_km(extent.centroid, location),
+      });
+    }
+  }
+
+  let closed = active
+    .iter()
+    .map(|hotspot| hotspot.id)
+    .filter(|id| !matched.contains(id))
+    .collect::<Vec<_>>();
+
+  if !closed.is_empty() {
+    let result = sqlx::query!(
+      "UPDATE hotspots
+      SET status      = 'closed',
+          emerging    = FALSE,
+          close_date  = NOW(),
+          update_date = NOW()
+      WHERE id = ANY($1)",
+      &closed[..]
+    )
+    .execute(&db.pool)
+    .await?;
+
+    stats.rows_updated += result.rows_affected() as i64;
+  }
+
+  Ok(())
+}
+
+/// Plantations of the cultures `pathogenic_id` attacks
+async fn susceptible_plantations(
+  db: &DataBase,
+  pathogenic_id: i64,
+) -> Result<Vec<Susceptible>, CrawlerError> {
+  Ok(
+    sqlx::query_as!(
+      Susceptible,
+      r#"SELECT p.id,
+              COALESCE(m.user_id, p.user_id) AS "owner_id!",
+              p.alias,
+              c.name                     AS culture_name,
+              st_x(p.location::geometry) AS "latitude!",
+              st_y(p.location::geometry) AS "longitude!"
+        FROM plantations p
+                JOIN pathogenic_cultures pc ON pc.culture_id = p.culture_id
+                JOIN cultures c ON c.id = p.culture_id
+                LEFT JOIN LATERAL (SELECT user_id
+                                   FROM farm_members
+                                   WHERE farm_id = p.farm_id
+                                     AND role = 'owner'
+                                   ORDER BY create_date, id
+                                   LIMIT 1) m ON TRUE
+        WHERE pc.pathogenic_id = $1
+          AND p.delete_at IS NULL"#,
+      pathogenic_id
+    )
+    .fetch_all(&db.pool)
+    .await?,
+  )
+}
+
+/// `Foco emergente de Ferrugem a 12 km de Talhão 1.`
+fn alert_message(alerts: &[Alert]) -> String {
+  let hotspots = alerts
+    .iter()
+    .map(|alert| {
+      format!(
+        "{} a {:.0} km de {}",
+        alert.pathogenic, alert.distance_km, alert.plantation
+      )
+    })
+    .collect::<Vec<_>>();
+
+  format!("Foco emergente de {}.", hotspots.join("; "))
+}
@@ -1,88 +1,98 @@
-use fantoccini::{error::CmdError, Locator};
-use scraper::{Html, Selector};
+use crate::{
+  error::CrawlerError,
+  utils::{crawl_runs::RunStats, retry},
+};
+use crawler::parsers::inmet_catalog::{parse_stations, Station};
+use fantoccini::Locator;
 use sqlx::PgPool;
 use std::time::Duration;
 
-#[allow(dead_code)]
-#[derive(Clone, Debug)]
-struct Station {
-  city: String,
-  uf: String,
-  situation: String,
-  latitude: String,
-  longitude: String,
-  altitude: String,
-  installation_date: String,
-  code: String,
-}
-
 #[derive(Clone, serde::Serialize, Debug)]
 struct StationDB {
   id: i64,
   inmet_code: Option<String>,
 }
 
-async fn parse_stations(html: String) -> Vec<Station> {
-  let document = Html::parse_document(&html);
-
-  let row_selector = Selector::parse("tr").unwrap();
-  let cell_selector = Selector::parse("td").unwrap();
+async fn load_catalog(client: &fantoccini::Client) -> Result<String, CrawlerError> {
+  client
+    .goto("https://portal.inmet.gov.br/paginas/catalogoaut")
+    .await?;
 
-  let mut stations = Vec::new();
+  client
+    .wait()
+    .at_most(Duration::from_secs(10))
+    .for_element(Locator::Css("#tb"))
+    .await?;
 
-  for row in document.select(&row_selector) {
-    let mut cells = row.select(&cell_selector);
+  Ok(client.source().await?)
+}
 
-    if cells.clone().count() == 0 {
-      continue;
-    }
+async fn save_station(
+  pool: &PgPool,
+  station: &Station,
+  station_db: Option<&StationDB>,
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  let point = format!(
+    "POINT ({} {})",
+    station.latitude.to_string().replace(",", "."),
+    station.longitude.to_string().replace(",", ".")
+  );
+
+  let status = station.situation == "Operante";
+
+  if let Some(station_db) = station_db {
+    stats.rows_updated += sqlx::query!(
+      "UPDATE stations SET city = $2, status = $3, location = ST_PointFromText($4)::point WHERE id = $1;",
+      station_db.id,
+      station.city,
+      status,
+      point
+    )
+    .execute(pool)
+    .await?
+    .rows_affected() as i64;
 
-    let city = cells.next().unwrap().text().collect::<String>();
-    let uf = cells.next().unwrap().text().collect::<String>();
-    let situation = cells.next().unwrap().text().collect::<String>();
-    let latitude = cells.next().unwrap().text().collect::<String>();
-    let longitude = cells.next().unwrap().text().collect::<String>();
-    let altitude = cells.next().unwrap().text().collect::<String>();
-    let installation_date = cells.next().unwrap().text().collect::<String>();
-    let code = cells.next().unwrap().text().collect::<String>();
-
-    let station = Station {
-      city,
-      uf,
-      situation,
-      latitude,
-      longitude,
-      altitude,
-      installation_date,
-      code,
-    };
-
-    stations.push(station);
+    return Ok(());
   }
 
-  stations
+  stats.rows_inserted += sqlx::query!(
+    "INSERT INTO stations (city, uf, location, status, inmet_code)
+              VALUES ($1, $2, ST_PointFromText($3)::point, $4, $5);",
+    station.city,
+    station.uf,
+    point,
+    status,
+    station.code
+  )
+  .execute(pool)
+  .await?
+  .rows_affected() as i64;
+
+  Ok(())
 }
 
-pub(crate) async fn handler(client: &fantoccini::Client) -> Result<(), CmdError> {
-  let pool = PgPool::connect(std::env::var("DATABASE_URL").unwrap().as_str())
-    .await
-    .expect("Error connecting to database");
+pub(crate) async fn handler(client: &fantoccini::Client) -> Result<RunStats, CrawlerError> {
+  let pool = PgPool::connect(std::env::var("DATABASE_URL").unwrap().as_str()).await?;
 
-  client
-    .goto("https://portal.inmet.gov.br/paginas/catalogoaut")
-    .await
-    .unwrap();
+  let html = retry::with_backoff("stations catalog", 3, || load_catalog(client)).await?;
 
-  client
-    .wait()
-    .at_most(Duration::from_secs(10))
-    .for_element(Locator::Css("#tb"))
-    .await
-    .unwrap();
+  let parsed = parse_stations(&html)?;
+  let mut stats = RunStats::default();
+
+  for skipped in &parsed.skipped {
+    println!("Skipped catalog row {}: {}", skipped.row, skipped.reason);
+  }
 
-  let html = client.source().await.unwrap();
+  stats.rows_skipped += parsed.skipped.len() as i64;
 
-  let stations = parse_stations(html).await;
+  let stations = parsed.rows;
+
+  if stations.is_empty() {
+    return Err(CrawlerError::Parse(
+      "no stations found on the catalog page".to_string(),
+    ));
+  }
 
   let stations_code = stations
     .clone()
@@ -99,50 +109,18 @@ pub(crate) async fn handler(client: &fantoccini::Client) -> Result<(), CmdError>
     &stations_code[..]
   )
   .fetch_all(&pool)
-  .await
-  .expect("error on find stations on the DB");
+  .await?;
 
   for station in stations {
     let station_db = stations_db
-      .to_owned()
-      .into_iter()
-      .find(|s| s.to_owned().inmet_code.unwrap_or("".to_string()) == station.code);
-
-    let point = format!(
-      "POINT ({} {})",
-      station.latitude.to_string().replace(",", "."),
-      station.longitude.to_string().replace(",", ".")
-    );
-
-    let status = station.situation == "Operante";
-
-    if station_db.is_some() {
-      sqlx::query!(
-        "UPDATE stations SET city = $2, status = $3, location = ST_PointFromText($4)::point WHERE id = $1;",
-        station_db.unwrap().id,
-        station.city,
-        status,
-        point
-      )
-      .execute(&pool)
-      .await
-      .unwrap();
-      continue;
-    }
+      .iter()
+      .find(|s| s.inmet_code.as_deref().unwrap_or("") == station.code);
 
-    sqlx::query!(
-      "INSERT INTO stations (city, uf, location, status, inmet_code)
-                VALUES ($1, $2, ST_PointFromText($3)::point, $4, $5);",
-      station.city,
-      station.uf,
-      point,
-      status,
-      station.code
-    )
-    .execute(&pool)
-    .await
-    .unwrap();
+    match save_station(&pool, &station, station_db, &mut stats).await {
+      Ok(()) => stats.success(),
+      Err(e) => stats.failure(&format!("station {}", station.code), &e),
+    }
   }
 
-  Ok(())
+  Ok(stats)
 }
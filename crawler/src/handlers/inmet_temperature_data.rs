@@ -1,30 +1,41 @@
-use crate::scrapers::inmet_station_data::get_station_data;
+use crate::{
+  error::CrawlerError,
+  scrapers::inmet_station_data::get_station_data,
+  utils::crawl_runs::RunStats,
+};
 use chrono::Utc;
-use fantoccini::error::CmdError;
 use std::collections::HashMap;
 
-pub(crate) async fn handler(client: &fantoccini::Client) -> Result<(), CmdError> {
+pub(crate) async fn handler(client: &fantoccini::Client) -> Result<RunStats, CrawlerError> {
   let stations_data = get_station_data(
     &client,
     "A814".to_string(),
     Utc::now() - chrono::Duration::days(7),
   )
-  .await;
+  .await?;
+
+  let mut stats = RunStats::default();
+  stats.rows_skipped += stations_data.skipped.len() as i64;
 
   let mut temperatures: HashMap<String, Vec<String>> = HashMap::new();
   let mut humidities: HashMap<String, Vec<String>> = HashMap::new();
 
-  for data in stations_data {
+  for data in stations_data.rows {
+    let (Some(temperature), Some(humidity)) = (data.temperature.instant, data.humidity.instant)
+    else {
+      continue;
+    };
+
     let index_temperature = format!(
       "{}|{}",
       data.date.format("%Y-%m-%d").to_string(),
-      data.temperature[0].floor().to_string()
+      temperature.floor().to_string()
     );
 
     let index_humidity = format!(
       "{}|{}",
       data.date.format("%Y-%m-%d").to_string(),
-      data.humidity[0].to_string()
+      humidity.to_string()
     );
 
     if temperatures.contains_key(&index_temperature) {
@@ -86,5 +97,5 @@ pub(crate) async fn handler(client: &fantoccini::Client) -> Result<(), CmdError>
     }
   }
 
-  Ok(())
+  Ok(stats)
 }
@@ -1,4 +1,120 @@
+pub mod forecast_risk;
+pub mod hotspots;
 pub mod inmet_stations;
 pub mod inmet_temperature_data;
 pub mod ocurrence_inmet_ocorrence_data;
 pub mod ocurrence_inmet_probability;
+
+use crate::{
+  error::CrawlerError,
+  utils::{
+    crawl_runs::{self, RunStats},
+    database::DataBase,
+  },
+};
+
+/// Scripts accepted by `--script` and by the daemon schedules.
+pub(crate) const SCRIPTS: [&str; 7] = [
+  "ocurrence-immet-climate-data",
+  "ocurrence-immet-climate-data-pending",
+  "inmet-stations",
+  "ocurrence-probability",
+  "inmet-temperature-data",
+  "forecast-risk",
+  "hotspots",
+];
+
+/// Runs the script, keeping its execution on `crawl_runs`.
+pub(crate) async fn run(
+  client: &fantoccini::Client,
+  script: &str,
+  ocurrence_id: Option<String>,
+  pathogenic_id: Option<String>,
+) -> Result<RunStats, CrawlerError> {
+  let db: DataBase = DataBase::new().await;
+
+  let mut args: Vec<String> = Vec::new();
+  if let Some(ocurrence_id) = ocurrence_id.as_ref() {
+    args.push(format!("--ocurrence-id {}", ocurrence_id));
+  }
+  if let Some(pathogenic_id) = pathogenic_id.as_ref() {
+    args.push(format!("--pathogenic-id {}", pathogenic_id));
+  }
+
+  let run_id = crawl_runs::start(&db, script, &args.join(" ")).await;
+
+  // Spawned so a panic is recorded as a failed run before going up
+  let script_client = client.clone();
+  let script_name = script.to_string();
+  let result = tokio::spawn(async move {
+    execute(&script_client, &script_name, ocurrence_id, pathogenic_id).await
+  })
+  .await;
+
+  match result {
+    Ok(Ok(stats)) => {
+      crawl_runs::finish(&db, run_id, stats.status(), &stats, stats.error()).await;
+      Ok(stats)
+    }
+    Ok(Err(e)) => {
+      crawl_runs::finish(
+        &db,
+        run_id,
+        "failed",
+        &RunStats::default(),
+        Some(crawl_runs::error_chain(&e)),
+      )
+      .await;
+      Err(e)
+    }
+    Err(e) => {
+      let panic = match e.try_into_panic() {
+        Ok(panic) => panic,
+        Err(e) => Box::new(e.to_string()),
+      };
+
+      let message = panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|m| m.to_string()))
+        .unwrap_or_default();
+
+      crawl_runs::finish(
+        &db,
+        run_id,
+        "failed",
+        &RunStats::default(),
+        Some(format!("panicked: {}", message)),
+      )
+      .await;
+
+      std::panic::resume_unwind(panic)
+    }
+  }
+}
+
+async fn execute(
+  client: &fantoccini::Client,
+  script: &str,
+  ocurrence_id: Option<String>,
+  pathogenic_id: Option<String>,
+) -> Result<RunStats, CrawlerError> {
+  match script {
+    "ocurrence-immet-climate-data" => {
+      ocurrence_inmet_ocorrence_data::handler(
+        client,
+        ocurrence_id.expect("--ocurrence-id is required"),
+      )
+      .await
+    }
+    "ocurrence-immet-climate-data-pending" => {
+      ocurrence_inmet_ocorrence_data::pending_handler(client).await
+    }
+    "inmet-stations" => inmet_stations::handler(client).await,
+    "ocurrence-probability" => ocurrence_inmet_probability::handler(client, pathogenic_id).await,
+    "inmet-temperature-data" => inmet_temperature_data::handler(client).await,
+    "forecast-risk" => forecast_risk::handler(pathogenic_id).await,
+    "hotspots" => hotspots::handler(pathogenic_id).await,
+    _ => panic!("script not found"),
+  }
+}
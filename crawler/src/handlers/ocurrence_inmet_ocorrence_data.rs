@@ -1,6 +1,14 @@
-use crate::{scrapers::inmet_station_data::get_station_data, utils::database::DataBase};
+use crate::{
+  error::CrawlerError,
+  scrapers::station_fetcher::{StationData, StationFetcher},
+  utils::{
+    crawl_runs::{self, RunStats},
+    database::DataBase,
+  },
+};
 use chrono::NaiveDateTime;
-use fantoccini::error::CmdError;
+use crawler::quality::{Climate, MAX_INTERPOLATED_HOURS};
+use sqlx::PgConnection;
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -14,86 +22,257 @@ struct Ocurrence {
   // pub update_date: Option<NaiveDateTime>,
 }
 
-pub(crate) async fn handler(
-  client: &fantoccini::Client,
-  ocurrence_id: String,
-) -> Result<(), CmdError> {
-  let db: DataBase = DataBase::new().await;
+/// Times the INMET page is requested before giving up on a station
+const FETCH_ATTEMPTS: u32 = 3;
 
-  let ocurrence = sqlx::query_as!(
-    Ocurrence,
-    "SELECT ppo.id, s.inmet_code AS station_immet_code, occurrence_date
+/// Runs of the pending script an ocurrence can fail before it stops being retried
+fn max_task_attempts() -> i32 {
+  std::env::var("CLIMATE_TASK_MAX_ATTEMPTS")
+    .ok()
+    .and_then(|attempts| attempts.parse::<i32>().ok())
+    .unwrap_or(5)
+}
+
+/// Ocurrences that still don't have the climate buckets, optionally limited to
+/// a single one.
+async fn pending_ocurrences(
+  db: &DataBase,
+  ocurrence_id: Option<Uuid>,
+  max_attempts: i32,
+) -> Result<Vec<Ocurrence>, CrawlerError> {
+  Ok(
+    sqlx::query_as!(
+      Ocurrence,
+      "SELECT ppo.id, s.inmet_code AS station_immet_code, occurrence_date
     FROM plantation_pathogenic_occurrences AS ppo
              JOIN plantations p ON p.id = ppo.plantation_id
              JOIN stations s ON s.id = p.station_id
-    WHERE ppo.id = $1
+             LEFT JOIN ocurrence_climate_tasks oct ON oct.ocurrence_id = ppo.id
+    WHERE ($1::uuid IS NULL OR ppo.id = $1)
+      AND p.delete_at IS NULL
       AND s.inmet_code IS NOT NULL
-      AND (SELECT COUNT(*)
-           FROM plantation_pathogenic_occurrences_temperatures
-           WHERE plantation_pathogenic_occurrence_id = ppo.id) = 0
-      AND (SELECT COUNT(*)
-            FROM plantation_pathogenic_occurrences_humidities
-            WHERE plantation_pathogenic_occurrence_id = ppo.id) = 0
-    LIMIT 1",
-    Uuid::parse_str(&ocurrence_id).unwrap()
+      AND NOT EXISTS(SELECT
+                     FROM plantation_pathogenic_occurrences_temperatures
+                     WHERE plantation_pathogenic_occurrence_id = ppo.id)
+      AND NOT EXISTS(SELECT
+                     FROM plantation_pathogenic_occurrences_humidities
+                     WHERE plantation_pathogenic_occurrence_id = ppo.id)
+      AND COALESCE(oct.attempts, 0) < $2
+    ORDER BY ppo.occurrence_date",
+      ocurrence_id,
+      max_attempts
+    )
+    .fetch_all(&db.pool)
+    .await?,
+  )
+}
+
+pub(crate) async fn handler(
+  client: &fantoccini::Client,
+  ocurrence_id: String,
+) -> Result<RunStats, CrawlerError> {
+  let db: DataBase = DataBase::new().await;
+  let mut stats = RunStats::default();
+
+  let ocurrence_id = Uuid::parse_str(&ocurrence_id)
+    .map_err(|e| CrawlerError::Parse(format!("ocurrence id {}: {}", ocurrence_id, e)))?;
+
+  let ocurrences = pending_ocurrences(&db, Some(ocurrence_id), i32::MAX).await?;
+  let fetched = fetch_stations(client, &ocurrences).await;
+
+  for (ocurrence, stations_data) in ocurrences.iter().zip(fetched) {
+    let result = match stations_data {
+      Ok(stations_data) => process_ocurrence(&db, ocurrence, &stations_data, &mut stats).await,
+      Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+      task_failed(&db, ocurrence.id, &e.to_string()).await?;
+      return Err(e);
+    }
+
+    stats.success();
+  }
+
+  Ok(stats)
+}
+
+/// Fills the climate data of every ocurrence queued by the app, or created
+/// before the queue existed. Failures are recorded and retried on the next run.
+pub(crate) async fn pending_handler(client: &fantoccini::Client) -> Result<RunStats, CrawlerError> {
+  let db: DataBase = DataBase::new().await;
+  let mut stats = RunStats::default();
+
+  let ocurrences = pending_ocurrences(&db, None, max_task_attempts()).await?;
+
+  println!("{} ocurrences without climate data", ocurrences.len());
+
+  let fetched = fetch_stations(client, &ocurrences).await;
+
+  for (ocurrence, stations_data) in ocurrences.iter().zip(fetched) {
+    let result = match stations_data {
+      Ok(stations_data) => process_ocurrence(&db, ocurrence, &stations_data, &mut stats).await,
+      Err(e) => Err(e),
+    };
+
+    match result {
+      Ok(()) => stats.success(),
+      Err(e) => {
+        stats.failure(&format!("ocurrence {}", ocurrence.id), &e);
+        task_failed(&db, ocurrence.id, &e.to_string()).await?;
+      }
+    }
+  }
+
+  Ok(stats)
+}
+
+/// Station data of each ocurrence, from 12 days before it. Ocurrences of the same
+/// station and day share the fetch.
+async fn fetch_stations(
+  client: &fantoccini::Client,
+  ocurrences: &[Ocurrence],
+) -> Vec<Result<StationData, CrawlerError>> {
+  let fetcher = StationFetcher::new(client, FETCH_ATTEMPTS).await;
+
+  let fetched = fetcher
+    .fetch_all(
+      ocurrences
+        .iter()
+        .map(|ocurrence| {
+          (
+            ocurrence.station_immet_code.clone().unwrap_or_default(),
+            (ocurrence.occurrence_date - chrono::Duration::days(12)).and_utc(),
+          )
+        })
+        .collect(),
+    )
+    .await;
+
+  fetcher.close().await;
+
+  fetched
+}
+
+async fn task_failed(db: &DataBase, ocurrence_id: Uuid, error: &str) -> Result<(), CrawlerError> {
+  sqlx::query!(
+    "INSERT INTO ocurrence_climate_tasks (ocurrence_id, attempts, last_error)
+    VALUES ($1, 1, $2)
+    ON CONFLICT (ocurrence_id) DO UPDATE
+        SET attempts    = ocurrence_climate_tasks.attempts + 1,
+            last_error  = $2,
+            update_date = NOW()",
+    ocurrence_id,
+    error
   )
-  .fetch_one(&db.pool)
-  .await
-  .map_err(DataBase::database_error)
-  .unwrap();
-
-  let stations_data = get_station_data(
-    &client,
-    ocurrence.station_immet_code.unwrap(),
-    (ocurrence.occurrence_date - chrono::Duration::days(12)).and_utc(),
+  .execute(&db.pool)
+  .await?;
+
+  Ok(())
+}
+
+async fn task_done(connection: &mut PgConnection, ocurrence_id: Uuid) -> Result<(), CrawlerError> {
+  sqlx::query!(
+    "INSERT INTO ocurrence_climate_tasks (ocurrence_id, done_date)
+    VALUES ($1, NOW())
+    ON CONFLICT (ocurrence_id) DO UPDATE
+        SET done_date   = NOW(),
+            last_error  = NULL,
+            update_date = NOW()",
+    ocurrence_id
+  )
+  .execute(connection)
+  .await?;
+
+  Ok(())
+}
+
+/// Saves the climate buckets and averages of the ocurrence, with its task, in
+/// a single transaction: a failure leaves nothing behind and the ocurrence is
+/// retried.
+async fn process_ocurrence(
+  db: &DataBase,
+  ocurrence: &Ocurrence,
+  stations_data: &StationData,
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  let inmet_code = ocurrence.station_immet_code.clone().unwrap_or_default();
+
+  stats.rows_skipped += stations_data.skipped.len() as i64;
+
+  let stations_data = &stations_data.rows;
+
+  crawl_runs::station_fetched(
+    db,
+    &inmet_code,
+    stations_data.iter().map(|data| data.date).max(),
   )
   .await;
 
   let mut temperatures: HashMap<String, Vec<String>> = HashMap::new();
   let mut humidities: HashMap<String, Vec<String>> = HashMap::new();
 
-  for data in stations_data {
-    if data.date.timestamp() > ocurrence.occurrence_date.timestamp() {
+  let mut temperature_sum = 0.0;
+  let mut humidity_sum = 0.0;
+  let mut hours = 0;
+
+  let climate = Climate::new(stations_data, MAX_INTERPOLATED_HOURS);
+
+  println!("Ocurrence {}: {}", ocurrence.id, climate.summary());
+
+  // Hours without one of the readings are left out, instead of counting as zero
+  for (date, temperature, humidity) in climate.hours() {
+    if date > ocurrence.occurrence_date {
       continue;
     }
 
+    temperature_sum += temperature;
+    humidity_sum += humidity;
+    hours += 1;
+
     // group by day and temperature
 
     let index_temperature = format!(
       "{}|{}",
-      data.date.format("%Y-%m-%d").to_string(),
-      data.temperature[0].floor().to_string()
+      date.format("%Y-%m-%d").to_string(),
+      temperature.floor().to_string()
     );
 
+    // Interpolated hours have fractional humidities, each would be a bucket
     let index_humidity = format!(
       "{}|{}",
-      data.date.format("%Y-%m-%d").to_string(),
-      data.humidity[0].to_string()
+      date.format("%Y-%m-%d").to_string(),
+      humidity.round().to_string()
     );
 
     if temperatures.contains_key(&index_temperature) {
       temperatures
         .get_mut(&index_temperature)
         .unwrap()
-        .push(data.date.format("%H:%M").to_string());
+        .push(date.format("%H:%M").to_string());
     } else {
-      temperatures.insert(
-        index_temperature,
-        vec![data.date.format("%H:%M").to_string()],
-      );
+      temperatures.insert(index_temperature, vec![date.format("%H:%M").to_string()]);
     }
 
     if humidities.contains_key(&index_humidity) {
       humidities
         .get_mut(&index_humidity)
         .unwrap()
-        .push(data.date.format("%H:%M").to_string());
+        .push(date.format("%H:%M").to_string());
     } else {
-      humidities.insert(index_humidity, vec![data.date.format("%H:%M").to_string()]);
+      humidities.insert(index_humidity, vec![date.format("%H:%M").to_string()]);
     }
   }
 
-  for temperature in temperatures {
+  if hours == 0 {
+    return Err(CrawlerError::Parse(
+      "no station data before the ocurrence date".to_string(),
+    ));
+  }
+
+  let mut transaction = db.pool.begin().await?;
+
+  for temperature in &temperatures {
     let temperature_values: Vec<&str> = temperature.0.split("|").collect();
     let date = temperature_values[0];
     let temperature_celcius = temperature_values[1];
@@ -104,17 +283,15 @@ pub(crate) async fn handler(
       "INSERT INTO plantation_pathogenic_occurrences_temperatures (plantation_pathogenic_occurrence_id, date, temperature, quantity)
       VALUES ($1, $2, $3, $4)",
       ocurrence.id,
-      NaiveDateTime::parse_from_str(format!("{} 00:00:00", date).as_str(), "%Y-%m-%d %H:%M:%S")
-        .unwrap(),
-        temperature_celcius.parse::<f64>().unwrap(),
-        i32::try_from(temp).unwrap(),
+      parse_bucket_date(date)?,
+      parse_bucket_value(temperature_celcius)?,
+      i32::try_from(temp).unwrap_or(i32::MAX),
     )
-    .execute(&db.pool)
-    .await
-    .map_err(DataBase::database_error).unwrap();
+    .execute(&mut *transaction)
+    .await?;
   }
 
-  for humidity in humidities {
+  for humidity in &humidities {
     let humidity_values: Vec<&str> = humidity.0.split("|").collect();
     let date = humidity_values[0];
     let humidity_value = humidity_values[1];
@@ -125,15 +302,43 @@ pub(crate) async fn handler(
       "INSERT INTO plantation_pathogenic_occurrences_humidities (plantation_pathogenic_occurrence_id, date, humidity, quantity)
       VALUES ($1, $2, $3, $4)",
       ocurrence.id,
-      NaiveDateTime::parse_from_str(format!("{} 00:00:00", date).as_str(), "%Y-%m-%d %H:%M:%S")
-        .unwrap(),
-        humidity_value.parse::<f64>().unwrap(),
-        i32::try_from(temp).unwrap(),
+      parse_bucket_date(date)?,
+      parse_bucket_value(humidity_value)?,
+      i32::try_from(temp).unwrap_or(i32::MAX),
     )
-    .execute(&db.pool)
-    .await
-    .map_err(DataBase::database_error).unwrap();
+    .execute(&mut *transaction)
+    .await?;
   }
 
+  // Keep the averages on the ocurrence row, so it can be read without the buckets
+  sqlx::query!(
+    "UPDATE plantation_pathogenic_occurrences
+    SET temperature = $2, humidity = $3, update_date = NOW()
+    WHERE id = $1",
+    ocurrence.id,
+    temperature_sum / hours as f64,
+    humidity_sum / hours as f64,
+  )
+  .execute(&mut *transaction)
+  .await?;
+
+  task_done(&mut transaction, ocurrence.id).await?;
+
+  transaction.commit().await?;
+
+  stats.rows_inserted += (temperatures.len() + humidities.len()) as i64;
+  stats.rows_updated += 1;
+
   Ok(())
 }
+
+fn parse_bucket_date(date: &str) -> Result<NaiveDateTime, CrawlerError> {
+  NaiveDateTime::parse_from_str(format!("{} 00:00:00", date).as_str(), "%Y-%m-%d %H:%M:%S")
+    .map_err(|e| CrawlerError::Parse(format!("date {}: {}", date, e)))
+}
+
+fn parse_bucket_value(value: &str) -> Result<f64, CrawlerError> {
+  value
+    .parse::<f64>()
+    .map_err(|e| CrawlerError::Parse(format!("value {}: {}", value, e)))
+}
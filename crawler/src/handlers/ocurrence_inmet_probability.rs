@@ -1,138 +1,325 @@
-use crate::utils::database::DataBase;
+use crate::{
+  error::CrawlerError,
+  scrapers::station_fetcher::{StationData, StationFetcher},
+  utils::{
+    alert_policies::AlertPolicies,
+    crawl_runs::{self, RunStats},
+    database::DataBase,
+    notification,
+    plantation_risks::{self, PathogenicCulture, Plantation, Protection, Risk, RiskKind},
+  },
+};
 use chrono::Utc;
-use reqwest::Client;
+use crawler::{
+  parsers::inmet_observations::InmetStationData,
+  quality::{Climate, MAX_INTERPOLATED_HOURS, MIN_CONFIDENCE},
+};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
 
-#[derive(Debug, serde::Serialize, Clone)]
-pub(crate) struct Station {
-  pub id: i64,
-  pub status: bool,
-  pub inmet_code: Option<String>,
+const FETCH_ATTEMPTS: u32 = 3;
+
+/// A plantation at risk, to be named on its owner's alert
+struct Alert {
+  plantation: String,
+  pathogenic: String,
+}
+
+/// Alerts of the run by user, to the growers and to the agronomists their
+/// organizations copy
+struct Alerts {
+  policies: AlertPolicies,
+  by_user: HashMap<Uuid, Vec<Alert>>,
 }
 
-#[derive(Debug, serde::Serialize, Clone)]
+impl Alerts {
+  fn add(&mut self, plantation: &Plantation, pathogenic: &PathogenicCulture, risk: &Risk) {
+    if self
+      .policies
+      .alerts_grower(plantation.owner_id, pathogenic.id, risk)
+    {
+      self
+        .by_user
+        .entry(plantation.owner_id)
+        .or_default()
+        .push(Alert {
+          plantation: plantation
+            .alias
+            .clone()
+            .unwrap_or_else(|| format!("sua plantação de {}", pathogenic.culture_name)),
+          pathogenic: pathogenic.name.clone(),
+        });
+    }
 
-pub(crate) struct PathogenicCulture {
-  pub(crate) id: i64,
-  pub(crate) name: String,
-  pub(crate) scientific_name: String,
-  pub(crate) culture_id: i64,
-  pub(crate) culture_name: String,
-  pub(crate) culture_scientific_name: String,
+    for agronomist in self
+      .policies
+      .agronomists(plantation.owner_id, pathogenic.id, risk)
+    {
+      self.by_user.entry(agronomist).or_default().push(Alert {
+        plantation: format!(
+          "{} ({})",
+          plantation
+            .alias
+            .clone()
+            .unwrap_or_else(|| format!("plantação de {}", pathogenic.culture_name)),
+          plantation.owner_name
+        ),
+        pathogenic: pathogenic.name.clone(),
+      });
+    }
+  }
 }
 
+/// Evaluates the risk of every pathogen on the plantations of its cultures,
+/// or only of `pathogenic_id`. Each station is fetched and checked once, and
+/// each user gets one alert naming the plantations at risk, as the policies of
+/// their organizations ask.
 pub(crate) async fn handler(
   client: &fantoccini::Client,
-  pathogenic_id: String,
-) -> Result<(), fantoccini::error::CmdError> {
+  pathogenic_id: Option<String>,
+) -> Result<RunStats, CrawlerError> {
   let db: DataBase = DataBase::new().await;
 
-  let pathogenic = sqlx::query_as!(
-    PathogenicCulture,
-    "SELECT p.id,
-          p.name,
-          p.scientific_name,
-          c.id              AS culture_id,
-          c.name            AS culture_name,
-          c.scientific_name AS culture_scientific_name
-      FROM pathogenics p
-            JOIN pathogenic_cultures pc ON pc.pathogenic_id = p.id
-            JOIN cultures c ON c.id = pc.culture_id
-      WHERE p.id = $1
-      LIMIT 1",
-    pathogenic_id.parse::<i64>().unwrap()
-  )
-  .fetch_one(&db.pool)
-  .await
-  .unwrap();
-
-  let stations = sqlx::query_as!(
-    Station,
-    "SELECT s.id, s.status, s.inmet_code
-    FROM stations s
-        JOIN plantations p ON s.id = p.station_id
-    WHERE s.status = TRUE
-      AND s.inmet_code IS NOT NULL
-      AND p.culture_id = $1
-    GROUP BY s.id",
-    pathogenic.culture_id
+  let pathogenic_id = pathogenic_id
+    .map(|id| {
+      id.parse::<i64>()
+        .map_err(|e| CrawlerError::Parse(format!("pathogenic id {}: {}", id, e)))
+    })
+    .transpose()?;
+
+  let pathogenics = plantation_risks::pathogenics(&db, pathogenic_id).await?;
+  let plantations = plantation_risks::plantations(&db, &pathogenics).await?;
+  let protections = plantation_risks::protections(
+    &db,
+    &plantations
+      .iter()
+      .map(|plantation| plantation.id)
+      .collect::<Vec<_>>(),
+    (Utc::now() - chrono::Duration::days(1)).date_naive(),
   )
-  .fetch_all(&db.pool)
-  .await
-  .unwrap();
+  .await?;
 
-  let google_jwt_token = crate::utils::google_jwt::get_firebase_jwt().await;
+  let mut stations: BTreeMap<String, Vec<&Plantation>> = BTreeMap::new();
 
-  for station in stations {
-    let stations_data = crate::scrapers::inmet_station_data::get_station_data(
-      &client,
-      station.inmet_code.unwrap(),
-      Utc::now() - chrono::Duration::days(1),
-    )
+  for plantation in &plantations {
+    stations
+      .entry(plantation.inmet_code.clone())
+      .or_default()
+      .push(plantation);
+  }
+
+  println!(
+    "{} pathogen-culture pairs, {} plantations on {} stations",
+    pathogenics.len(),
+    plantations.len(),
+    stations.len()
+  );
+
+  let mut stats = RunStats::default();
+
+  let fetcher = StationFetcher::new(client, FETCH_ATTEMPTS).await;
+  let since = Utc::now() - chrono::Duration::days(1);
+
+  let fetched = fetcher
+    .fetch_all(stations.keys().map(|code| (code.clone(), since)).collect())
     .await;
 
-    let hours_temperature = stations_data
-      .iter()
-      .filter(|data| data.temperature[0] > 17.0 && data.temperature[0] < 24.0)
-      .count();
+  fetcher.close().await;
 
-    let hours_humidity = stations_data
-      .iter()
-      .filter(|data| data.humidity[0] > 90.0)
-      .count();
-
-    if hours_temperature > 12 && hours_humidity > 12 {
-      let users = sqlx::query!(
-        "SELECT u.notification_token, u.id
-        FROM stations s
-        JOIN plantations p ON s.id = p.station_id
-        JOIN users u ON u.id = p.user_id
-        WHERE s.id = $1
-          AND p.culture_id = $2
-        GROUP BY u.id",
-        station.id,
-        pathogenic.culture_id
-      )
-      .fetch_all(&db.pool)
-      .await
-      .unwrap();
-
-      for user in users {
-        if user.notification_token.is_none() {
-          continue;
-        }
-
-        let message = format!(
-          "Detectamos que há probabilidade de {} em uma ou mais plantações de {}.",
-          pathogenic.name, pathogenic.culture_name
-        );
-
-        let body = serde_json::json!({
-          "message": {
-            "token": user.notification_token.unwrap(),
-            "notification": {
-              "title": "ALERTA: Probabilidade de ocorrência",
-              "body": message,
-            },
-          }
-        });
+  let mut alerts = Alerts {
+    policies: AlertPolicies::load(&db).await?,
+    by_user: HashMap::new(),
+  };
 
-        sqlx::query!(
-          "INSERT INTO user_notifications (user_id, message) VALUES ($1, $2)",
-          user.id,
-          message
+  for ((inmet_code, station_plantations), stations_data) in stations.iter().zip(fetched) {
+    let result = match stations_data {
+      Ok(stations_data) => {
+        process_station(
+          &db,
+          &stations_data,
+          station_plantations,
+          &pathogenics,
+          &protections,
+          &mut alerts,
+          &mut stats,
         )
-        .execute(&db.pool)
         .await
-        .unwrap();
-
-        let _ = Client::new()
-          .post("https://fcm.googleapis.com/v1/projects/cropi-399723/messages:send")
-          .header("Content-Type", "application/json")
-          .header("Authorization", format!("Bearer {}", google_jwt_token))
-          .body(body.to_string())
-          .send()
-          .await;
       }
+      Err(e) => Err(e),
+    };
+
+    match result {
+      Ok(()) => stats.success(),
+      Err(e) => stats.failure(&format!("station {}", inmet_code), &e),
+    }
+  }
+
+  let messages = alerts
+    .by_user
+    .into_iter()
+    .map(|(user_id, alerts)| (user_id, alert_message(&alerts)))
+    .collect();
+
+  notification::notify_users(
+    &db,
+    "ALERTA: Probabilidade de ocorrência",
+    messages,
+    &mut stats,
+  )
+  .await?;
+
+  Ok(stats)
+}
+
+async fn process_station(
+  db: &DataBase,
+  stations_data: &StationData,
+  plantations: &[&Plantation],
+  pathogenics: &[PathogenicCulture],
+  protections: &[Protection],
+  alerts: &mut Alerts,
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  let inmet_code = &plantations[0].inmet_code;
+
+  stats.rows_skipped += stations_data.skipped.len() as i64;
+
+  let stations_data = &stations_data.rows;
+
+  crawl_runs::station_fetched(
+    db,
+    inmet_code,
+    stations_data.iter().map(|data| data.date).max(),
+  )
+  .await;
+
+  save_observations(db, plantations[0].station_id, stations_data, stats).await?;
+
+  let climate = Climate::new(stations_data, MAX_INTERPOLATED_HOURS);
+
+  println!("Station {}: {}", inmet_code, climate.summary());
+
+  // Not alerting is safer than alerting from a handful of readings
+  if climate.confidence() < MIN_CONFIDENCE {
+    return Err(CrawlerError::Parse(format!(
+      "not enough readings to assess the risk ({})",
+      climate.summary()
+    )));
+  }
+
+  let (Some(period_start), Some(period_end)) = (
+    stations_data.iter().map(|data| data.date).min(),
+    stations_data.iter().map(|data| data.date).max(),
+  ) else {
+    return Ok(());
+  };
+
+  for pathogenic in pathogenics {
+    let culture_plantations = plantations
+      .iter()
+      .filter(|plantation| plantation.culture_id == pathogenic.culture_id)
+      .collect::<Vec<_>>();
+
+    if culture_plantations.is_empty() {
+      continue;
+    }
+
+    let risk = Risk::new(&climate, pathogenic);
+
+    for plantation in culture_plantations {
+      let risk = risk.protected_by(plantation_risks::protection(
+        protections,
+        plantation,
+        pathogenic,
+        period_end.date(),
+      ));
+
+      let inserted = plantation_risks::save(
+        db,
+        plantation,
+        pathogenic,
+        RiskKind::Observed,
+        &risk,
+        climate.confidence(),
+        (period_start, period_end),
+      )
+      .await?;
+
+      if inserted {
+        stats.rows_inserted += 1;
+      } else {
+        stats.rows_updated += 1;
+      }
+
+      alerts.add(plantation, pathogenic, &risk);
+    }
+  }
+
+  Ok(())
+}
+
+/// `Detectamos que há probabilidade de Ferrugem em Talhão 1 e Talhão 2; Oídio em Talhão 3.`
+fn alert_message(alerts: &[Alert]) -> String {
+  let mut by_pathogenic: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+  for alert in alerts {
+    by_pathogenic
+      .entry(&alert.pathogenic)
+      .or_default()
+      .push(&alert.plantation);
+  }
+
+  let risks = by_pathogenic
+    .into_iter()
+    .map(|(pathogenic, plantations)| format!("{} em {}", pathogenic, join(&plantations)))
+    .collect::<Vec<_>>();
+
+  format!("Detectamos que há probabilidade de {}.", risks.join("; "))
+}
+
+/// `a`, `a e b`, `a, b e c`
+fn join(items: &[&str]) -> String {
+  match items {
+    [] => String::new(),
+    [item] => item.to_string(),
+    [items @ .., last] => format!("{} e {}", items.join(", "), last),
+  }
+}
+
+/// Keeps the hourly readings of the station, the spraying windows check the
+/// rain that already fell on them
+async fn save_observations(
+  db: &DataBase,
+  station_id: i64,
+  stations_data: &[InmetStationData],
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  for data in stations_data {
+    let inserted = sqlx::query_scalar!(
+      r#"INSERT INTO station_observations (station_id, date, temperature, humidity, precipitation,
+                                  wind_speed)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      ON CONFLICT (station_id, date) DO UPDATE
+          SET temperature   = $3,
+              humidity      = $4,
+              precipitation = $5,
+              wind_speed    = $6,
+              fetch_date    = NOW()
+      RETURNING (xmax = 0) AS "inserted!""#,
+      station_id,
+      data.date,
+      data.temperature.instant,
+      data.humidity.instant,
+      data.precipitation,
+      data.wind_speed
+    )
+    .fetch_one(&db.pool)
+    .await?;
+
+    if inserted {
+      stats.rows_inserted += 1;
+    } else {
+      stats.rows_updated += 1;
     }
   }
 
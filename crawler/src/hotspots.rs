@@ -0,0 +1,157 @@
+//! Clustering of the occurrences of a pathogen into hotspots with DBSCAN, and
+//! how the hotspots are followed from one run to the next.
+
+/// Mean radius of the Earth, in km
+const EARTH_RADIUS: f64 = 6371.0;
+
+/// Share a hotspot has to grow since the last run to be emerging again.
+pub const EMERGING_GROWTH: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+  pub latitude: f64,
+  pub longitude: f64,
+}
+
+/// Great-circle distance, in km
+pub fn distance_km(a: Point, b: Point) -> f64 {
+  let (lat_a, lat_b) = (a.latitude.to_radians(), b.latitude.to_radians());
+  let d_lat = lat_b - lat_a;
+  let d_lon = (b.longitude - a.longitude).to_radians();
+
+  let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+
+  2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Indexes of the points of each cluster. A point is a core one when at least
+/// `min_points` points, itself included, are within `eps_km`; points out of reach
+/// of every core point are noise and left out.
+pub fn dbscan(points: &[Point], eps_km: f64, min_points: usize) -> Vec<Vec<usize>> {
+  let neighbours = |index: usize| -> Vec<usize> {
+    (0..points.len())
+      .filter(|&other| distance_km(points[index], points[other]) <= eps_km)
+      .collect()
+  };
+
+  let mut visited = vec![false; points.len()];
+  let mut clustered = vec![false; points.len()];
+  let mut clusters = vec![];
+
+  for index in 0..points.len() {
+    if visited[index] {
+      continue;
+    }
+    visited[index] = true;
+
+    let mut queue = neighbours(index);
+    if queue.len() < min_points {
+      continue;
+    }
+
+    let mut cluster = vec![index];
+    clustered[index] = true;
+
+    while let Some(other) = queue.pop() {
+      if !clustered[other] {
+        clustered[other] = true;
+        cluster.push(other);
+      }
+
+      if visited[other] {
+        continue;
+      }
+      visited[other] = true;
+
+      let reached = neighbours(other);
+      if reached.len() >= min_points {
+        queue.extend(reached.into_iter().filter(|&next| !clustered[next]));
+      }
+    }
+
+    cluster.sort_unstable();
+    clusters.push(cluster);
+  }
+
+  clusters
+}
+
+/// Where a cluster is and how far it spreads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extent {
+  pub centroid: Point,
+  /// Distance from the centroid to the farthest point
+  pub radius_km: f64,
+}
+
+impl Extent {
+  /// Extent of the `members` of `points`, `None` when there are no members.
+  pub fn new(points: &[Point], members: &[usize]) -> Option<Extent> {
+    if members.is_empty() {
+      return None;
+    }
+
+    let count = members.len() as f64;
+    let centroid = Point {
+      latitude: members.iter().map(|&i| points[i].latitude).sum::<f64>() / count,
+      longitude: members.iter().map(|&i| points[i].longitude).sum::<f64>() / count,
+    };
+
+    let radius_km = members
+      .iter()
+      .map(|&i| distance_km(centroid, points[i]))
+      .fold(0.0, f64::max);
+
+    Some(Extent {
+      centroid,
+      radius_km,
+    })
+  }
+
+  /// Whether `point` is within `buffer_km` of the hotspot, now or where it
+  /// gets if it keeps moving as it did since `previous`.
+  pub fn in_path(&self, previous: Option<Point>, point: Point, buffer_km: f64) -> bool {
+    let reach = self.radius_km + buffer_km;
+
+    if distance_km(self.centroid, point) <= reach {
+      return true;
+    }
+
+    previous.is_some_and(|previous| {
+      let ahead = Point {
+        latitude: 2.0 * self.centroid.latitude - previous.latitude,
+        longitude: 2.0 * self.centroid.longitude - previous.longitude,
+      };
+
+      distance_km(ahead, point) <= reach
+    })
+  }
+}
+
+/// Index of the hotspot of the last run sharing the most occurrences with
+/// `members`, `None` when none shares any.
+pub fn matching<T: PartialEq>(members: &[T], previous: &[Vec<T>]) -> Option<usize> {
+  previous
+    .iter()
+    .enumerate()
+    .map(|(index, occurrences)| {
+      let shared = members
+        .iter()
+        .filter(|member| occurrences.contains(member))
+        .count();
+
+      (index, shared)
+    })
+    .filter(|&(_, shared)| shared > 0)
+    .max_by_key(|&(_, shared)| shared)
+    .map(|(index, _)| index)
+}
+
+/// A hotspot is emerging when it's new, or grew by `EMERGING_GROWTH` since the
+/// last run.
+pub fn emerging(previous_size: Option<usize>, size: usize) -> bool {
+  match previous_size {
+    None => true,
+    Some(previous_size) => size as f64 >= previous_size as f64 * (1.0 + EMERGING_GROWTH),
+  }
+}
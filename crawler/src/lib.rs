@@ -0,0 +1,8 @@
+//! Parsers of the INMET pages, the quality checks of their readings, the
+//! forecast providers and the clustering of the occurrences into hotspots.
+//! They don't depend on the browser or the database, so they live in the
+//! library and are tested against saved HTML, stub servers and fixed points.
+pub mod forecast;
+pub mod hotspots;
+pub mod parsers;
+pub mod quality;
@@ -1,13 +1,10 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use handlers::{
-  inmet_stations,
-  inmet_temperature_data,
-  ocurrence_inmet_ocorrence_data,
-  ocurrence_inmet_probability,
-};
+use std::process::ExitCode;
 
 pub mod client;
+mod daemon;
+mod error;
 mod handlers;
 mod scrapers;
 pub mod utils;
@@ -15,39 +12,75 @@ pub mod utils;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+  #[command(subcommand)]
+  command: Option<Command>,
   #[arg(short, long)]
-  script: String,
+  script: Option<String>,
   #[arg(short, long)]
   ocurrence_id: Option<String>,
   #[arg(short, long)]
   pathogenic_id: Option<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Keeps running the scripts of the config file on their schedules
+  Daemon {
+    #[arg(short, long, default_value = "crawler.toml")]
+    config: String,
+  },
+}
+
 #[tokio::main]
-async fn main() -> Result<(), fantoccini::error::CmdError> {
+async fn main() -> ExitCode {
   dotenv().ok();
 
   let args = Args::parse();
 
+  if let Some(Command::Daemon { config }) = args.command {
+    if let Err(e) = daemon::run(&config).await {
+      println!("failed: {}", e);
+      return ExitCode::FAILURE;
+    }
+
+    return ExitCode::SUCCESS;
+  }
+
   let client = client::make().await.expect("error on client build");
 
-  match args.script.as_str() {
-    "ocurrence-immet-climate-data" => {
-      ocurrence_inmet_ocorrence_data::handler(&client, args.ocurrence_id.unwrap())
-        .await
-        .unwrap();
-    }
-    "inmet-stations" => inmet_stations::handler(&client).await.unwrap(),
-    "ocurrence-probability" => {
-      ocurrence_inmet_probability::handler(&client, args.pathogenic_id.unwrap())
-        .await
-        .unwrap();
+  let result = handlers::run(
+    &client,
+    args.script.expect("--script is required").as_str(),
+    args.ocurrence_id,
+    args.pathogenic_id,
+  )
+  .await;
+
+  let _ = client.close().await;
+
+  // Some stations or ocurrences failing is expected, it's only an error when
+  // nothing could be done
+  match result {
+    Ok(stats) => {
+      println!(
+        "{}: {} succeeded, {} failed, {} rows inserted, {} rows updated, {} rows skipped",
+        stats.status(),
+        stats.succeeded,
+        stats.failed,
+        stats.rows_inserted,
+        stats.rows_updated,
+        stats.rows_skipped
+      );
+
+      if stats.status() == "failed" {
+        return ExitCode::FAILURE;
+      }
+
+      ExitCode::SUCCESS
     }
-    "inmet-temperature-data" => {
-      inmet_temperature_data::handler(&client).await.unwrap();
+    Err(e) => {
+      println!("failed: {}", utils::crawl_runs::error_chain(&e));
+      ExitCode::FAILURE
     }
-    _ => panic!("script not found"),
   }
-
-  client.close().await
 }
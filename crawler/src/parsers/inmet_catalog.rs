@@ -0,0 +1,84 @@
+use super::table::{data_rows, find_table, ParseError, Parsed, SkippedRow};
+use scraper::Html;
+
+/// A station of the automatic stations catalog
+/// (https://portal.inmet.gov.br/paginas/catalogoaut).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Station {
+  pub city: String,
+  pub uf: String,
+  pub situation: String,
+  pub latitude: String,
+  pub longitude: String,
+  pub altitude: Option<String>,
+  pub installation_date: Option<String>,
+  pub code: String,
+}
+
+const CODE: &[&str] = &["cd_estacao", "codigo"];
+
+pub fn parse_stations(html: &str) -> Result<Parsed<Station>, ParseError> {
+  let document = Html::parse_document(html);
+  let (table, header) = find_table(&document, CODE)?;
+
+  let city = header.require(&["dc_nome", "nome", "cidade"], "")?;
+  let uf = header.require(&["sg_estado", "uf"], "")?;
+  let situation = header.require(&["cd_situacao", "situacao"], "")?;
+  let latitude = header.require(&["vl_latitude", "latitude"], "")?;
+  let longitude = header.require(&["vl_longitude", "longitude"], "")?;
+  let altitude = header.find(&["vl_altitude", "altitude"], "");
+  let installation_date = header.find(&["dt_inicio_operacao", "inicio"], "");
+  let code = header.require(CODE, "")?;
+
+  let mut parsed = Parsed {
+    rows: Vec::new(),
+    skipped: Vec::new(),
+  };
+
+  for (index, cells) in data_rows(table).into_iter().enumerate() {
+    if cells.len() != header.len() {
+      parsed.skipped.push(SkippedRow {
+        row: index + 1,
+        reason: format!("expected {} cells, found {}", header.len(), cells.len()),
+      });
+      continue;
+    }
+
+    let required = [
+      ("code", code),
+      ("city", city),
+      ("latitude", latitude),
+      ("longitude", longitude),
+    ];
+
+    if let Some((name, _)) = required
+      .iter()
+      .find(|(_, column)| cells[*column].is_empty())
+    {
+      parsed.skipped.push(SkippedRow {
+        row: index + 1,
+        reason: format!("empty {}", name),
+      });
+      continue;
+    }
+
+    let optional = |column: Option<usize>| {
+      column
+        .map(|column| cells[column].clone())
+        .filter(|value| !value.is_empty())
+    };
+
+    parsed.rows.push(Station {
+      city: cells[city].clone(),
+      uf: cells[uf].clone(),
+      situation: cells[situation].clone(),
+      latitude: cells[latitude].clone(),
+      longitude: cells[longitude].clone(),
+      altitude: optional(altitude),
+      installation_date: optional(installation_date),
+      code: cells[code].clone(),
+    });
+  }
+
+  Ok(parsed)
+}
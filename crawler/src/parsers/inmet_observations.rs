@@ -0,0 +1,118 @@
+use super::table::{data_rows, find_table, number, Header, ParseError, Parsed, SkippedRow};
+use chrono::NaiveDateTime;
+use scraper::Html;
+
+/// Instant, maximum and minimum of the hour, `None` when INMET left the cell
+/// empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HourlyReading {
+  pub instant: Option<f64>,
+  pub max: Option<f64>,
+  pub min: Option<f64>,
+}
+
+/// A row of the station table (https://tempo.inmet.gov.br/TabelaEstacoes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct InmetStationData {
+  pub date: NaiveDateTime,
+  pub temperature: HourlyReading,
+  pub humidity: HourlyReading,
+  pub dew_point: HourlyReading,
+  pub pressure: HourlyReading,
+  pub wind_speed: Option<f64>,
+  pub wind_direction: Option<f64>,
+  pub wind_gust: Option<f64>,
+  pub radiation: Option<f64>,
+  pub precipitation: Option<f64>,
+}
+
+const DATE: &[&str] = &["data"];
+
+struct ReadingColumns {
+  instant: usize,
+  max: Option<usize>,
+  min: Option<usize>,
+}
+
+impl ReadingColumns {
+  fn find(header: &Header, names: &[&str]) -> Result<ReadingColumns, ParseError> {
+    Ok(ReadingColumns {
+      instant: header.require(names, "inst")?,
+      max: header.find(names, "max"),
+      min: header.find(names, "min"),
+    })
+  }
+
+  fn read(&self, cells: &[String]) -> HourlyReading {
+    HourlyReading {
+      instant: number(&cells[self.instant]),
+      max: self.max.and_then(|column| number(&cells[column])),
+      min: self.min.and_then(|column| number(&cells[column])),
+    }
+  }
+}
+
+pub fn parse_stations_climate_data(html: &str) -> Result<Parsed<InmetStationData>, ParseError> {
+  let document = Html::parse_document(html);
+  let (table, header) = find_table(&document, DATE)?;
+
+  let date = header.require(DATE, "")?;
+  let hour = header.require(&["hora"], "")?;
+  let temperature = ReadingColumns::find(&header, &["temperatura", "temp"])?;
+  let humidity = ReadingColumns::find(&header, &["umidade"])?;
+  let dew_point = ReadingColumns::find(&header, &["pto", "ponto de orvalho"]);
+  let pressure = ReadingColumns::find(&header, &["pressao"]);
+  let wind_speed = header.find(&["vento"], "vel");
+  let wind_direction = header.find(&["vento"], "dir");
+  let wind_gust = header.find(&["vento"], "raj");
+  let radiation = header.find(&["radiacao"], "");
+  let precipitation = header.find(&["chuva"], "");
+
+  let optional = |cells: &[String], column: Option<usize>| column.and_then(|c| number(&cells[c]));
+
+  let mut parsed = Parsed {
+    rows: Vec::new(),
+    skipped: Vec::new(),
+  };
+
+  for (index, cells) in data_rows(table).into_iter().enumerate() {
+    if cells.len() != header.len() {
+      parsed.skipped.push(SkippedRow {
+        row: index + 1,
+        reason: format!("expected {} cells, found {}", header.len(), cells.len()),
+      });
+      continue;
+    }
+
+    let datetime = format!("{} {}", cells[date], cells[hour]);
+
+    let Ok(datetime) = NaiveDateTime::parse_from_str(&datetime, "%d/%m/%Y %H%M") else {
+      parsed.skipped.push(SkippedRow {
+        row: index + 1,
+        reason: format!("invalid date {}", datetime),
+      });
+      continue;
+    };
+
+    parsed.rows.push(InmetStationData {
+      date: datetime,
+      temperature: temperature.read(&cells),
+      humidity: humidity.read(&cells),
+      dew_point: dew_point
+        .as_ref()
+        .map(|columns| columns.read(&cells))
+        .unwrap_or_default(),
+      pressure: pressure
+        .as_ref()
+        .map(|columns| columns.read(&cells))
+        .unwrap_or_default(),
+      wind_speed: optional(&cells, wind_speed),
+      wind_direction: optional(&cells, wind_direction),
+      wind_gust: optional(&cells, wind_gust),
+      radiation: optional(&cells, radiation),
+      precipitation: optional(&cells, precipitation),
+    });
+  }
+
+  Ok(parsed)
+}
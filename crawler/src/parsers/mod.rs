@@ -0,0 +1,3 @@
+pub mod inmet_catalog;
+pub mod inmet_observations;
+pub mod table;
@@ -0,0 +1,162 @@
+use scraper::{ElementRef, Html, Selector};
+
+/// A data row left out of the parsed result, `row` counts from 1 and only
+/// includes the rows with `td` cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRow {
+  pub row: usize,
+  pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct Parsed<T> {
+  pub rows: Vec<T>,
+  pub skipped: Vec<SkippedRow>,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ParseError {
+  #[error("no table with a {0} column")]
+  TableNotFound(String),
+  #[error("column {0} not found")]
+  ColumnNotFound(String),
+}
+
+/// Labels of the table columns. Tables with two header rows, like the
+/// observations one (`Temperatura (°C)` over `Inst. Máx. Mín.`), have the
+/// sub header saved apart from the group.
+pub struct Header {
+  columns: Vec<(String, String)>,
+}
+
+impl Header {
+  pub fn parse(table: ElementRef) -> Header {
+    let row_selector = Selector::parse("tr").unwrap();
+    let th_selector = Selector::parse("th").unwrap();
+    let td_selector = Selector::parse("td").unwrap();
+
+    let rows = table
+      .select(&row_selector)
+      .filter(|row| row.select(&td_selector).next().is_none())
+      .map(|row| row.select(&th_selector).collect::<Vec<_>>())
+      .filter(|cells| !cells.is_empty())
+      .collect::<Vec<_>>();
+
+    let mut columns = Vec::new();
+    let mut sub_headers = rows
+      .get(1)
+      .map(|cells| cells.iter().map(|cell| text(*cell)).collect::<Vec<_>>())
+      .unwrap_or_default()
+      .into_iter();
+
+    for cell in rows
+      .first()
+      .map(|cells| cells.as_slice())
+      .unwrap_or_default()
+    {
+      let group = normalize(&text(*cell));
+      let colspan = cell
+        .value()
+        .attr("colspan")
+        .and_then(|colspan| colspan.parse::<usize>().ok())
+        .unwrap_or(1);
+
+      if colspan == 1 {
+        columns.push((group, String::new()));
+        continue;
+      }
+
+      for _ in 0..colspan {
+        let sub = sub_headers.next().unwrap_or_default();
+        columns.push((group.clone(), normalize(&sub)));
+      }
+    }
+
+    Header { columns }
+  }
+
+  pub fn len(&self) -> usize {
+    self.columns.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.columns.is_empty()
+  }
+
+  /// Index of the first column whose group starts with one of `names` and
+  /// whose sub header starts with `sub` (empty matches any).
+  pub fn find(&self, names: &[&str], sub: &str) -> Option<usize> {
+    self.columns.iter().position(|(group, column_sub)| {
+      names.iter().any(|name| group.starts_with(name)) && column_sub.starts_with(sub)
+    })
+  }
+
+  pub fn require(&self, names: &[&str], sub: &str) -> Result<usize, ParseError> {
+    self.find(names, sub).ok_or_else(|| {
+      ParseError::ColumnNotFound(format!("{} {}", names.join("/"), sub).trim().to_string())
+    })
+  }
+}
+
+/// First table of the document with a column named after one of `names`.
+pub fn find_table<'a>(
+  document: &'a Html,
+  names: &[&str],
+) -> Result<(ElementRef<'a>, Header), ParseError> {
+  let table_selector = Selector::parse("table").unwrap();
+
+  document
+    .select(&table_selector)
+    .map(|table| (table, Header::parse(table)))
+    .find(|(_, header)| header.find(names, "").is_some())
+    .ok_or_else(|| ParseError::TableNotFound(names.join("/")))
+}
+
+/// Cell texts of each data row, header rows are left out.
+pub fn data_rows(table: ElementRef) -> Vec<Vec<String>> {
+  let row_selector = Selector::parse("tr").unwrap();
+  let td_selector = Selector::parse("td").unwrap();
+
+  table
+    .select(&row_selector)
+    .map(|row| row.select(&td_selector).map(text).collect::<Vec<_>>())
+    .filter(|cells| !cells.is_empty())
+    .collect()
+}
+
+/// INMET numbers use a decimal comma. Empty or invalid cells are a missing
+/// reading, not zero.
+pub fn number(cell: &str) -> Option<f64> {
+  let cell = cell.trim();
+
+  if cell.is_empty() {
+    return None;
+  }
+
+  cell.replace(',', ".").parse::<f64>().ok()
+}
+
+fn text(element: ElementRef) -> String {
+  element.text().collect::<String>().trim().to_string()
+}
+
+/// Lowercase without accents and repeated spaces, so `Pressão  (hPa)` matches
+/// `pressao`.
+fn normalize(label: &str) -> String {
+  label
+    .to_lowercase()
+    .chars()
+    .map(|c| match c {
+      'á' | 'à' | 'â' | 'ã' => 'a',
+      'é' | 'ê' => 'e',
+      'í' => 'i',
+      'ó' | 'ô' | 'õ' => 'o',
+      'ú' => 'u',
+      'ç' => 'c',
+      c => c,
+    })
+    .collect::<String>()
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+}
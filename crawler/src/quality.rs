@@ -0,0 +1,329 @@
+//! Quality control of the hourly readings before they are used on the risk
+//! calculations. Bad readings are dropped and flagged, never replaced by zero.
+use crate::parsers::inmet_observations::InmetStationData;
+use chrono::{Duration, NaiveDateTime};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+  /// No reading for the hour, the cell was empty or the row wasn't published
+  Missing,
+  /// Outside of the physical limits of the sensor
+  OutOfRange,
+  /// Same value for too many hours in a row
+  Stuck,
+  /// Missing hour filled from its neighbours
+  Interpolated,
+}
+
+/// Valid range of a measurement and after how many equal hours it's
+/// considered stuck.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+  pub min: f64,
+  pub max: f64,
+  pub stuck_hours: usize,
+}
+
+impl Limits {
+  pub const fn without_stuck_check(self) -> Limits {
+    Limits {
+      stuck_hours: usize::MAX,
+      ..self
+    }
+  }
+}
+
+pub const TEMPERATURE: Limits = Limits {
+  min: -10.0,
+  max: 50.0,
+  stuck_hours: 6,
+};
+
+/// Gaps up to this long are interpolated, longer ones lower the confidence.
+pub const MAX_INTERPOLATED_HOURS: usize = 3;
+
+/// Below this share of real readings the data isn't enough to assess risk.
+pub const MIN_CONFIDENCE: f64 = 0.5;
+
+pub const HUMIDITY: Limits = Limits {
+  min: 0.0,
+  max: 100.0,
+  stuck_hours: 6,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+  pub date: NaiveDateTime,
+  pub value: Option<f64>,
+  pub flag: Option<Flag>,
+}
+
+/// One measurement for each hour between the first and the last row.
+#[derive(Debug, Clone)]
+pub struct Series {
+  pub measurements: Vec<Measurement>,
+}
+
+impl Series {
+  pub fn new(
+    rows: &[InmetStationData],
+    read: fn(&InmetStationData) -> Option<f64>,
+    limits: Limits,
+  ) -> Series {
+    Series::from_values(rows.iter().map(|row| (row.date, read(row))), limits)
+  }
+
+  /// Same as [`Series::new`] from hourly `(date, value)` of any source.
+  pub fn from_values(
+    values: impl IntoIterator<Item = (NaiveDateTime, Option<f64>)>,
+    limits: Limits,
+  ) -> Series {
+    let values = values.into_iter().collect::<HashMap<_, _>>();
+
+    let (Some(start), Some(end)) = (values.keys().min().copied(), values.keys().max().copied())
+    else {
+      return Series {
+        measurements: Vec::new(),
+      };
+    };
+
+    let mut measurements = Vec::new();
+    let mut date = start;
+
+    while date <= end {
+      let measurement = match values.get(&date).copied().flatten() {
+        Some(value) if value < limits.min || value > limits.max => Measurement {
+          date,
+          value: None,
+          flag: Some(Flag::OutOfRange),
+        },
+        Some(value) => Measurement {
+          date,
+          value: Some(value),
+          flag: None,
+        },
+        None => Measurement {
+          date,
+          value: None,
+          flag: Some(Flag::Missing),
+        },
+      };
+
+      measurements.push(measurement);
+      date += Duration::hours(1);
+    }
+
+    let mut series = Series { measurements };
+    series.flag_stuck(limits);
+    series
+  }
+
+  /// Drops runs of `stuck_hours` or more equal values. Values on the limits
+  /// are left alone, 100% humidity through a rainy night is a real reading.
+  fn flag_stuck(&mut self, limits: Limits) {
+    let mut start = 0;
+
+    while start < self.measurements.len() {
+      let Some(value) = self.measurements[start].value else {
+        start += 1;
+        continue;
+      };
+
+      let length = self.measurements[start..]
+        .iter()
+        .take_while(|measurement| measurement.value == Some(value))
+        .count();
+
+      if length >= limits.stuck_hours && value > limits.min && value < limits.max {
+        for measurement in &mut self.measurements[start..start + length] {
+          measurement.value = None;
+          measurement.flag = Some(Flag::Stuck);
+        }
+      }
+
+      start += length;
+    }
+  }
+
+  /// Fills gaps of up to `max_hours` linearly between the readings around
+  /// them. Longer gaps and gaps on the edges are kept missing.
+  pub fn interpolate(&mut self, max_hours: usize) {
+    let mut index = 0;
+
+    while index < self.measurements.len() {
+      if self.measurements[index].value.is_some() {
+        index += 1;
+        continue;
+      }
+
+      let length = self.measurements[index..]
+        .iter()
+        .take_while(|measurement| measurement.value.is_none())
+        .count();
+
+      let before = index
+        .checked_sub(1)
+        .and_then(|i| self.measurements[i].value);
+      let after = self
+        .measurements
+        .get(index + length)
+        .and_then(|measurement| measurement.value);
+
+      if let (Some(before), Some(after)) = (before, after) {
+        if length <= max_hours {
+          let step = (after - before) / (length + 1) as f64;
+
+          for (offset, measurement) in self.measurements[index..index + length]
+            .iter_mut()
+            .enumerate()
+          {
+            measurement.value = Some(before + step * (offset + 1) as f64);
+            measurement.flag = Some(Flag::Interpolated);
+          }
+        }
+      }
+
+      index += length;
+    }
+  }
+
+  pub fn count(&self, flag: Flag) -> usize {
+    self
+      .measurements
+      .iter()
+      .filter(|measurement| measurement.flag == Some(flag))
+      .count()
+  }
+
+  pub fn value_at(&self, date: NaiveDateTime) -> Option<f64> {
+    self
+      .measurements
+      .iter()
+      .find(|measurement| measurement.date == date)
+      .and_then(|measurement| measurement.value)
+  }
+
+  /// Share of the hours with a real reading, interpolated hours don't count.
+  pub fn confidence(&self) -> f64 {
+    if self.measurements.is_empty() {
+      return 0.0;
+    }
+
+    let observed = self
+      .measurements
+      .iter()
+      .filter(|measurement| measurement.flag.is_none())
+      .count();
+
+    observed as f64 / self.measurements.len() as f64
+  }
+
+  /// `3 missing, 1 out of range, 0 stuck, 2 interpolated`
+  pub fn summary(&self) -> String {
+    format!(
+      "{} missing, {} out of range, {} stuck, {} interpolated",
+      self.count(Flag::Missing),
+      self.count(Flag::OutOfRange),
+      self.count(Flag::Stuck),
+      self.count(Flag::Interpolated)
+    )
+  }
+}
+
+/// Temperature and humidity of the same rows, the inputs of the risk
+/// calculations.
+#[derive(Debug, Clone)]
+pub struct Climate {
+  pub temperature: Series,
+  pub humidity: Series,
+}
+
+impl Climate {
+  pub fn new(rows: &[InmetStationData], max_interpolated_hours: usize) -> Climate {
+    Climate::from_hours(
+      rows
+        .iter()
+        .map(|row| (row.date, row.temperature.instant, row.humidity.instant)),
+      max_interpolated_hours,
+    )
+  }
+
+  /// From hourly `(date, temperature, humidity)` of any station.
+  pub fn from_hours(
+    hours: impl IntoIterator<Item = (NaiveDateTime, Option<f64>, Option<f64>)>,
+    max_interpolated_hours: usize,
+  ) -> Climate {
+    Climate::build(hours, max_interpolated_hours, TEMPERATURE, HUMIDITY)
+  }
+
+  /// From a weather forecast. Model output often repeats values for hours, so
+  /// there are no stuck sensor checks.
+  pub fn from_forecast(
+    hours: impl IntoIterator<Item = (NaiveDateTime, Option<f64>, Option<f64>)>,
+    max_interpolated_hours: usize,
+  ) -> Climate {
+    Climate::build(
+      hours,
+      max_interpolated_hours,
+      TEMPERATURE.without_stuck_check(),
+      HUMIDITY.without_stuck_check(),
+    )
+  }
+
+  fn build(
+    hours: impl IntoIterator<Item = (NaiveDateTime, Option<f64>, Option<f64>)>,
+    max_interpolated_hours: usize,
+    temperature_limits: Limits,
+    humidity_limits: Limits,
+  ) -> Climate {
+    let hours = hours.into_iter().collect::<Vec<_>>();
+
+    let mut temperature = Series::from_values(
+      hours
+        .iter()
+        .map(|(date, temperature, _)| (*date, *temperature)),
+      temperature_limits,
+    );
+    let mut humidity = Series::from_values(
+      hours.iter().map(|(date, _, humidity)| (*date, *humidity)),
+      humidity_limits,
+    );
+
+    temperature.interpolate(max_interpolated_hours);
+    humidity.interpolate(max_interpolated_hours);
+
+    Climate {
+      temperature,
+      humidity,
+    }
+  }
+
+  pub fn confidence(&self) -> f64 {
+    self
+      .temperature
+      .confidence()
+      .min(self.humidity.confidence())
+  }
+
+  /// Hours with both readings, real or interpolated.
+  pub fn hours(&self) -> impl Iterator<Item = (NaiveDateTime, f64, f64)> + '_ {
+    self
+      .temperature
+      .measurements
+      .iter()
+      .zip(&self.humidity.measurements)
+      .filter_map(|(temperature, humidity)| {
+        Some((temperature.date, temperature.value?, humidity.value?))
+      })
+  }
+
+  pub fn summary(&self) -> String {
+    format!(
+      "temperature: {}; humidity: {}; confidence {:.0}%",
+      self.temperature.summary(),
+      self.humidity.summary(),
+      self.confidence() * 100.0
+    )
+  }
+}
@@ -1 +1,2 @@
 pub mod inmet_station_data;
+pub mod station_fetcher;
@@ -0,0 +1,347 @@
+use crate::{
+  client,
+  error::CrawlerError,
+  scrapers::inmet_station_data::get_station_data_with_retry,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use crawler::parsers::{inmet_observations::InmetStationData, table::Parsed};
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{Arc, LazyLock, Mutex},
+  time::{Duration, Instant},
+};
+use tokio::{
+  sync::{OnceCell, Semaphore, SemaphorePermit},
+  task::JoinSet,
+};
+
+pub(crate) type StationData = Arc<Parsed<InmetStationData>>;
+
+struct CacheEntry {
+  data: Arc<OnceCell<StationData>>,
+  created: Instant,
+}
+
+/// Tables already fetched by this process, shared by every run, so the daemon
+/// schedules reuse each other's fetches too.
+static CACHE: LazyLock<StationCache> = LazyLock::new(|| StationCache::new(cache_ttl()));
+
+/// Fetched tables by station and start date, kept for `ttl`.
+struct StationCache {
+  entries: Mutex<HashMap<(String, NaiveDate), CacheEntry>>,
+  ttl: Duration,
+}
+
+impl StationCache {
+  fn new(ttl: Duration) -> StationCache {
+    StationCache {
+      entries: Default::default(),
+      ttl,
+    }
+  }
+
+  /// The table of `key` as of `now`, fetched with `fetch` when it isn't
+  /// cached. Callers asking while it's being fetched wait for the same fetch.
+  async fn get<F, Fut>(
+    &self,
+    key: (String, NaiveDate),
+    now: Instant,
+    fetch: F,
+  ) -> Result<StationData, CrawlerError>
+  where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<StationData, CrawlerError>>,
+  {
+    let data = {
+      let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+      // The dates in the keys change every day, expired tables are dropped
+      // here or the daemon would keep them all
+      entries.retain(|_, entry| now.saturating_duration_since(entry.created) < self.ttl);
+
+      entries
+        .entry(key)
+        .or_insert_with(|| CacheEntry {
+          data: Arc::new(OnceCell::new()),
+          created: now,
+        })
+        .data
+        .clone()
+    };
+
+    // A failed fetch leaves the cell empty, so the next caller tries again
+    data.get_or_try_init(fetch).await.cloned()
+  }
+}
+
+/// WebDriver sessions opened at the same time by a run
+fn concurrency() -> usize {
+  std::env::var("CRAWLER_CONCURRENCY")
+    .ok()
+    .and_then(|concurrency| concurrency.parse::<usize>().ok())
+    .unwrap_or(4)
+    .max(1)
+}
+
+/// Seconds a fetched table is reused, INMET publishes a new row every hour
+fn cache_ttl() -> Duration {
+  let seconds = std::env::var("CRAWLER_CACHE_TTL")
+    .ok()
+    .and_then(|ttl| ttl.parse::<u64>().ok())
+    .unwrap_or(1800);
+
+  Duration::from_secs(seconds)
+}
+
+/// A session taken from the pool, given back when dropped, even if the fetch
+/// panics or its task is aborted
+struct Session<'a> {
+  sessions: &'a Mutex<Vec<fantoccini::Client>>,
+  client: Option<fantoccini::Client>,
+  _permit: SemaphorePermit<'a>,
+}
+
+impl Drop for Session<'_> {
+  fn drop(&mut self) {
+    if let Some(client) = self.client.take() {
+      self
+        .sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(client);
+    }
+  }
+}
+
+/// Fetches the INMET station tables over a pool of WebDriver sessions.
+/// Requests for the same station and date are made only once, callers asking
+/// while it's being fetched wait for the same result.
+pub(crate) struct StationFetcher {
+  sessions: Mutex<Vec<fantoccini::Client>>,
+  available: Semaphore,
+  /// Sessions opened by the fetcher, the run's client is closed by the caller
+  opened: Vec<fantoccini::Client>,
+  attempts: u32,
+}
+
+impl StationFetcher {
+  /// Uses `client` and up to `CRAWLER_CONCURRENCY - 1` new sessions.
+  pub(crate) async fn new(client: &fantoccini::Client, attempts: u32) -> Arc<StationFetcher> {
+    let mut sessions = vec![client.clone()];
+    let mut opened = Vec::new();
+
+    for _ in 1..concurrency() {
+      match client::make().await {
+        Ok(session) => {
+          sessions.push(session.clone());
+          opened.push(session);
+        }
+        Err(e) => {
+          println!(
+            "Error opening a WebDriver session, fetching with {}: {}",
+            sessions.len(),
+            e
+          );
+          break;
+        }
+      }
+    }
+
+    Arc::new(StationFetcher {
+      available: Semaphore::new(sessions.len()),
+      sessions: Mutex::new(sessions),
+      opened,
+      attempts,
+    })
+  }
+
+  pub(crate) async fn get(
+    &self,
+    station: &str,
+    date: DateTime<Utc>,
+  ) -> Result<StationData, CrawlerError> {
+    CACHE
+      .get(
+        (station.to_string(), date.date_naive()),
+        Instant::now(),
+        || self.fetch(station, date),
+      )
+      .await
+  }
+
+  async fn session(&self) -> Session<'_> {
+    let permit = self.available.acquire().await.expect("fetcher closed");
+    let client = self
+      .sessions
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .pop()
+      .expect("a session for each permit");
+
+    Session {
+      sessions: &self.sessions,
+      client: Some(client),
+      _permit: permit,
+    }
+  }
+
+  async fn fetch(&self, station: &str, date: DateTime<Utc>) -> Result<StationData, CrawlerError> {
+    let session = self.session().await;
+    let client = session.client.as_ref().expect("a session until dropped");
+
+    get_station_data_with_retry(client, station.to_string(), date, self.attempts)
+      .await
+      .map(Arc::new)
+  }
+
+  /// Fetches every `(station, date)` with the pool's concurrency, results are
+  /// in the same order as `requests`.
+  pub(crate) async fn fetch_all(
+    self: &Arc<Self>,
+    requests: Vec<(String, DateTime<Utc>)>,
+  ) -> Vec<Result<StationData, CrawlerError>> {
+    let started = Instant::now();
+    let total = requests.len();
+    let mut running = JoinSet::new();
+
+    for (index, (station, date)) in requests.into_iter().enumerate() {
+      let fetcher = self.clone();
+
+      running.spawn(async move { (index, fetcher.get(&station, date).await) });
+    }
+
+    let mut results = (0..total).map(|_| None).collect::<Vec<_>>();
+
+    while let Some(result) = running.join_next().await {
+      match result {
+        Ok((index, result)) => results[index] = Some(result),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // Its slot stays empty and is reported below
+        Err(_) => {}
+      }
+    }
+
+    println!(
+      "Fetched {} stations in {:.1}s",
+      total,
+      started.elapsed().as_secs_f64()
+    );
+
+    results
+      .into_iter()
+      .map(|result| {
+        result.unwrap_or_else(|| Err(CrawlerError::Cancelled("station fetch".to_string())))
+      })
+      .collect()
+  }
+
+  /// Closes the sessions opened by the fetcher.
+  pub(crate) async fn close(&self) {
+    for session in &self.opened {
+      let _ = session.clone().close().await;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  fn key() -> (String, NaiveDate) {
+    (
+      "A801".to_string(),
+      NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+    )
+  }
+
+  async fn fetch(fetches: &AtomicUsize) -> Result<StationData, CrawlerError> {
+    fetches.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    Ok(Arc::new(Parsed {
+      rows: vec![],
+      skipped: vec![],
+    }))
+  }
+
+  #[tokio::test]
+  async fn cached_tables_are_reused_until_they_expire() {
+    let cache = StationCache::new(Duration::from_secs(60));
+    let fetches = AtomicUsize::new(0);
+    let start = Instant::now();
+
+    let first = cache.get(key(), start, || fetch(&fetches)).await.unwrap();
+    let cached = cache
+      .get(key(), start + Duration::from_secs(59), || fetch(&fetches))
+      .await
+      .unwrap();
+
+    assert!(Arc::ptr_eq(&first, &cached));
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    let expired = cache
+      .get(key(), start + Duration::from_secs(60), || fetch(&fetches))
+      .await
+      .unwrap();
+
+    assert!(!Arc::ptr_eq(&first, &expired));
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+  }
+
+  #[tokio::test]
+  async fn expired_tables_are_dropped() {
+    let cache = StationCache::new(Duration::from_secs(60));
+    let fetches = AtomicUsize::new(0);
+    let start = Instant::now();
+    let other = ("A802".to_string(), key().1);
+
+    cache.get(key(), start, || fetch(&fetches)).await.unwrap();
+    cache
+      .get(other, start + Duration::from_secs(61), || fetch(&fetches))
+      .await
+      .unwrap();
+
+    assert_eq!(cache.entries.lock().unwrap().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn concurrent_callers_share_one_fetch() {
+    let cache = StationCache::new(Duration::from_secs(60));
+    let fetches = AtomicUsize::new(0);
+    let now = Instant::now();
+
+    let results = tokio::join!(
+      cache.get(key(), now, || fetch(&fetches)),
+      cache.get(key(), now, || fetch(&fetches)),
+      cache.get(key(), now, || fetch(&fetches)),
+      cache.get(key(), now, || fetch(&fetches)),
+    );
+
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    let first = results.0.unwrap();
+
+    for other in [results.1, results.2, results.3] {
+      assert!(Arc::ptr_eq(&first, &other.unwrap()));
+    }
+  }
+
+  #[tokio::test]
+  async fn failed_fetches_are_tried_again() {
+    let cache = StationCache::new(Duration::from_secs(60));
+    let fetches = AtomicUsize::new(0);
+    let now = Instant::now();
+
+    let failed = cache
+      .get(key(), now, || async {
+        Err(CrawlerError::Parse("no table".to_string()))
+      })
+      .await;
+
+    assert!(failed.is_err());
+    assert!(cache.get(key(), now, || fetch(&fetches)).await.is_ok());
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+  }
+}
@@ -0,0 +1,112 @@
+use crate::{
+  error::CrawlerError,
+  utils::{database::DataBase, plantation_risks::Risk},
+};
+use uuid::Uuid;
+
+/// How an organization wants its growers alerted of a pathogen, or of every
+/// pathogen without a policy of its own when `pathogenic_id` is empty.
+#[derive(Debug, Clone)]
+pub(crate) struct Policy {
+  pub(crate) organization_id: Uuid,
+  pub(crate) pathogenic_id: Option<i64>,
+  pub(crate) min_level: String,
+  pub(crate) notify_agronomists: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Member {
+  pub(crate) organization_id: Uuid,
+  pub(crate) user_id: Uuid,
+  pub(crate) role: String,
+}
+
+/// Organization policies layered over the alerts a grower gets anyway: they
+/// can alert on lower risks and copy the agronomists, never silence a grower.
+#[derive(Debug, Default)]
+pub(crate) struct AlertPolicies {
+  policies: Vec<Policy>,
+  members: Vec<Member>,
+}
+
+impl AlertPolicies {
+  pub(crate) async fn load(db: &DataBase) -> Result<AlertPolicies, CrawlerError> {
+    let policies = sqlx::query_as!(
+      Policy,
+      "SELECT a.organization_id, a.pathogenic_id, a.min_level, a.notify_agronomists
+        FROM organization_alert_policies a
+              JOIN organizations o ON o.id = a.organization_id
+        WHERE o.delete_at IS NULL"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let members = sqlx::query_as!(
+      Member,
+      "SELECT m.organization_id, m.user_id, m.role
+        FROM organization_members m
+              JOIN organizations o ON o.id = m.organization_id
+        WHERE o.delete_at IS NULL"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    Ok(AlertPolicies { policies, members })
+  }
+
+  /// Policy of each organization of the grower on the pathogen, their default
+  /// one when there's none for it
+  fn applying(&self, user_id: Uuid, pathogenic_id: i64) -> Vec<&Policy> {
+    self
+      .members
+      .iter()
+      .filter(|member| member.user_id == user_id)
+      .filter_map(|member| {
+        let policies = self
+          .policies
+          .iter()
+          .filter(|policy| policy.organization_id == member.organization_id);
+
+        policies
+          .clone()
+          .find(|policy| policy.pathogenic_id == Some(pathogenic_id))
+          .or_else(|| {
+            policies
+              .clone()
+              .find(|policy| policy.pathogenic_id.is_none())
+          })
+      })
+      .collect()
+  }
+
+  /// Whether the grower is alerted of the risk on their plantation
+  pub(crate) fn alerts_grower(&self, user_id: Uuid, pathogenic_id: i64, risk: &Risk) -> bool {
+    risk.alert()
+      || self
+        .applying(user_id, pathogenic_id)
+        .iter()
+        .any(|policy| risk.alert_at(&policy.min_level))
+  }
+
+  /// Agronomists copied on the alert of the risk on the grower's plantation
+  pub(crate) fn agronomists(&self, user_id: Uuid, pathogenic_id: i64, risk: &Risk) -> Vec<Uuid> {
+    let mut agronomists = self
+      .applying(user_id, pathogenic_id)
+      .into_iter()
+      .filter(|policy| policy.notify_agronomists && risk.alert_at(&policy.min_level))
+      .flat_map(|policy| {
+        self.members.iter().filter(|member| {
+          member.organization_id == policy.organization_id
+            && member.role == "agronomist"
+            && member.user_id != user_id
+        })
+      })
+      .map(|member| member.user_id)
+      .collect::<Vec<_>>();
+
+    agronomists.sort();
+    agronomists.dedup();
+
+    agronomists
+  }
+}
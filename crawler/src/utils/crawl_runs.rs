@@ -0,0 +1,113 @@
+use crate::{error::CrawlerError, utils::database::DataBase};
+use chrono::NaiveDateTime;
+
+/// Summary of a script execution, saved on `crawl_runs`. Scripts work on a
+/// list of items (stations, ocurrences...), one failing doesn't stop the others.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RunStats {
+  pub(crate) rows_inserted: i64,
+  pub(crate) rows_updated: i64,
+  /// Rows of the INMET tables left out by the parsers
+  pub(crate) rows_skipped: i64,
+  pub(crate) succeeded: i64,
+  pub(crate) failed: i64,
+  pub(crate) errors: Vec<String>,
+}
+
+impl RunStats {
+  pub(crate) fn success(&mut self) {
+    self.succeeded += 1;
+  }
+
+  pub(crate) fn failure(&mut self, item: &str, err: &CrawlerError) {
+    println!("Error on {}: {}", item, error_chain(err));
+
+    self.failed += 1;
+    self.errors.push(format!("{}: {}", item, error_chain(err)));
+  }
+
+  /// `failed` only when nothing worked, some items failing is `partial`.
+  pub(crate) fn status(&self) -> &'static str {
+    match (self.succeeded, self.failed) {
+      (_, 0) => "success",
+      (0, _) => "failed",
+      _ => "partial",
+    }
+  }
+
+  pub(crate) fn error(&self) -> Option<String> {
+    if self.errors.is_empty() {
+      return None;
+    }
+
+    Some(self.errors.join("\n"))
+  }
+}
+
+pub(crate) async fn start(db: &DataBase, script: &str, args: &str) -> i64 {
+  sqlx::query_scalar!(
+    "INSERT INTO crawl_runs (script, args) VALUES ($1, NULLIF($2, '')) RETURNING id",
+    script,
+    args
+  )
+  .fetch_one(&db.pool)
+  .await
+  .map_err(DataBase::database_error)
+  .unwrap()
+}
+
+pub(crate) async fn finish(
+  db: &DataBase,
+  id: i64,
+  status: &str,
+  stats: &RunStats,
+  error: Option<String>,
+) {
+  let _ = sqlx::query!(
+    "UPDATE crawl_runs
+    SET status = $2, rows_inserted = $3, rows_updated = $4, rows_skipped = $5, error = $6, end_date = NOW()
+    WHERE id = $1",
+    id,
+    status,
+    stats.rows_inserted,
+    stats.rows_updated,
+    stats.rows_skipped,
+    error
+  )
+  .execute(&db.pool)
+  .await
+  .map_err(DataBase::database_error);
+}
+
+/// `error: cause: cause...`, following the `source()` of each error.
+pub(crate) fn error_chain(err: &(dyn std::error::Error + 'static)) -> String {
+  let mut chain = err.to_string();
+  let mut source = err.source();
+
+  while let Some(err) = source {
+    chain.push_str(&format!(": {}", err));
+    source = err.source();
+  }
+
+  chain
+}
+
+/// Keeps track of when a station was last read and of its newest observation,
+/// the app lists the stations that stopped returning data.
+pub(crate) async fn station_fetched(
+  db: &DataBase,
+  inmet_code: &str,
+  last_data_date: Option<NaiveDateTime>,
+) {
+  let _ = sqlx::query!(
+    "UPDATE stations
+    SET last_fetch_date = NOW(),
+        last_data_date  = GREATEST(last_data_date, $2)
+    WHERE inmet_code = $1",
+    inmet_code,
+    last_data_date
+  )
+  .execute(&db.pool)
+  .await
+  .map_err(DataBase::database_error);
+}
@@ -1,3 +1,4 @@
+use crate::error::CrawlerError;
 use serde::{Deserialize, Serialize};
 use yup_oauth2::ServiceAccountAuthenticator;
 
@@ -11,19 +12,19 @@ struct PayloadBody {
   uid: String,
 }
 
-pub(crate) async fn get_firebase_jwt() -> String {
+pub(crate) async fn get_firebase_jwt() -> Result<String, CrawlerError> {
   let secret = yup_oauth2::read_service_account_key("assets/firebase-config.json")
     .await
-    .expect("assets/firebase-config.json");
+    .map_err(|e| CrawlerError::Notification(format!("assets/firebase-config.json: {}", e)))?;
 
   let auth = ServiceAccountAuthenticator::builder(secret)
     .build()
     .await
-    .unwrap();
+    .map_err(|e| CrawlerError::Notification(e.to_string()))?;
 
   let scopes = &["https://www.googleapis.com/auth/firebase.messaging"];
   match auth.token(scopes).await {
-    Ok(access_token) => access_token.token().unwrap_or("").to_string(),
-    Err(_e) => "".to_string(),
+    Ok(access_token) => Ok(access_token.token().unwrap_or("").to_string()),
+    Err(e) => Err(CrawlerError::Notification(e.to_string())),
   }
 }
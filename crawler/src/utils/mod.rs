@@ -1,2 +1,7 @@
+pub mod alert_policies;
+pub mod crawl_runs;
 pub mod database;
-pub mod google_jwt;
\ No newline at end of file
+pub mod google_jwt;
+pub mod notification;
+pub mod plantation_risks;
+pub mod retry;
\ No newline at end of file
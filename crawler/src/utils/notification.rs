@@ -0,0 +1,103 @@
+use crate::{
+  error::CrawlerError,
+  utils::{crawl_runs::RunStats, database::DataBase, google_jwt, retry},
+};
+use reqwest::Client;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const SEND_ATTEMPTS: u32 = 3;
+
+/// Sends a push notification through Firebase Cloud Messaging.
+pub(crate) async fn send(
+  google_jwt_token: &str,
+  token: &str,
+  title: &str,
+  message: &str,
+) -> Result<(), CrawlerError> {
+  let body = serde_json::json!({
+    "message": {
+      "token": token,
+      "notification": {
+        "title": title,
+        "body": message,
+      },
+    }
+  });
+
+  retry::with_backoff("notification", SEND_ATTEMPTS, || async {
+    Client::new()
+      .post("https://fcm.googleapis.com/v1/projects/cropi-399723/messages:send")
+      .header("Content-Type", "application/json")
+      .header("Authorization", format!("Bearer {}", google_jwt_token))
+      .body(body.to_string())
+      .send()
+      .await
+      .and_then(|response| response.error_for_status())
+      .map_err(|e| CrawlerError::Notification(e.to_string()))?;
+
+    Ok(())
+  })
+  .await
+}
+
+/// Saves each user's message on their notifications and pushes it to the ones
+/// with a device registered. The messages are saved before anything is
+/// pushed, so they aren't lost when Firebase can't be reached.
+pub(crate) async fn notify_users(
+  db: &DataBase,
+  title: &str,
+  messages: HashMap<Uuid, String>,
+  stats: &mut RunStats,
+) -> Result<(), CrawlerError> {
+  if messages.is_empty() {
+    return Ok(());
+  }
+
+  for (user_id, message) in &messages {
+    sqlx::query!(
+      "INSERT INTO user_notifications (user_id, message) VALUES ($1, $2)",
+      user_id,
+      message
+    )
+    .execute(&db.pool)
+    .await?;
+
+    stats.rows_inserted += 1;
+  }
+
+  let notification_tokens: HashMap<Uuid, String> = sqlx::query!(
+    r#"SELECT id, notification_token AS "notification_token!"
+      FROM users
+      WHERE id = ANY($1)
+        AND notification_token IS NOT NULL"#,
+    &messages.keys().copied().collect::<Vec<_>>()[..]
+  )
+  .fetch_all(&db.pool)
+  .await?
+  .into_iter()
+  .map(|user| (user.id, user.notification_token))
+  .collect();
+
+  if notification_tokens.is_empty() {
+    return Ok(());
+  }
+
+  let google_jwt_token = google_jwt::get_firebase_jwt().await?;
+
+  for (user_id, notification_token) in notification_tokens {
+    // The notification is already saved, a failed push shouldn't fail the run
+    if let Err(e) = send(
+      &google_jwt_token,
+      &notification_token,
+      title,
+      &messages[&user_id],
+    )
+    .await
+    {
+      println!("Error sending notification to user {}: {}", user_id, e);
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,518 @@
+use crate::{error::CrawlerError, utils::database::DataBase};
+use chrono::{NaiveDate, NaiveDateTime};
+use crawler::quality::Climate;
+use favourable::Favourable;
+use uuid::Uuid;
+
+/// Hours of favourable temperature and humidity over a day above which a
+/// plantation is at risk.
+const RISK_HOURS: i32 = 12;
+
+/// Share of favourable hours for a `medium` risk, not enough for an alert
+const MEDIUM_SCORE: f64 = 0.25;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub(crate) struct PathogenicCulture {
+  pub(crate) id: i64,
+  pub(crate) name: String,
+  pub(crate) culture_id: i64,
+  pub(crate) culture_name: String,
+  pub(crate) favourable_min_temperature: f64,
+  pub(crate) favourable_max_temperature: f64,
+  pub(crate) favourable_min_humidity: f64,
+}
+
+impl PathogenicCulture {
+  pub(crate) fn favourable(&self) -> Favourable {
+    Favourable {
+      min_temperature: self.favourable_min_temperature,
+      max_temperature: self.favourable_max_temperature,
+      min_humidity: self.favourable_min_humidity,
+    }
+  }
+}
+
+/// Plantation with an active INMET station
+#[derive(Debug, Clone)]
+pub(crate) struct Plantation {
+  pub(crate) id: Uuid,
+  /// First owner of the farm holding the plantation, who's alerted of it, or
+  /// its creator when it has no farm
+  pub(crate) owner_id: Uuid,
+  pub(crate) alias: Option<String>,
+  pub(crate) culture_id: i64,
+  pub(crate) station_id: i64,
+  pub(crate) inmet_code: String,
+  pub(crate) station_latitude: Option<f64>,
+  pub(crate) station_longitude: Option<f64>,
+  pub(crate) owner_name: String,
+}
+
+/// Days a fungicide or treatment logged on the plantation protects it against
+/// a pathogen, or every one when `pathogenic_id` is empty.
+#[derive(Debug, Clone)]
+pub(crate) struct Protection {
+  pub(crate) activity_id: Uuid,
+  pub(crate) plantation_id: Uuid,
+  pub(crate) pathogenic_id: Option<i64>,
+  pub(crate) start: NaiveDate,
+  pub(crate) end: NaiveDate,
+}
+
+/// Observed risks come from the station readings, predicted ones from a
+/// weather forecast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RiskKind {
+  Observed,
+  Predicted,
+}
+
+impl RiskKind {
+  fn as_str(&self) -> &'static str {
+    match self {
+      RiskKind::Observed => "observed",
+      RiskKind::Predicted => "predicted",
+    }
+  }
+}
+
+/// Risk of a pathogen over a day of hourly readings, saved with the inputs
+/// that drove it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Risk {
+  pub(crate) hours: i32,
+  pub(crate) temperature_hours: i32,
+  pub(crate) humidity_hours: i32,
+  pub(crate) favourable_hours: i32,
+  pub(crate) mean_temperature: Option<f64>,
+  pub(crate) mean_humidity: Option<f64>,
+  /// Activity protecting the plantation that day
+  pub(crate) protected_by: Option<Uuid>,
+}
+
+impl Risk {
+  pub(crate) fn new(climate: &Climate, pathogenic: &PathogenicCulture) -> Risk {
+    let mut risk = Risk {
+      hours: 0,
+      temperature_hours: 0,
+      humidity_hours: 0,
+      favourable_hours: 0,
+      mean_temperature: None,
+      mean_humidity: None,
+      protected_by: None,
+    };
+
+    let mut temperature_sum = 0.0;
+    let mut humidity_sum = 0.0;
+
+    let favourable = pathogenic.favourable();
+
+    for (_, temperature, humidity) in climate.hours() {
+      let favourable_temperature = favourable.temperature(temperature);
+      let favourable_humidity = favourable.humidity(humidity);
+
+      risk.hours += 1;
+      risk.temperature_hours += favourable_temperature as i32;
+      risk.humidity_hours += favourable_humidity as i32;
+      risk.favourable_hours += (favourable_temperature && favourable_humidity) as i32;
+
+      temperature_sum += temperature;
+      humidity_sum += humidity;
+    }
+
+    if risk.hours > 0 {
+      risk.mean_temperature = Some(temperature_sum / risk.hours as f64);
+      risk.mean_humidity = Some(humidity_sum / risk.hours as f64);
+    }
+
+    risk
+  }
+
+  pub(crate) fn at_risk(&self) -> bool {
+    self.temperature_hours > RISK_HOURS && self.humidity_hours > RISK_HOURS
+  }
+
+  /// Share of the hours favourable to the pathogen
+  pub(crate) fn score(&self) -> f64 {
+    if self.hours == 0 {
+      return 0.0;
+    }
+
+    self.favourable_hours as f64 / self.hours as f64
+  }
+
+  /// The same risk on a plantation protected by `activity_id`
+  pub(crate) fn protected_by(self, activity_id: Option<Uuid>) -> Risk {
+    Risk {
+      protected_by: activity_id,
+      ..self
+    }
+  }
+
+  /// A protected plantation is still `at_risk`, it alerts only if it isn't
+  pub(crate) fn alert(&self) -> bool {
+    self.at_risk() && self.protected_by.is_none()
+  }
+
+  /// Like `alert`, also on a `medium` risk when that's the `min_level`
+  pub(crate) fn alert_at(&self, min_level: &str) -> bool {
+    match min_level {
+      "medium" => self.protected_by.is_none() && self.level() != "low",
+      _ => self.alert(),
+    }
+  }
+
+  /// `high` is what triggers the alerts, or `medium` on organizations asking
+  /// for it. A protected plantation is one level lower
+  pub(crate) fn level(&self) -> &'static str {
+    match (
+      self.at_risk(),
+      self.score() >= MEDIUM_SCORE,
+      self.protected_by,
+    ) {
+      (true, _, None) => "high",
+      (true, _, Some(_)) | (false, true, None) => "medium",
+      _ => "low",
+    }
+  }
+}
+
+/// Pathogen-culture pairs, of every pathogen or only of `pathogenic_id`.
+pub(crate) async fn pathogenics(
+  db: &DataBase,
+  pathogenic_id: Option<i64>,
+) -> Result<Vec<PathogenicCulture>, CrawlerError> {
+  Ok(
+    sqlx::query_as!(
+      PathogenicCulture,
+      "SELECT p.id,
+            p.name,
+            c.id              AS culture_id,
+            c.name            AS culture_name,
+            p.favourable_min_temperature,
+            p.favourable_max_temperature,
+            p.favourable_min_humidity
+        FROM pathogenics p
+              JOIN pathogenic_cultures pc ON pc.pathogenic_id = p.id
+              JOIN cultures c ON c.id = pc.culture_id
+        WHERE ($1::bigint IS NULL OR p.id = $1)
+        ORDER BY p.id, c.id",
+      pathogenic_id
+    )
+    .fetch_all(&db.pool)
+    .await?,
+  )
+}
+
+/// Plantations of the pathogens' cultures that have an active station.
+pub(crate) async fn plantations(
+  db: &DataBase,
+  pathogenics: &[PathogenicCulture],
+) -> Result<Vec<Plantation>, CrawlerError> {
+  let culture_ids = pathogenics
+    .iter()
+    .map(|pathogenic| pathogenic.culture_id)
+    .collect::<Vec<i64>>();
+
+  Ok(
+    sqlx::query_as!(
+      Plantation,
+      r#"SELECT p.id,
+            u.id                               AS owner_id,
+            p.alias,
+            p.culture_id,
+            s.id                               AS "station_id!",
+            s.inmet_code                       AS "inmet_code!",
+            st_x(s.location::geometry)         AS station_latitude,
+            st_y(s.location::geometry)         AS station_longitude,
+            u.name                             AS owner_name
+        FROM plantations p
+              JOIN stations s ON s.id = p.station_id
+              LEFT JOIN LATERAL (SELECT user_id
+                                 FROM farm_members
+                                 WHERE farm_id = p.farm_id
+                                   AND role = 'owner'
+                                 ORDER BY create_date, id
+                                 LIMIT 1) m ON TRUE
+              JOIN users u ON u.id = COALESCE(m.user_id, p.user_id)
+        WHERE s.status = TRUE
+          AND s.inmet_code IS NOT NULL
+          AND p.delete_at IS NULL
+          AND p.culture_id = ANY($1)
+        ORDER BY s.id, p.create_date"#,
+      &culture_ids[..]
+    )
+    .fetch_all(&db.pool)
+    .await?,
+  )
+}
+
+/// Fungicides and treatments on `plantation_ids` still protecting them since
+/// `since`.
+pub(crate) async fn protections(
+  db: &DataBase,
+  plantation_ids: &[Uuid],
+  since: NaiveDate,
+) -> Result<Vec<Protection>, CrawlerError> {
+  Ok(
+    sqlx::query_as!(
+      Protection,
+      r#"SELECT id                    AS activity_id,
+              plantation_id,
+              pathogenic_id,
+              date                  AS start,
+              date + protection_days AS "end!"
+        FROM plantation_activities
+        WHERE delete_at IS NULL
+          AND kind IN ('fungicide', 'treatment')
+          AND protection_days > 0
+          AND plantation_id = ANY($1)
+          AND date + protection_days >= $2
+        ORDER BY date DESC"#,
+      plantation_ids,
+      since
+    )
+    .fetch_all(&db.pool)
+    .await?,
+  )
+}
+
+/// Latest activity protecting the plantation from the pathogen on `date`
+pub(crate) fn protection(
+  protections: &[Protection],
+  plantation: &Plantation,
+  pathogenic: &PathogenicCulture,
+  date: NaiveDate,
+) -> Option<Uuid> {
+  protections
+    .iter()
+    .find(|protection| {
+      protection.plantation_id == plantation.id
+        && protection
+          .pathogenic_id
+          .is_none_or(|id| id == pathogenic.id)
+        && protection.start <= date
+        && protection.end >= date
+    })
+    .map(|protection| protection.activity_id)
+}
+
+/// Saves the risk of the day the period ends, replacing the one of an earlier
+/// run of the same kind, on the open season of the plantation. Returns whether
+/// it was a new row.
+pub(crate) async fn save(
+  db: &DataBase,
+  plantation: &Plantation,
+  pathogenic: &PathogenicCulture,
+  kind: RiskKind,
+  risk: &Risk,
+  confidence: f64,
+  period: (NaiveDateTime, NaiveDateTime),
+) -> Result<bool, CrawlerError> {
+  Ok(
+    sqlx::query_scalar!(
+      r#"INSERT INTO plantation_risks (plantation_id, pathogenic_id, station_id, date, level, score,
+                              at_risk, hours, temperature_hours, humidity_hours,
+                              favourable_hours, mean_temperature, mean_humidity,
+                              favourable_min_temperature, favourable_max_temperature,
+                              favourable_min_humidity, confidence, period_start, period_end, kind,
+                              protected_by, season_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+                $21, (SELECT id FROM plantation_seasons WHERE plantation_id = $1 AND close_date IS NULL))
+        ON CONFLICT (plantation_id, pathogenic_id, date, kind) DO UPDATE
+            SET station_id                 = $3,
+                level                      = $5,
+                score                      = $6,
+                at_risk                    = $7,
+                hours                      = $8,
+                temperature_hours          = $9,
+                humidity_hours             = $10,
+                favourable_hours           = $11,
+                mean_temperature           = $12,
+                mean_humidity              = $13,
+                favourable_min_temperature = $14,
+                favourable_max_temperature = $15,
+                favourable_min_humidity    = $16,
+                confidence                 = $17,
+                period_start               = $18,
+                period_end                 = $19,
+                protected_by               = $21,
+                season_id                  = EXCLUDED.season_id,
+                update_date                = NOW()
+        RETURNING (xmax = 0) AS "inserted!""#,
+      plantation.id,
+      pathogenic.id,
+      plantation.station_id,
+      period.1.date(),
+      risk.level(),
+      risk.score(),
+      risk.at_risk(),
+      risk.hours,
+      risk.temperature_hours,
+      risk.humidity_hours,
+      risk.favourable_hours,
+      risk.mean_temperature,
+      risk.mean_humidity,
+      pathogenic.favourable_min_temperature,
+      pathogenic.favourable_max_temperature,
+      pathogenic.favourable_min_humidity,
+      confidence,
+      period.0,
+      period.1,
+      kind.as_str(),
+      risk.protected_by
+    )
+    .fetch_one(&db.pool)
+    .await?,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn risk(hours: i32, temperature_hours: i32, humidity_hours: i32, favourable_hours: i32) -> Risk {
+    Risk {
+      hours,
+      temperature_hours,
+      humidity_hours,
+      favourable_hours,
+      mean_temperature: None,
+      mean_humidity: None,
+      protected_by: None,
+    }
+  }
+
+  fn high() -> Risk {
+    risk(24, 18, 16, 14)
+  }
+
+  fn medium() -> Risk {
+    risk(24, 8, 20, 6)
+  }
+
+  fn low() -> Risk {
+    risk(24, 4, 20, 3)
+  }
+
+  fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+  }
+
+  fn plantation() -> Plantation {
+    Plantation {
+      id: Uuid::new_v4(),
+      owner_id: Uuid::new_v4(),
+      alias: None,
+      culture_id: 1,
+      station_id: 1,
+      inmet_code: "A801".to_string(),
+      station_latitude: None,
+      station_longitude: None,
+      owner_name: "Grower".to_string(),
+    }
+  }
+
+  fn pathogenic(id: i64) -> PathogenicCulture {
+    PathogenicCulture {
+      id,
+      name: "Rust".to_string(),
+      culture_id: 1,
+      culture_name: "Soy".to_string(),
+      favourable_min_temperature: 15.0,
+      favourable_max_temperature: 28.0,
+      favourable_min_humidity: 80.0,
+    }
+  }
+
+  #[test]
+  fn levels_follow_the_favourable_hours() {
+    assert_eq!(high().level(), "high");
+    assert_eq!(medium().level(), "medium");
+    assert_eq!(low().level(), "low");
+  }
+
+  #[test]
+  fn at_risk_needs_more_than_the_risk_hours_of_both_readings() {
+    assert!(!risk(24, 12, 20, 12).at_risk());
+    assert!(!risk(24, 20, 12, 12).at_risk());
+    assert!(risk(24, 13, 13, 13).at_risk());
+  }
+
+  #[test]
+  fn medium_starts_at_a_quarter_of_the_hours() {
+    assert_eq!(risk(24, 6, 20, 6).level(), "medium");
+    assert_eq!(risk(24, 5, 20, 5).level(), "low");
+    assert_eq!(risk(0, 0, 0, 0).level(), "low");
+  }
+
+  #[test]
+  fn a_protected_plantation_is_one_level_lower() {
+    let activity_id = Some(Uuid::new_v4());
+
+    assert_eq!(high().protected_by(activity_id).level(), "medium");
+    assert_eq!(medium().protected_by(activity_id).level(), "low");
+    assert_eq!(low().protected_by(activity_id).level(), "low");
+  }
+
+  #[test]
+  fn a_protected_plantation_is_still_at_risk_but_doesnt_alert() {
+    let protected = high().protected_by(Some(Uuid::new_v4()));
+
+    assert!(protected.at_risk());
+    assert!(!protected.alert());
+    assert!(!protected.alert_at("medium"));
+    assert!(high().alert());
+  }
+
+  #[test]
+  fn medium_risks_alert_only_when_asked() {
+    assert!(!medium().alert());
+    assert!(!medium().alert_at("high"));
+    assert!(medium().alert_at("medium"));
+    assert!(!low().alert_at("medium"));
+  }
+
+  #[test]
+  fn protection_covers_its_pathogen_and_days() {
+    let sprayed = plantation();
+    let activity_id = Uuid::new_v4();
+    let protections = vec![Protection {
+      activity_id,
+      plantation_id: sprayed.id,
+      pathogenic_id: Some(1),
+      start: date(10),
+      end: date(20),
+    }];
+
+    assert_eq!(
+      protection(&protections, &sprayed, &pathogenic(1), date(10)),
+      Some(activity_id)
+    );
+    assert_eq!(
+      protection(&protections, &sprayed, &pathogenic(1), date(20)),
+      Some(activity_id)
+    );
+    assert_eq!(protection(&protections, &sprayed, &pathogenic(1), date(21)), None);
+    assert_eq!(protection(&protections, &sprayed, &pathogenic(2), date(15)), None);
+    assert_eq!(protection(&protections, &plantation(), &pathogenic(1), date(15)), None);
+  }
+
+  #[test]
+  fn protection_without_a_pathogen_covers_every_one() {
+    let plantation = plantation();
+    let activity_id = Uuid::new_v4();
+    let protections = vec![Protection {
+      activity_id,
+      plantation_id: plantation.id,
+      pathogenic_id: None,
+      start: date(10),
+      end: date(20),
+    }];
+
+    assert_eq!(
+      protection(&protections, &plantation, &pathogenic(2), date(15)),
+      Some(activity_id)
+    );
+  }
+}
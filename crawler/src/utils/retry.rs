@@ -0,0 +1,32 @@
+use crate::error::CrawlerError;
+use std::{future::Future, time::Duration};
+
+/// Calls `f` up to `attempts` times while it fails with a retryable error,
+/// waiting 2s, 4s, 8s... between the calls.
+pub(crate) async fn with_backoff<T, F, Fut>(
+  description: &str,
+  attempts: u32,
+  mut f: F,
+) -> Result<T, CrawlerError>
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<T, CrawlerError>>,
+{
+  let mut attempt = 1;
+
+  loop {
+    match f().await {
+      Ok(value) => return Ok(value),
+      Err(e) if attempt < attempts && e.is_retryable() => {
+        println!(
+          "Error on {} (attempt {}/{}): {}",
+          description, attempt, attempts, e
+        );
+
+        tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+        attempt += 1;
+      }
+      Err(e) => return Err(e),
+    }
+  }
+}
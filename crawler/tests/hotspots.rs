@@ -0,0 +1,101 @@
+use crawler::hotspots::{self, Extent, Point};
+
+fn point(latitude: f64, longitude: f64) -> Point {
+  Point {
+    latitude,
+    longitude,
+  }
+}
+
+#[test]
+fn distance_is_great_circle() {
+  // A degree of latitude is about 111 km anywhere
+  let distance = hotspots::distance_km(point(-28.0, -52.0), point(-29.0, -52.0));
+
+  assert!((distance - 111.2).abs() < 0.5);
+  assert_eq!(
+    hotspots::distance_km(point(-28.0, -52.0), point(-28.0, -52.0)),
+    0.0
+  );
+}
+
+#[test]
+fn close_points_are_clustered_and_isolated_ones_are_noise() {
+  // Two groups about 100 km apart, and a lone report far from both
+  let points = vec![
+    point(-28.20, -52.40),
+    point(-28.22, -52.41),
+    point(-28.25, -52.38),
+    point(-29.10, -52.40),
+    point(-29.12, -52.42),
+    point(-29.11, -52.39),
+    point(-25.00, -50.00),
+  ];
+
+  let clusters = hotspots::dbscan(&points, 10.0, 3);
+
+  assert_eq!(clusters, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+}
+
+#[test]
+fn clusters_grow_through_their_core_points() {
+  // A chain of reports 6 km apart, the ends only reach their neighbour
+  let points = (0..6)
+    .map(|i| point(-28.0 - 0.054 * i as f64, -52.0))
+    .collect::<Vec<_>>();
+
+  assert_eq!(
+    hotspots::dbscan(&points, 7.0, 3),
+    vec![vec![0, 1, 2, 3, 4, 5]]
+  );
+  assert!(hotspots::dbscan(&points, 7.0, 4).is_empty());
+}
+
+#[test]
+fn extent_is_centroid_and_farthest_member() {
+  let points = vec![
+    point(-28.0, -52.0),
+    point(-28.2, -52.0),
+    point(-40.0, -40.0),
+  ];
+
+  let extent = Extent::new(&points, &[0, 1]).unwrap();
+
+  assert!((extent.centroid.latitude - -28.1).abs() < 1e-9);
+  assert!((extent.radius_km - 11.1).abs() < 0.1);
+  assert_eq!(Extent::new(&points, &[]), None);
+}
+
+#[test]
+fn path_follows_the_drift_of_the_hotspot() {
+  let extent = Extent {
+    centroid: point(-28.0, -52.0),
+    radius_km: 5.0,
+  };
+  // Moved 0.5° north since the last run, so it's expected 0.5° further
+  let previous = Some(point(-28.5, -52.0));
+  let ahead = point(-27.5, -52.0);
+  let behind = point(-28.6, -52.3);
+
+  assert!(extent.in_path(None, point(-28.1, -52.0), 10.0));
+  assert!(!extent.in_path(None, ahead, 10.0));
+  assert!(extent.in_path(previous, ahead, 10.0));
+  assert!(!extent.in_path(previous, behind, 10.0));
+}
+
+#[test]
+fn hotspots_are_matched_by_shared_occurrences() {
+  let previous = vec![vec![1, 2, 3], vec![4, 5, 6, 7]];
+
+  assert_eq!(hotspots::matching(&[5, 6, 8], &previous), Some(1));
+  assert_eq!(hotspots::matching(&[3, 4, 5], &previous), Some(1));
+  assert_eq!(hotspots::matching(&[9, 10, 11], &previous), None);
+}
+
+#[test]
+fn new_or_fast_growing_hotspots_are_emerging() {
+  assert!(hotspots::emerging(None, 3));
+  assert!(hotspots::emerging(Some(4), 6));
+  assert!(!hotspots::emerging(Some(4), 5));
+  assert!(!hotspots::emerging(Some(6), 4));
+}
@@ -0,0 +1,161 @@
+use chrono::NaiveDate;
+use crawler::parsers::{
+  inmet_catalog::parse_stations,
+  inmet_observations::{parse_stations_climate_data, HourlyReading},
+  table::{ParseError, SkippedRow},
+};
+
+#[test]
+fn catalog_parses_stations_by_header() {
+  let parsed = parse_stations(include_str!("fixtures/inmet_catalog.html")).unwrap();
+
+  assert_eq!(parsed.rows.len(), 2);
+
+  let passo_fundo = &parsed.rows[0];
+  assert_eq!(passo_fundo.city, "PASSO FUNDO");
+  assert_eq!(passo_fundo.uf, "RS");
+  assert_eq!(passo_fundo.situation, "Operante");
+  assert_eq!(passo_fundo.latitude, "-28,22666666");
+  assert_eq!(passo_fundo.longitude, "-52,40361111");
+  assert_eq!(passo_fundo.altitude.as_deref(), Some("684"));
+  assert_eq!(passo_fundo.installation_date.as_deref(), Some("2001-09-25"));
+  assert_eq!(passo_fundo.code, "A839");
+
+  let erechim = &parsed.rows[1];
+  assert_eq!(erechim.code, "A828");
+  assert_eq!(erechim.altitude, None);
+  assert_eq!(erechim.installation_date, None);
+}
+
+#[test]
+fn catalog_reports_skipped_rows() {
+  let parsed = parse_stations(include_str!("fixtures/inmet_catalog.html")).unwrap();
+
+  assert_eq!(
+    parsed.skipped,
+    vec![
+      SkippedRow {
+        row: 3,
+        reason: "expected 8 cells, found 4".to_string(),
+      },
+      SkippedRow {
+        row: 4,
+        reason: "empty code".to_string(),
+      },
+    ]
+  );
+}
+
+#[test]
+fn catalog_follows_column_order() {
+  let parsed = parse_stations(include_str!("fixtures/inmet_catalog_reordered.html")).unwrap();
+
+  assert!(parsed.skipped.is_empty());
+  assert_eq!(parsed.rows.len(), 1);
+  assert_eq!(parsed.rows[0].code, "A814");
+  assert_eq!(parsed.rows[0].city, "CAMPO BOM");
+  assert_eq!(parsed.rows[0].situation, "Operante");
+  assert_eq!(parsed.rows[0].altitude, None);
+}
+
+#[test]
+fn catalog_without_stations_table_is_an_error() {
+  let result = parse_stations(include_str!("fixtures/inmet_observations.html"));
+
+  assert!(matches!(result, Err(ParseError::TableNotFound(_))));
+}
+
+#[test]
+fn observations_parse_readings_by_header() {
+  let parsed =
+    parse_stations_climate_data(include_str!("fixtures/inmet_observations.html")).unwrap();
+
+  assert_eq!(parsed.rows.len(), 3);
+
+  let first = &parsed.rows[0];
+  assert_eq!(
+    first.date,
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap()
+  );
+  assert_eq!(
+    first.temperature,
+    HourlyReading {
+      instant: Some(18.4),
+      max: Some(19.1),
+      min: Some(18.3),
+    }
+  );
+  assert_eq!(first.humidity.instant, Some(93.0));
+  assert_eq!(first.dew_point.instant, Some(17.2));
+  assert_eq!(first.pressure.max, Some(931.7));
+  assert_eq!(first.wind_speed, Some(1.8));
+  assert_eq!(first.wind_direction, Some(94.0));
+  assert_eq!(first.wind_gust, Some(4.5));
+  assert_eq!(first.precipitation, Some(0.2));
+}
+
+#[test]
+fn observations_keep_empty_cells_as_missing() {
+  let parsed =
+    parse_stations_climate_data(include_str!("fixtures/inmet_observations.html")).unwrap();
+
+  // Radiation is empty at night, not zero
+  assert_eq!(parsed.rows[0].radiation, None);
+
+  assert_eq!(parsed.rows[1].temperature.instant, Some(18.0));
+  assert_eq!(parsed.rows[1].humidity, HourlyReading::default());
+  assert_eq!(parsed.rows[1].precipitation, None);
+
+  assert_eq!(parsed.rows[2].temperature, HourlyReading::default());
+  assert_eq!(parsed.rows[2].wind_speed, None);
+}
+
+#[test]
+fn observations_report_skipped_rows() {
+  let parsed =
+    parse_stations_climate_data(include_str!("fixtures/inmet_observations.html")).unwrap();
+
+  assert_eq!(
+    parsed.skipped,
+    vec![
+      SkippedRow {
+        row: 4,
+        reason: "expected 19 cells, found 3".to_string(),
+      },
+      SkippedRow {
+        row: 5,
+        reason: "invalid date --/--/---- 0400".to_string(),
+      },
+    ]
+  );
+}
+
+#[test]
+fn observations_follow_column_order() {
+  let parsed =
+    parse_stations_climate_data(include_str!("fixtures/inmet_observations_reordered.html"))
+      .unwrap();
+
+  assert!(parsed.skipped.is_empty());
+  assert_eq!(parsed.rows.len(), 1);
+  assert_eq!(parsed.rows[0].temperature.instant, Some(22.5));
+  assert_eq!(parsed.rows[0].humidity.instant, Some(71.0));
+  assert_eq!(parsed.rows[0].precipitation, Some(1.4));
+  assert_eq!(parsed.rows[0].dew_point, HourlyReading::default());
+  assert_eq!(parsed.rows[0].wind_speed, None);
+}
+
+#[test]
+fn observations_without_required_column_are_an_error() {
+  let result = parse_stations_climate_data(include_str!(
+    "fixtures/inmet_observations_without_humidity.html"
+  ));
+
+  assert_eq!(
+    result.unwrap_err(),
+    ParseError::ColumnNotFound("umidade inst".to_string())
+  );
+}
@@ -0,0 +1,100 @@
+use chrono::NaiveDate;
+use crawler::forecast::{open_meteo::OpenMeteo, ForecastError, ForecastProvider};
+use std::{
+  io::{Read, Write},
+  net::TcpListener,
+  sync::mpsc,
+};
+
+/// Answers one request with `body`, sending back the request line
+fn stub(body: &'static str) -> (String, mpsc::Receiver<String>) {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", listener.local_addr().unwrap());
+  let (sender, receiver) = mpsc::channel();
+
+  std::thread::spawn(move || {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+      let read = stream.read(&mut buffer).unwrap();
+      if read == 0 {
+        break;
+      }
+      request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    // Not every test checks the request
+    let _ = sender.send(request.lines().next().unwrap_or("").to_string());
+
+    write!(
+      stream,
+      "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+      body.len(),
+      body
+    )
+    .unwrap();
+  });
+
+  (url, receiver)
+}
+
+#[tokio::test]
+async fn reads_the_hourly_series() {
+  let (url, request) = stub(include_str!("fixtures/open_meteo_forecast.json"));
+
+  let hours = OpenMeteo::new(format!("{}/", url))
+    .hourly(-28.25, -52.375, 2)
+    .await
+    .unwrap();
+
+  let request = request.recv().unwrap();
+  assert!(request.starts_with("GET /v1/forecast?"), "{}", request);
+  assert!(request.contains("forecast_days=3"), "{}", request);
+  assert!(request.contains("wind_speed_unit=ms"), "{}", request);
+
+  assert_eq!(hours.len(), 3);
+  assert_eq!(
+    hours[1].date,
+    NaiveDate::from_ymd_opt(2026, 10, 19)
+      .unwrap()
+      .and_hms_opt(1, 0, 0)
+      .unwrap()
+  );
+  assert_eq!(hours[0].temperature, Some(18.4));
+  assert_eq!(hours[0].humidity, Some(88.0));
+  assert_eq!(hours[1].temperature, None);
+  assert_eq!(hours[1].precipitation, Some(0.4));
+}
+
+#[tokio::test]
+async fn missing_series_are_empty() {
+  let (url, _) = stub(include_str!("fixtures/open_meteo_forecast.json"));
+
+  let hours = OpenMeteo::new(url)
+    .hourly(-28.25, -52.375, 2)
+    .await
+    .unwrap();
+
+  // Shorter than `time`
+  assert_eq!(hours[2].precipitation, None);
+  // Not in the response at all
+  assert!(hours
+    .iter()
+    .all(|hour| hour.precipitation_probability.is_none() && hour.wind_speed.is_none()));
+}
+
+#[tokio::test]
+async fn rejects_a_malformed_time() {
+  let (url, _) = stub(include_str!("fixtures/open_meteo_forecast_bad_time.json"));
+
+  let result = OpenMeteo::new(url).hourly(-28.25, -52.375, 2).await;
+
+  assert!(
+    matches!(&result, Err(ForecastError::Parse(e)) if e.contains("19/10/2026 01:00")),
+    "{:?}",
+    result
+  );
+}
@@ -0,0 +1,150 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use crawler::{
+  parsers::inmet_observations::{HourlyReading, InmetStationData},
+  quality::{Climate, Flag, Series, HUMIDITY, TEMPERATURE},
+};
+
+fn hour(hour: u32) -> NaiveDateTime {
+  NaiveDate::from_ymd_opt(2026, 10, 18)
+    .unwrap()
+    .and_hms_opt(hour, 0, 0)
+    .unwrap()
+}
+
+fn row(hour_of_day: u32, temperature: Option<f64>, humidity: Option<f64>) -> InmetStationData {
+  InmetStationData {
+    date: hour(hour_of_day),
+    temperature: HourlyReading {
+      instant: temperature,
+      ..Default::default()
+    },
+    humidity: HourlyReading {
+      instant: humidity,
+      ..Default::default()
+    },
+    dew_point: HourlyReading::default(),
+    pressure: HourlyReading::default(),
+    wind_speed: None,
+    wind_direction: None,
+    wind_gust: None,
+    radiation: None,
+    precipitation: None,
+  }
+}
+
+fn temperatures(rows: &[InmetStationData]) -> Series {
+  Series::new(rows, |row| row.temperature.instant, TEMPERATURE)
+}
+
+#[test]
+fn missing_hours_are_flagged_not_zero() {
+  // 01h has an empty cell and 02h isn't on the table at all
+  let rows = vec![
+    row(0, Some(18.0), Some(90.0)),
+    row(1, None, Some(91.0)),
+    row(3, Some(19.0), Some(92.0)),
+  ];
+
+  let series = temperatures(&rows);
+
+  assert_eq!(series.measurements.len(), 4);
+  assert_eq!(series.count(Flag::Missing), 2);
+  assert_eq!(series.value_at(hour(1)), None);
+  assert_eq!(series.confidence(), 0.5);
+}
+
+#[test]
+fn out_of_range_values_are_dropped() {
+  let rows = vec![
+    row(0, Some(18.0), Some(120.0)),
+    row(1, Some(-40.0), Some(95.0)),
+  ];
+
+  let temperature = temperatures(&rows);
+  let humidity = Series::new(&rows, |row| row.humidity.instant, HUMIDITY);
+
+  assert_eq!(temperature.value_at(hour(1)), None);
+  assert_eq!(temperature.count(Flag::OutOfRange), 1);
+  assert_eq!(humidity.value_at(hour(0)), None);
+  assert_eq!(humidity.count(Flag::OutOfRange), 1);
+}
+
+#[test]
+fn stuck_sensors_are_flagged() {
+  let rows = (0..8)
+    .map(|hour_of_day| row(hour_of_day, Some(21.3), Some(100.0)))
+    .collect::<Vec<_>>();
+
+  let temperature = temperatures(&rows);
+  let humidity = Series::new(&rows, |row| row.humidity.instant, HUMIDITY);
+
+  assert_eq!(temperature.count(Flag::Stuck), 8);
+  assert_eq!(temperature.confidence(), 0.0);
+
+  // Saturated humidity is a real reading
+  assert_eq!(humidity.count(Flag::Stuck), 0);
+}
+
+#[test]
+fn short_gaps_are_interpolated() {
+  let rows = vec![
+    row(0, Some(18.0), Some(90.0)),
+    row(1, None, Some(90.0)),
+    row(2, None, Some(90.0)),
+    row(3, Some(21.0), Some(90.0)),
+  ];
+
+  let mut series = temperatures(&rows);
+  series.interpolate(2);
+
+  assert_eq!(series.value_at(hour(1)), Some(19.0));
+  assert_eq!(series.value_at(hour(2)), Some(20.0));
+  assert_eq!(series.count(Flag::Interpolated), 2);
+  assert_eq!(series.confidence(), 0.5);
+}
+
+#[test]
+fn long_gaps_are_kept_missing() {
+  let rows = vec![
+    row(0, Some(18.0), Some(90.0)),
+    row(5, Some(21.0), Some(90.0)),
+  ];
+
+  let mut series = temperatures(&rows);
+  series.interpolate(3);
+
+  assert_eq!(series.count(Flag::Missing), 4);
+  assert_eq!(series.count(Flag::Interpolated), 0);
+}
+
+#[test]
+fn climate_hours_need_both_readings() {
+  let rows = vec![
+    row(0, Some(18.0), Some(90.0)),
+    row(1, Some(18.5), None),
+    row(2, Some(19.0), None),
+    row(3, Some(19.5), None),
+    row(4, Some(20.0), None),
+    row(5, Some(20.5), Some(95.0)),
+  ];
+
+  let climate = Climate::new(&rows, 3);
+
+  assert_eq!(
+    climate.hours().collect::<Vec<_>>(),
+    vec![(hour(0), 18.0, 90.0), (hour(5), 20.5, 95.0)]
+  );
+  assert_eq!(climate.confidence(), 2.0 / 6.0);
+}
+
+#[test]
+fn forecasts_are_not_flagged_as_stuck() {
+  let hours = (0..12).map(|hour_of_day| (hour(hour_of_day), Some(20.0), Some(95.0)));
+
+  let observed = Climate::from_hours(hours.clone(), 3);
+  let forecast = Climate::from_forecast(hours, 3);
+
+  assert!(observed.temperature.count(Flag::Stuck) > 0);
+  assert_eq!(forecast.temperature.count(Flag::Stuck), 0);
+  assert_eq!(forecast.confidence(), 1.0);
+}
@@ -0,0 +1,22 @@
+//! Conditions favourable to a pathogen, the same on the risk calculations and
+//! on the climate shown with an occurrence. The bounds are exclusive: a reading
+//! exactly on the pathogen's limit isn't favourable. A crate of its own so the
+//! app shares it with the crawler without depending on the crawler.
+
+/// Favourable range of a pathogen
+#[derive(Debug, Clone, Copy)]
+pub struct Favourable {
+  pub min_temperature: f64,
+  pub max_temperature: f64,
+  pub min_humidity: f64,
+}
+
+impl Favourable {
+  pub fn temperature(&self, temperature: f64) -> bool {
+    temperature > self.min_temperature && temperature < self.max_temperature
+  }
+
+  pub fn humidity(&self, humidity: f64) -> bool {
+    humidity > self.min_humidity
+  }
+}
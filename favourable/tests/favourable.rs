@@ -0,0 +1,27 @@
+use favourable::Favourable;
+
+const RUST: Favourable = Favourable {
+  min_temperature: 15.0,
+  max_temperature: 28.0,
+  min_humidity: 80.0,
+};
+
+#[test]
+fn inside_the_range_is_favourable() {
+  assert!(RUST.temperature(20.0));
+  assert!(RUST.humidity(90.0));
+}
+
+#[test]
+fn the_bounds_are_exclusive() {
+  assert!(!RUST.temperature(15.0));
+  assert!(!RUST.temperature(28.0));
+  assert!(!RUST.humidity(80.0));
+}
+
+#[test]
+fn outside_the_range_isnt_favourable() {
+  assert!(!RUST.temperature(10.0));
+  assert!(!RUST.temperature(30.0));
+  assert!(!RUST.humidity(60.0));
+}
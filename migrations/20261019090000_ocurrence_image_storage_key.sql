@@ -0,0 +1,4 @@
+-- Images are now referenced by their storage key, the public URL is signed on demand
+UPDATE plantation_pathogenic_occurrences
+SET image = regexp_replace(image, '^/images/', '')
+WHERE image LIKE '/images/%';
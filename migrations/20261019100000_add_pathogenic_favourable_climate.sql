@@ -0,0 +1,4 @@
+ALTER TABLE pathogenics
+    ADD favourable_min_temperature float8 NOT NULL DEFAULT 17,
+    ADD favourable_max_temperature float8 NOT NULL DEFAULT 24,
+    ADD favourable_min_humidity    float8 NOT NULL DEFAULT 90;
@@ -0,0 +1,14 @@
+CREATE TABLE ocurrence_climate_tasks
+(
+    id           bigserial NOT NULL
+        CONSTRAINT ocurrence_climate_tasks_pk
+            PRIMARY KEY,
+    ocurrence_id uuid      NOT NULL
+        CONSTRAINT ocurrence_climate_tasks_ocurrence_id_key
+            UNIQUE,
+    attempts     int       NOT NULL DEFAULT 0,
+    last_error   text      NULL,
+    done_date    timestamp NULL,
+    create_date  timestamp NOT NULL DEFAULT NOW(),
+    update_date  timestamp NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,27 @@
+CREATE TABLE crawl_runs
+(
+    id            bigserial NOT NULL
+        CONSTRAINT crawl_runs_pk
+            PRIMARY KEY,
+    script        varchar   NOT NULL,
+    args          varchar   NULL,
+    status        varchar   NOT NULL DEFAULT 'running',
+    rows_inserted bigint    NOT NULL DEFAULT 0,
+    rows_updated  bigint    NOT NULL DEFAULT 0,
+    error         text      NULL,
+    start_date    timestamp NOT NULL DEFAULT NOW(),
+    end_date      timestamp NULL
+);
+
+CREATE INDEX crawl_runs_start_date_idx
+    ON crawl_runs (start_date DESC);
+
+CREATE INDEX crawl_runs_script_idx
+    ON crawl_runs (script);
+
+ALTER TABLE stations
+    ADD last_fetch_date timestamp NULL,
+    ADD last_data_date  timestamp NULL;
+
+ALTER TABLE users
+    ADD role varchar NOT NULL DEFAULT 'user';
@@ -0,0 +1,2 @@
+ALTER TABLE crawl_runs
+    ADD rows_skipped bigint NOT NULL DEFAULT 0;
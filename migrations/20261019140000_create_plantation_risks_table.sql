@@ -0,0 +1,30 @@
+CREATE TABLE plantation_risks
+(
+    id                         bigserial NOT NULL
+        CONSTRAINT plantation_risks_pk
+            PRIMARY KEY,
+    plantation_id              uuid      NOT NULL,
+    pathogenic_id              bigint    NOT NULL,
+    station_id                 bigint    NOT NULL,
+    date                       date      NOT NULL,
+    kind                       varchar   NOT NULL,
+    level                      varchar   NOT NULL,
+    score                      float8    NOT NULL,
+    at_risk                    boolean   NOT NULL,
+    hours                      int       NOT NULL,
+    temperature_hours          int       NOT NULL,
+    humidity_hours             int       NOT NULL,
+    favourable_hours           int       NOT NULL,
+    mean_temperature           float8    NULL,
+    mean_humidity              float8    NULL,
+    favourable_min_temperature float8    NULL,
+    favourable_max_temperature float8    NULL,
+    favourable_min_humidity    float8    NULL,
+    confidence                 float8    NOT NULL,
+    period_start               timestamp NOT NULL,
+    period_end                 timestamp NOT NULL,
+    create_date                timestamp NOT NULL DEFAULT NOW(),
+    update_date                timestamp NOT NULL DEFAULT NOW(),
+    CONSTRAINT plantation_risks_plantation_id_pathogenic_id_date_kind_key
+        UNIQUE (plantation_id, pathogenic_id, date, kind)
+);
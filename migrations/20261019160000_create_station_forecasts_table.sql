@@ -0,0 +1,17 @@
+CREATE TABLE station_forecasts
+(
+    id                        bigserial NOT NULL
+        CONSTRAINT station_forecasts_pk
+            PRIMARY KEY,
+    station_id                bigint    NOT NULL,
+    provider                  varchar   NOT NULL,
+    date                      timestamp NOT NULL,
+    temperature               float8    NULL,
+    humidity                  float8    NULL,
+    precipitation             float8    NULL,
+    precipitation_probability float8    NULL,
+    wind_speed                float8    NULL,
+    fetch_date                timestamp NOT NULL DEFAULT NOW(),
+    CONSTRAINT station_forecasts_station_id_provider_date_key
+        UNIQUE (station_id, provider, date)
+);
@@ -0,0 +1,26 @@
+CREATE TABLE plantation_activities
+(
+    id              uuid      NOT NULL
+        CONSTRAINT plantation_activities_pk
+            PRIMARY KEY,
+    plantation_id   uuid      NOT NULL,
+    pathogenic_id   bigint    NULL,
+    kind            varchar   NOT NULL,
+    date            date      NOT NULL,
+    product         varchar   NULL,
+    dose            float8    NULL,
+    dose_unit       varchar   NULL,
+    area            float8    NULL,
+    protection_days int       NULL,
+    notes           text      NULL,
+    operator        varchar   NULL,
+    create_date     timestamp NOT NULL DEFAULT NOW(),
+    update_date     timestamp NULL,
+    delete_at       timestamp NULL
+);
+
+CREATE INDEX plantation_activities_plantation_id_date_idx
+    ON plantation_activities (plantation_id, date DESC);
+
+ALTER TABLE plantation_risks
+    ADD protected_by uuid NULL;
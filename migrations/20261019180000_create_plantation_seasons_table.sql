@@ -0,0 +1,62 @@
+CREATE TABLE plantation_seasons
+(
+    id                    uuid      NOT NULL
+        CONSTRAINT plantation_seasons_pk
+            PRIMARY KEY,
+    plantation_id         uuid      NOT NULL,
+    culture_id            bigint    NOT NULL,
+    planting_date         timestamp NOT NULL,
+    expected_harvest_date date      NULL,
+    harvest_date          date      NULL,
+    harvest_yield         float8    NULL,
+    notes                 text      NULL,
+    create_date           timestamp NOT NULL DEFAULT NOW(),
+    update_date           timestamp NULL,
+    close_date            timestamp NULL
+);
+
+CREATE INDEX plantation_seasons_plantation_id_idx
+    ON plantation_seasons (plantation_id, planting_date DESC);
+
+-- A plantation has a single season open at a time
+CREATE UNIQUE INDEX plantation_seasons_plantation_id_active_key
+    ON plantation_seasons (plantation_id)
+    WHERE close_date IS NULL;
+
+ALTER TABLE plantation_pathogenic_occurrences
+    ADD season_id uuid NULL;
+
+ALTER TABLE plantation_activities
+    ADD season_id uuid NULL;
+
+ALTER TABLE plantation_risks
+    ADD season_id uuid NULL;
+
+CREATE INDEX plantation_pathogenic_occurrences_season_id_idx
+    ON plantation_pathogenic_occurrences (season_id);
+
+CREATE INDEX plantation_activities_season_id_idx
+    ON plantation_activities (season_id);
+
+CREATE INDEX plantation_risks_season_id_idx
+    ON plantation_risks (season_id);
+
+-- Every existing plantation is on its first season
+INSERT INTO plantation_seasons (id, plantation_id, culture_id, planting_date, create_date)
+SELECT gen_random_uuid(), id, culture_id, planting_date, create_date
+FROM plantations;
+
+UPDATE plantation_pathogenic_occurrences o
+SET season_id = s.id
+FROM plantation_seasons s
+WHERE s.plantation_id = o.plantation_id;
+
+UPDATE plantation_activities a
+SET season_id = s.id
+FROM plantation_seasons s
+WHERE s.plantation_id = a.plantation_id;
+
+UPDATE plantation_risks r
+SET season_id = s.id
+FROM plantation_seasons s
+WHERE s.plantation_id = r.plantation_id;
@@ -0,0 +1,70 @@
+CREATE TABLE farms
+(
+    id          uuid      NOT NULL
+        CONSTRAINT farms_pk
+            PRIMARY KEY,
+    name        varchar   NOT NULL,
+    created_by  uuid      NOT NULL,
+    create_date timestamp NOT NULL DEFAULT NOW(),
+    update_date timestamp NULL,
+    delete_at   timestamp NULL
+);
+
+CREATE TABLE farm_members
+(
+    id          bigserial NOT NULL
+        CONSTRAINT farm_members_pk
+            PRIMARY KEY,
+    farm_id     uuid      NOT NULL,
+    user_id     uuid      NOT NULL,
+    role        varchar   NOT NULL,
+    create_date timestamp NOT NULL DEFAULT NOW(),
+    update_date timestamp NULL,
+    CONSTRAINT farm_members_farm_id_user_id_key
+        UNIQUE (farm_id, user_id)
+);
+
+CREATE INDEX farm_members_user_id_idx
+    ON farm_members (user_id);
+
+CREATE TABLE farm_invitations
+(
+    id          uuid      NOT NULL
+        CONSTRAINT farm_invitations_pk
+            PRIMARY KEY,
+    farm_id     uuid      NOT NULL,
+    email       varchar   NOT NULL,
+    role        varchar   NOT NULL,
+    token       varchar   NOT NULL
+        CONSTRAINT farm_invitations_token_key
+            UNIQUE,
+    invited_by  uuid      NOT NULL,
+    create_date timestamp NOT NULL DEFAULT NOW(),
+    expire_date timestamp NOT NULL,
+    accept_date timestamp NULL,
+    revoke_date timestamp NULL
+);
+
+CREATE INDEX farm_invitations_email_idx
+    ON farm_invitations (email);
+
+ALTER TABLE plantations
+    ADD farm_id uuid NULL;
+
+CREATE INDEX plantations_farm_id_idx
+    ON plantations (farm_id);
+
+-- Every grower with plantations gets a farm of their own holding them
+INSERT INTO farms (id, name, created_by)
+SELECT gen_random_uuid(), 'Fazenda de ' || u.name, u.id
+FROM users u
+WHERE EXISTS(SELECT 1 FROM plantations p WHERE p.user_id = u.id);
+
+INSERT INTO farm_members (farm_id, user_id, role)
+SELECT id, created_by, 'owner'
+FROM farms;
+
+UPDATE plantations p
+SET farm_id = f.id
+FROM farms f
+WHERE f.created_by = p.user_id;
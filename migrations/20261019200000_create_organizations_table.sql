@@ -0,0 +1,47 @@
+CREATE TABLE organizations
+(
+    id          uuid      NOT NULL
+        CONSTRAINT organizations_pk
+            PRIMARY KEY,
+    name        varchar   NOT NULL,
+    code        varchar   NOT NULL
+        CONSTRAINT organizations_code_key
+            UNIQUE,
+    created_by  uuid      NOT NULL,
+    create_date timestamp NOT NULL DEFAULT NOW(),
+    update_date timestamp NULL,
+    delete_at   timestamp NULL
+);
+
+CREATE TABLE organization_members
+(
+    id              bigserial NOT NULL
+        CONSTRAINT organization_members_pk
+            PRIMARY KEY,
+    organization_id uuid      NOT NULL,
+    user_id         uuid      NOT NULL,
+    role            varchar   NOT NULL,
+    create_date     timestamp NOT NULL DEFAULT NOW(),
+    update_date     timestamp NULL,
+    CONSTRAINT organization_members_organization_id_user_id_key
+        UNIQUE (organization_id, user_id)
+);
+
+CREATE INDEX organization_members_user_id_idx
+    ON organization_members (user_id);
+
+-- A policy without pathogen applies to the pathogens without one of their own
+CREATE TABLE organization_alert_policies
+(
+    id                 bigserial NOT NULL
+        CONSTRAINT organization_alert_policies_pk
+            PRIMARY KEY,
+    organization_id    uuid      NOT NULL,
+    pathogenic_id      bigint    NULL,
+    min_level          varchar   NOT NULL,
+    notify_agronomists bool      NOT NULL DEFAULT FALSE,
+    create_date        timestamp NOT NULL DEFAULT NOW(),
+    update_date        timestamp NULL,
+    CONSTRAINT organization_alert_policies_organization_id_pathogenic_id_key
+        UNIQUE NULLS NOT DISTINCT (organization_id, pathogenic_id)
+);
@@ -0,0 +1,30 @@
+ALTER TABLE plantation_pathogenic_occurrences
+    ADD status          varchar   NOT NULL DEFAULT 'reported',
+    ADD review_date     timestamp NULL,
+    ADD notify_date     timestamp NULL,
+    -- Status the neighbours were last told of
+    ADD notified_status varchar   NULL;
+
+CREATE INDEX plantation_pathogenic_occurrences_status_idx
+    ON plantation_pathogenic_occurrences (status);
+
+CREATE TABLE plantation_pathogenic_occurrence_reviews
+(
+    id            bigserial NOT NULL
+        CONSTRAINT plantation_pathogenic_occurrence_reviews_pk
+            PRIMARY KEY,
+    occurrence_id uuid      NOT NULL,
+    reviewer_id   uuid      NOT NULL,
+    status        varchar   NOT NULL,
+    comment       varchar   NULL,
+    create_date   timestamp NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX plantation_pathogenic_occurrence_reviews_occurrence_id_idx
+    ON plantation_pathogenic_occurrence_reviews (occurrence_id);
+
+-- Reports from before the review already reached the neighbours
+UPDATE plantation_pathogenic_occurrences
+SET status          = 'confirmed',
+    notify_date     = create_date,
+    notified_status = 'confirmed';
@@ -0,0 +1,51 @@
+-- Clusters of recent confirmed occurrences of a pathogen, followed between the
+-- runs of the hotspots script by the occurrences they share
+CREATE TABLE hotspots
+(
+    id             bigserial NOT NULL
+        CONSTRAINT hotspots_pk
+            PRIMARY KEY,
+    pathogenic_id  bigint    NOT NULL,
+    status         varchar   NOT NULL DEFAULT 'active',
+    location       point     NOT NULL,
+    radius_km      float8    NOT NULL,
+    size           int       NOT NULL,
+    growth         int       NOT NULL DEFAULT 0,
+    emerging       boolean   NOT NULL DEFAULT FALSE,
+    occurrence_ids uuid[]    NOT NULL,
+    create_date    timestamp NOT NULL DEFAULT NOW(),
+    update_date    timestamp NOT NULL DEFAULT NOW(),
+    close_date     timestamp NULL
+);
+
+CREATE INDEX hotspots_pathogenic_id_status_idx
+    ON hotspots (pathogenic_id, status);
+
+CREATE TABLE hotspot_snapshots
+(
+    id          bigserial NOT NULL
+        CONSTRAINT hotspot_snapshots_pk
+            PRIMARY KEY,
+    hotspot_id  bigint    NOT NULL,
+    location    point     NOT NULL,
+    radius_km   float8    NOT NULL,
+    size        int       NOT NULL,
+    create_date timestamp NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX hotspot_snapshots_hotspot_id_idx
+    ON hotspot_snapshots (hotspot_id, create_date);
+
+-- A plantation is alerted once per hotspot, when the hotspot first reaches it
+CREATE TABLE hotspot_alerts
+(
+    id            bigserial NOT NULL
+        CONSTRAINT hotspot_alerts_pk
+            PRIMARY KEY,
+    hotspot_id    bigint    NOT NULL,
+    plantation_id uuid      NOT NULL,
+    user_id       uuid      NOT NULL,
+    create_date   timestamp NOT NULL DEFAULT NOW(),
+    CONSTRAINT hotspot_alerts_hotspot_id_plantation_id_key
+        UNIQUE (hotspot_id, plantation_id)
+);
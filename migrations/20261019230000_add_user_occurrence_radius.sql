@@ -0,0 +1,4 @@
+-- Radius of the regional occurrences the user sees and is notified of, the
+-- OCCURRENCE_RADIUS_KM default when empty
+ALTER TABLE users
+    ADD occurrence_radius_km float8 NULL;
@@ -0,0 +1,3 @@
+-- Plantations inside a bounding box, for the map tiles
+CREATE INDEX plantations_location_idx
+    ON plantations USING gist (location);
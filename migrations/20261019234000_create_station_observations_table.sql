@@ -0,0 +1,17 @@
+-- Hourly readings of the INMET stations, saved by the crawler when it fetches
+-- the station tables. Dates are in UTC.
+CREATE TABLE station_observations
+(
+    id            bigserial NOT NULL
+        CONSTRAINT station_observations_pk
+            PRIMARY KEY,
+    station_id    bigint    NOT NULL,
+    date          timestamp NOT NULL,
+    temperature   float8    NULL,
+    humidity      float8    NULL,
+    precipitation float8    NULL,
+    wind_speed    float8    NULL,
+    fetch_date    timestamp NOT NULL DEFAULT NOW(),
+    CONSTRAINT station_observations_station_id_date_key
+        UNIQUE (station_id, date)
+);